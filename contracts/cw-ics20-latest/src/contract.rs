@@ -1,36 +1,67 @@
+use std::collections::HashSet;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_json, to_json_binary, wasm_execute, Addr, Binary, CosmosMsg, Deps, DepsMut, Empty, Env,
-    IbcEndpoint, IbcQuery, MessageInfo, Order, PortIdResponse, Response, StdError, StdResult,
-    Storage, Timestamp, Uint128,
+    attr, coins, from_json, to_json_binary, wasm_execute, Addr, Api, BankMsg, Binary, CosmosMsg,
+    Deps, DepsMut, Empty, Env, IbcEndpoint, IbcQuery, MessageInfo, Order, PortIdResponse,
+    QuerierWrapper, Response, StdError, StdResult, Storage, Timestamp, Uint128,
 };
 use cw2::set_contract_version;
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
 use cw20_ics20_msg::converter::ConverterController;
-use cw20_ics20_msg::helper::parse_ibc_wasm_port_id;
+use cw20_ics20_msg::helper::{parse_asset_info_denom, parse_ibc_wasm_port_id};
 use cw_storage_plus::Bound;
 use oraiswap::asset::AssetInfo;
 use oraiswap::router::RouterController;
+use token_bindings::Metadata;
 
 use crate::error::ContractError;
-use crate::ibc::{build_ibc_send_packet, parse_voucher_denom, process_deduct_fee};
+use crate::ibc::{
+    build_fee_msgs, build_ibc_send_packet, parse_ibc_channel_without_sanity_checks,
+    parse_ibc_info_without_sanity_checks, parse_voucher_denom, process_deduct_fee,
+    simulate_receive, RelayerFeeMode, ICS20_VERSION,
+};
 use crate::ibc_hooks::ibc_hooks_receive;
 use crate::msg::{
-    AllowedResponse, ChannelResponse, ChannelWithKeyResponse, ConfigResponse, ExecuteMsg, InitMsg,
-    ListAllowedResponse, ListChannelsResponse, ListMappingResponse, MigrateMsg, PairQuery,
-    PortResponse, QueryMsg, RegisterDenomMsg, RelayerFeeResponse,
+    AllowedResponse, ChannelBalance, ChannelBalanceAtResponse, ChannelBalanceOverrideResponse,
+    ChannelMappingResponse, ChannelResponse, ChannelWithKeyResponse, ConfigResponse,
+    DenomTraceResponse, ExecuteMsg, ExportMappingPairsResponse, FeeEpochResponse,
+    FeeExemptionResponse, FeeRevenue, FeesResponse, InitMsg, ListAllowedResponse,
+    ListChannelBalanceOverridesResponse, ListChannelBalancesResponse, ListChannelMappingsResponse,
+    ListChannelsResponse, ListDenomTracesResponse, ListFeeExemptionsResponse, ListFeeStatsResponse,
+    ListMappingMigrationsResponse, ListMappingResponse, ListPendingChannelBalanceOverridesResponse,
+    ListTokenFactoryDenomsResponse, MappingMigrationResponse, MappingPairState, MigrateMsg,
+    PairQuery, PendingChannelBalanceOverrideResponse, PortResponse, QueryMsg, RegisterDenomMsg,
+    RelayerFeeResponse, ScheduledFeeChangeResponse, TokenFactoryDenomResponse,
 };
-use crate::query_helper::get_mappings_from_asset_info;
+use crate::query_helper::{get_channel_utilization, get_mappings_from_asset_info, get_solvency};
 use crate::state::{
-    get_key_ics20_ibc_denom, ics20_denoms, increase_channel_balance, override_channel_balance,
-    reduce_channel_balance, Config, ADMIN, ALLOW_LIST, CHANNEL_INFO, CHANNEL_REVERSE_STATE, CONFIG,
-    RELAYER_FEE, REPLY_ARGS, SINGLE_STEP_REPLY_ARGS, TOKEN_FEE,
+    apply_fee_change, apply_scheduled_fee_changes, assert_fee_schedule, assert_mapping_not_halted,
+    assert_mapping_status, assert_outstanding_cap, assert_transfer_amount,
+    channel_balance_at_height, due_fee_changes, get_key_ics20_ibc_denom, get_relayer_fee_pricing,
+    get_token_fee, ics20_denoms, increase_channel_balance, list_token_fees,
+    migrate_legacy_token_fees, override_channel_balance, record_fee_revenue,
+    reduce_channel_balance, save_denom_trace, save_token_fee, update_channel_stats,
+    ChannelBalanceOverride, Config, FeeChange, FeeType, MappingMigration,
+    PendingChannelBalanceOverride, RelayerFeePriceSource, RelayerFeePricing, ScheduledFeeChange,
+    ADMIN, ALLOW_LIST, CHANNEL_BALANCE_OVERRIDES, CHANNEL_BALANCE_OVERRIDE_COUNT, CHANNEL_INFO,
+    CHANNEL_RELAYER_FEE, CHANNEL_REVERSE_STATE, CHANNEL_STATS, CONFIG, DENOM_TRACES,
+    EPOCH_FEE_REVENUE, FEE_EPOCH_DURATION, FEE_EXEMPTIONS, FEE_REVENUE, HALTED_MAPPINGS,
+    MAPPING_MIGRATIONS, OVERRIDE_MAX_DELTA, PENDING_CHANNEL_BALANCE_OVERRIDES,
+    PENDING_CHANNEL_BALANCE_OVERRIDE_COUNT, RELAYER_FEE, RELAYER_FEE_DEPOSITS, RELAYER_FEE_PRICING,
+    REPLY_ARGS, SCHEDULED_FEE_CHANGES, SCHEDULED_FEE_CHANGE_COUNT, SCHEDULED_FEE_CHANGE_TIMES,
+    SINGLE_STEP_REPLY_ARGS, TOKEN_FACTORY_DENOMS,
+};
+use cw20_ics20_msg::amount::{
+    convert_local_to_remote, convert_remote_to_local, decimals_convertible, Amount,
+};
+use cw20_ics20_msg::msg::{AllowedInfo, DeletePairMsg, DenomTrace, TransferBackMsg, UpdatePairMsg};
+use cw20_ics20_msg::state::{
+    AllowInfo, ChannelMetadata, ChannelRelayerFee, FeeDirection, FeeExemption, FeeRecipient,
+    FeeShare, MappingMetadata, MappingStatus, RelayerFee, ReplyArgs, TokenFee, MAX_BPS,
 };
-use cw20_ics20_msg::amount::{convert_local_to_remote, convert_remote_to_local, Amount};
-use cw20_ics20_msg::msg::{AllowedInfo, DeletePairMsg, TransferBackMsg, UpdatePairMsg};
-use cw20_ics20_msg::state::{AllowInfo, MappingMetadata, RelayerFee, ReplyArgs, TokenFee};
-use cw_utils::{maybe_addr, nonpayable, one_coin};
+use cw_utils::{maybe_addr, must_pay, nonpayable, one_coin};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw20-ics20";
@@ -56,6 +87,8 @@ pub fn instantiate(
         converter_contract: ConverterController(msg.converter_contract),
         osor_entrypoint_contract: msg.osor_entrypoint_contract,
         token_factory_addr: deps.api.addr_validate(&msg.token_factory_addr)?,
+        token_fee_shares: vec![],
+        relayer_fee_shares: vec![],
     };
     CONFIG.save(deps.storage, &cfg)?;
 
@@ -84,12 +117,36 @@ pub fn execute(
         //     execute_transfer(deps, env, msg, Amount::Native(coin), info.sender)
         // }
         ExecuteMsg::TransferToRemote(msg) => {
-            let coin = one_coin(&info)?;
+            // an ORAI coin attached next to the transferred coin pays the relayer fee
+            let (coin, relayer_fee_payment) = match info.funds.as_slice() {
+                [first, second] if first.denom == "orai" => (
+                    second.clone(),
+                    Some(RelayerFeePayment::Attached(first.amount)),
+                ),
+                [first, second] if second.denom == "orai" => (
+                    first.clone(),
+                    Some(RelayerFeePayment::Attached(second.amount)),
+                ),
+                _ => (one_coin(&info)?, None),
+            };
             let amount = Amount::from_parts(coin.denom, coin.amount);
-            execute_transfer_back_to_remote_chain(deps, env, msg, amount, info.sender)
+            execute_transfer_back_to_remote_chain(
+                deps,
+                env,
+                msg,
+                amount,
+                info.sender,
+                relayer_fee_payment,
+            )
         }
         ExecuteMsg::UpdateMappingPair(msg) => execute_update_mapping_pair(deps, env, info, msg),
         ExecuteMsg::DeleteMappingPair(msg) => execute_delete_mapping_pair(deps, env, info, msg),
+        ExecuteMsg::BatchUpdateMappingPairs { pairs, token_fees } => {
+            execute_batch_update_mapping_pairs(deps, env, info, pairs, token_fees)
+        }
+        ExecuteMsg::BatchDeleteMappingPairs { pairs } => {
+            execute_batch_delete_mapping_pairs(deps, env, info, pairs)
+        }
         // ExecuteMsg::Allow(allow) => execute_allow(deps, env, info, allow),
         ExecuteMsg::UpdateConfig {
             default_timeout,
@@ -100,9 +157,12 @@ pub fn execute(
             fee_receiver,
             relayer_fee_receiver,
             relayer_fee,
+            channel_relayer_fee,
             converter_contract,
             osor_entrypoint_contract,
             token_factory_addr,
+            token_fee_shares,
+            relayer_fee_shares,
         } => update_config(
             deps,
             info,
@@ -114,9 +174,12 @@ pub fn execute(
             fee_receiver,
             relayer_fee_receiver,
             relayer_fee,
+            channel_relayer_fee,
             converter_contract,
             osor_entrypoint_contract,
             token_factory_addr,
+            token_fee_shares,
+            relayer_fee_shares,
         ),
         // self-called msgs for ibc_packet_receive
         ExecuteMsg::IncreaseChannelBalanceIbcReceive {
@@ -128,6 +191,7 @@ pub fn execute(
             deps,
             info.sender,
             env.contract.address,
+            env.block.height,
             dest_channel_id,
             ibc_denom,
             amount,
@@ -142,6 +206,7 @@ pub fn execute(
             deps.storage,
             info.sender,
             env.contract.address,
+            env.block.height,
             src_channel_id,
             ibc_denom,
             amount,
@@ -152,20 +217,120 @@ pub fn execute(
             ibc_denom,
             outstanding,
             total_sent,
+            reason,
         } => handle_override_channel_balance(
             deps,
+            env,
             info,
             channel_id,
             ibc_denom,
             outstanding,
             total_sent,
+            reason,
         ),
+        ExecuteMsg::ConfirmOverrideChannelBalance { id } => {
+            confirm_override_channel_balance(deps, env, info, id)
+        }
+        ExecuteMsg::UpdateOverrideMaxDelta { max_delta } => {
+            update_override_max_delta(deps, info, max_delta)
+        }
         ExecuteMsg::IbcHooksReceive {
             func,
             orai_receiver,
             args,
         } => ibc_hooks_receive(deps, env, info, func, orai_receiver, args),
         ExecuteMsg::RegisterDenom(msg) => register_denom(deps, info, msg),
+        ExecuteMsg::RegisterTokenFactoryDenoms { denoms } => {
+            register_token_factory_denoms(deps, info, denoms)
+        }
+        ExecuteMsg::ChangeDenomAdmin {
+            denom,
+            new_admin_address,
+        } => change_denom_admin(deps, info, denom, new_admin_address),
+        ExecuteMsg::SetDenomMetadata { denom, metadata } => {
+            set_denom_metadata(deps, info, denom, metadata)
+        }
+        ExecuteMsg::ForceTransfer {
+            denom,
+            amount,
+            from_address,
+            to_address,
+        } => force_transfer(deps, info, denom, amount, from_address, to_address),
+        ExecuteMsg::BurnFrom {
+            denom,
+            amount,
+            burn_from_address,
+        } => burn_from(deps, info, denom, amount, burn_from_address),
+        ExecuteMsg::UpdateChannelMetadata {
+            channel_id,
+            metadata,
+        } => update_channel_metadata(deps, info, channel_id, metadata),
+        ExecuteMsg::CheckSolvency { mapping_key } => {
+            execute_check_solvency(deps, env, info, mapping_key)
+        }
+        ExecuteMsg::ResumeMapping { mapping_key } => {
+            execute_resume_mapping(deps, info, mapping_key)
+        }
+        ExecuteMsg::UpdateMappingStatus {
+            mapping_key,
+            status,
+        } => execute_update_mapping_status(deps, info, mapping_key, status),
+        ExecuteMsg::MigrateMappingAsset {
+            mapping_key,
+            new_asset_info,
+            new_asset_info_decimals,
+            is_mint_burn,
+            grace_period,
+        } => execute_migrate_mapping_asset(
+            deps,
+            env,
+            info,
+            mapping_key,
+            new_asset_info,
+            new_asset_info_decimals,
+            is_mint_burn,
+            grace_period,
+        ),
+        ExecuteMsg::UpdateFeeExemption {
+            direction,
+            address,
+            exemption,
+        } => update_fee_exemption(deps, info, direction, address, exemption),
+        ExecuteMsg::UpdateRelayerFeePricing {
+            asset_info,
+            pricing,
+        } => update_relayer_fee_pricing(deps, info, asset_info, pricing),
+        ExecuteMsg::UpdateFees {
+            updates,
+            effective_at,
+        } => execute_change_fees(deps, env, info, FeeChange::Update { updates }, effective_at),
+        ExecuteMsg::ReplaceFees {
+            token_fees,
+            relayer_fees,
+            channel_relayer_fees,
+            effective_at,
+        } => execute_change_fees(
+            deps,
+            env,
+            info,
+            FeeChange::Replace {
+                token_fees,
+                relayer_fees,
+                channel_relayer_fees,
+            },
+            effective_at,
+        ),
+        ExecuteMsg::UpdateFeeEpochDuration { duration } => {
+            update_fee_epoch_duration(deps, info, duration)
+        }
+        ExecuteMsg::CancelScheduledFeeChange { id } => {
+            execute_cancel_scheduled_fee_change(deps, info, id)
+        }
+        ExecuteMsg::DepositRelayerFee {} => deposit_relayer_fee(deps, info),
+        ExecuteMsg::WithdrawRelayerFee { amount } => withdraw_relayer_fee(deps, info, amount),
+        ExecuteMsg::ReindexMappings { start_after, limit } => {
+            execute_reindex_mappings(deps, info, start_after, limit)
+        }
     }
 }
 
@@ -187,6 +352,10 @@ pub fn register_denom(
 
     let config = CONFIG.load(deps.storage)?;
 
+    // the token factory contract creates the denom under its own address
+    let denom = format!("factory/{}/{}", config.token_factory_addr, msg.subdenom);
+    TOKEN_FACTORY_DENOMS.save(deps.storage, &denom, &msg.subdenom)?;
+
     let create_denom_msg = wasm_execute(
         config.token_factory_addr,
         &tokenfactory::msg::ExecuteMsg::CreateDenom {
@@ -198,230 +367,999 @@ pub fn register_denom(
 
     Ok(Response::new()
         .add_attribute("action", "register_denom")
+        .add_attribute("denom", denom)
         .add_message(create_denom_msg))
 }
 
-pub fn handle_override_channel_balance(
+/// returns the token factory address if the sender is the admin and the denom was created by the bridge
+pub fn register_token_factory_denoms(
     deps: DepsMut,
     info: MessageInfo,
-    channel_id: String,
-    ibc_denom: String,
-    outstanding: Uint128,
-    total_sent: Option<Uint128>,
+    denoms: Vec<String>,
 ) -> Result<Response, ContractError> {
     ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
-    override_channel_balance(
-        deps.storage,
-        &channel_id,
-        &ibc_denom,
-        outstanding,
-        total_sent,
-    )?;
+    let config = CONFIG.load(deps.storage)?;
+    let prefix = format!("factory/{}/", config.token_factory_addr);
+    let mut subdenoms = vec![];
+    for denom in denoms.iter() {
+        match denom.strip_prefix(&prefix) {
+            Some(subdenom) if !subdenom.is_empty() => subdenoms.push(subdenom.to_string()),
+            _ => {
+                return Err(ContractError::InvalidTokenFactoryDenom {
+                    denom: denom.to_string(),
+                })
+            }
+        }
+    }
+    for (denom, subdenom) in denoms.iter().zip(subdenoms) {
+        TOKEN_FACTORY_DENOMS.save(deps.storage, denom, &subdenom)?;
+    }
     Ok(Response::new().add_attributes(vec![
-        ("action", "override_channel_balance"),
-        ("channel_id", &channel_id),
-        ("ibc_denom", &ibc_denom),
-        ("new_outstanding", &outstanding.to_string()),
-        ("total_sent", &total_sent.unwrap_or_default().to_string()),
+        ("action", "register_token_factory_denoms"),
+        ("count", &denoms.len().to_string()),
     ]))
 }
 
-pub fn handle_increase_channel_balance_ibc_receive(
+fn assert_token_factory_denom(
+    deps: Deps,
+    info: &MessageInfo,
+    denom: &str,
+) -> Result<Addr, ContractError> {
+    ADMIN.assert_admin(deps, &info.sender)?;
+    if !TOKEN_FACTORY_DENOMS.has(deps.storage, denom) {
+        return Err(ContractError::UnknownTokenFactoryDenom {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(CONFIG.load(deps.storage)?.token_factory_addr)
+}
+
+pub fn change_denom_admin(
     deps: DepsMut,
-    caller: Addr,
-    contract_addr: Addr,
-    dst_channel_id: String,
-    ibc_denom: String,
-    remote_amount: Uint128,
-    local_receiver: String,
+    info: MessageInfo,
+    denom: String,
+    new_admin_address: String,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    is_caller_contract(caller, contract_addr.clone())?;
-    // will have to increase balance here because if this tx fails then it will be reverted, and the balance on the remote chain will also be reverted
-    increase_channel_balance(deps.storage, &dst_channel_id, &ibc_denom, remote_amount)?;
-
-    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
-    let pair_mapping = ics20_denoms()
-        .load(deps.storage, &ibc_denom)
-        .map_err(|_| ContractError::NotOnMappingList {})?;
-
-    let mint_amount = convert_remote_to_local(
-        remote_amount,
-        pair_mapping.remote_decimals,
-        pair_mapping.asset_info_decimals,
-    )?;
-    let mint_msg = build_mint_mapping_msg(
-        config.token_factory_addr.to_string(),
-        pair_mapping.is_mint_burn,
-        pair_mapping.asset_info,
-        mint_amount,
-        contract_addr.to_string(),
+    let token_factory_addr = assert_token_factory_denom(deps.as_ref(), &info, &denom)?;
+    deps.api.addr_validate(&new_admin_address)?;
+
+    let change_admin_msg = wasm_execute(
+        token_factory_addr,
+        &tokenfactory::msg::ExecuteMsg::ChangeAdmin {
+            denom: denom.clone(),
+            new_admin_address: new_admin_address.clone(),
+        },
+        vec![],
     )?;
 
-    if let Some(mint_msg) = mint_msg {
-        cosmos_msgs.push(mint_msg);
-    }
-
-    // we need to save the data to update the balances in reply
-    let reply_args = ReplyArgs {
-        channel: dst_channel_id.clone(),
-        denom: ibc_denom.clone(),
-        amount: remote_amount,
-        local_receiver: local_receiver.clone(),
-    };
-    REPLY_ARGS.save(deps.storage, &reply_args)?;
-    Ok(Response::default()
+    Ok(Response::new()
         .add_attributes(vec![
-            ("action", "increase_channel_balance_ibc_receive"),
-            ("channel_id", dst_channel_id.as_str()),
-            ("ibc_denom", ibc_denom.as_str()),
-            ("amount", remote_amount.to_string().as_str()),
-            ("local_receiver", local_receiver.as_str()),
+            ("action", "change_denom_admin"),
+            ("denom", &denom),
+            ("new_admin_address", &new_admin_address),
         ])
-        .add_messages(cosmos_msgs))
+        .add_message(change_admin_msg))
 }
 
-pub fn handle_reduce_channel_balance_ibc_receive(
-    storage: &mut dyn Storage,
-    caller: Addr,
-    contract_addr: Addr,
-    src_channel_id: String,
-    ibc_denom: String,
-    remote_amount: Uint128,
-    local_receiver: String,
+pub fn set_denom_metadata(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    metadata: Metadata,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(storage)?;
-    is_caller_contract(caller, contract_addr.clone())?;
-    // because we are transferring back, we reduce the channel's balance
-    reduce_channel_balance(storage, src_channel_id.as_str(), &ibc_denom, remote_amount)
-        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let token_factory_addr = assert_token_factory_denom(deps.as_ref(), &info, &denom)?;
 
-    // keep track of the single-step reply since we need ibc data to undo reducing channel balance and local data for refunding.
-    // we use a different item to not override REPLY_ARGS
-    SINGLE_STEP_REPLY_ARGS.save(
-        storage,
-        &ReplyArgs {
-            channel: src_channel_id.to_string(),
-            denom: ibc_denom.clone(),
-            amount: remote_amount,
-            local_receiver: local_receiver.to_string(),
+    let set_metadata_msg = wasm_execute(
+        token_factory_addr,
+        &tokenfactory::msg::ExecuteMsg::SetMetadata {
+            denom: denom.clone(),
+            metadata,
         },
+        vec![],
     )?;
 
-    //  burn cw20 token if the mechanism is mint burn
+    Ok(Response::new()
+        .add_attributes(vec![("action", "set_denom_metadata"), ("denom", &denom)])
+        .add_message(set_metadata_msg))
+}
 
-    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
-    let pair_mapping = ics20_denoms()
-        .load(storage, &ibc_denom)
-        .map_err(|_| ContractError::NotOnMappingList {})?;
-    let burn_amount = convert_remote_to_local(
-        remote_amount,
-        pair_mapping.remote_decimals,
-        pair_mapping.asset_info_decimals,
+pub fn force_transfer(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    from_address: String,
+    to_address: String,
+) -> Result<Response, ContractError> {
+    let token_factory_addr = assert_token_factory_denom(deps.as_ref(), &info, &denom)?;
+    deps.api.addr_validate(&from_address)?;
+    deps.api.addr_validate(&to_address)?;
+
+    let force_transfer_msg = wasm_execute(
+        token_factory_addr,
+        &tokenfactory::msg::ExecuteMsg::ForceTransfer {
+            denom: denom.clone(),
+            amount,
+            from_address: from_address.clone(),
+            to_address: to_address.clone(),
+        },
+        vec![],
     )?;
-    let burn_msg = build_burn_mapping_msg(
-        config.token_factory_addr.to_string(),
-        pair_mapping.is_mint_burn,
-        pair_mapping.asset_info,
-        burn_amount,
-        contract_addr.to_string(),
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            ("action", "force_transfer"),
+            ("denom", &denom),
+            ("amount", &amount.to_string()),
+            ("from_address", &from_address),
+            ("to_address", &to_address),
+        ])
+        .add_message(force_transfer_msg))
+}
+
+pub fn burn_from(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    burn_from_address: String,
+) -> Result<Response, ContractError> {
+    let token_factory_addr = assert_token_factory_denom(deps.as_ref(), &info, &denom)?;
+    deps.api.addr_validate(&burn_from_address)?;
+
+    let burn_msg = wasm_execute(
+        token_factory_addr,
+        &tokenfactory::msg::ExecuteMsg::BurnTokens {
+            denom: denom.clone(),
+            amount,
+            burn_from_address: burn_from_address.clone(),
+        },
+        vec![],
     )?;
-    if let Some(burn_msg) = burn_msg {
-        cosmos_msgs.push(burn_msg);
-    }
 
-    Ok(Response::default()
+    Ok(Response::new()
         .add_attributes(vec![
-            ("action", "reduce_channel_balance_ibc_receive"),
-            ("channel_id", src_channel_id.as_str()),
-            ("ibc_denom", ibc_denom.as_str()),
-            ("amount", remote_amount.to_string().as_str()),
-            ("local_receiver", local_receiver.as_str()),
+            ("action", "burn_from"),
+            ("denom", &denom),
+            ("amount", &amount.to_string()),
+            ("burn_from_address", &burn_from_address),
         ])
-        .add_messages(cosmos_msgs))
+        .add_message(burn_msg))
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn update_config(
+pub fn update_channel_metadata(
     deps: DepsMut,
     info: MessageInfo,
-    default_timeout: Option<u64>,
-    default_gas_limit: Option<u64>,
-    swap_router_contract: Option<String>,
-    admin: Option<String>,
-    token_fee: Option<Vec<TokenFee>>,
-    fee_receiver: Option<String>,
-    relayer_fee_receiver: Option<String>,
-    relayer_fee: Option<Vec<RelayerFee>>,
-    converter_contract: Option<String>,
-    osor_entrypoint_contract: Option<String>,
-    token_factory_addr: Option<String>,
+    channel_id: String,
+    metadata: ChannelMetadata,
 ) -> Result<Response, ContractError> {
     ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
-    if let Some(token_fee) = token_fee {
-        for fee in token_fee {
-            TOKEN_FEE.save(deps.storage, &fee.token_denom, &fee.ratio)?;
-        }
-    }
-    if let Some(relayer_fee) = relayer_fee {
-        for fee in relayer_fee {
-            RELAYER_FEE.save(deps.storage, &fee.prefix, &fee.fee)?;
-        }
+
+    let mut channel_info = CHANNEL_INFO
+        .may_load(deps.storage, &channel_id)?
+        .ok_or_else(|| ContractError::NoSuchChannel {
+            id: channel_id.clone(),
+        })?;
+    channel_info.metadata = Some(metadata.clone());
+    CHANNEL_INFO.save(deps.storage, &channel_id, &channel_info)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_channel_metadata"),
+        ("channel_id", &channel_id),
+        ("chain_id", &metadata.chain_id),
+        ("bech32_prefix", &metadata.bech32_prefix),
+    ]))
+}
+
+pub fn update_fee_exemption(
+    deps: DepsMut,
+    info: MessageInfo,
+    direction: FeeDirection,
+    address: String,
+    exemption: Option<FeeExemption>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    // remote senders cannot be validated locally
+    if direction == FeeDirection::Outbound {
+        deps.api.addr_validate(&address)?;
     }
-    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
-        if let Some(default_timeout) = default_timeout {
-            config.default_timeout = default_timeout;
-        }
-        if let Some(swap_router_contract) = swap_router_contract {
-            config.swap_router_contract = RouterController(swap_router_contract);
-        }
-        if let Some(fee_receiver) = fee_receiver {
-            config.token_fee_receiver = deps.api.addr_validate(&fee_receiver)?;
-        }
-        if let Some(relayer_fee_receiver) = relayer_fee_receiver {
-            config.relayer_fee_receiver = deps.api.addr_validate(&relayer_fee_receiver)?;
-        }
-        if let Some(converter_contract) = converter_contract {
-            config.converter_contract = ConverterController(converter_contract);
-        }
-        if let Some(osor_entrypoint_contract) = osor_entrypoint_contract {
-            config.osor_entrypoint_contract = osor_entrypoint_contract;
-        }
-        if let Some(token_factory_addr) = token_factory_addr {
-            config.token_factory_addr = deps.api.addr_validate(&token_factory_addr)?;
+    match &exemption {
+        Some(exemption) => {
+            if exemption.token_fee_discount_bps > MAX_BPS
+                || exemption.relayer_fee_discount_bps > MAX_BPS
+            {
+                return Err(ContractError::InvalidFeeDiscount {});
+            }
+            FEE_EXEMPTIONS.save(deps.storage, (direction.key(), &address), exemption)?
         }
-        config.default_gas_limit = default_gas_limit;
-        Ok(config)
-    })?;
-    if let Some(admin) = admin {
-        let admin = deps.api.addr_validate(&admin)?;
-        ADMIN.execute_update_admin::<Empty, Empty>(deps, info, Some(admin))?;
+        None => FEE_EXEMPTIONS.remove(deps.storage, (direction.key(), &address)),
     }
-    Ok(Response::default().add_attribute("action", "update_config"))
+    let exemption = exemption.unwrap_or_default();
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_fee_exemption"),
+        ("direction", direction.key()),
+        ("address", &address),
+        (
+            "token_fee_discount_bps",
+            &exemption.token_fee_discount_bps.to_string(),
+        ),
+        (
+            "relayer_fee_discount_bps",
+            &exemption.relayer_fee_discount_bps.to_string(),
+        ),
+    ]))
 }
 
-pub fn execute_receive(
+/// applies the fee change, or schedules it if it becomes effective later
+pub fn execute_change_fees(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    wrapper: Cw20ReceiveMsg,
+    change: FeeChange,
+    effective_at: Option<Timestamp>,
 ) -> Result<Response, ContractError> {
-    nonpayable(&info)?;
-
-    let amount = Amount::cw20(wrapper.amount, info.sender);
-    let api = deps.api;
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    for token_fee in change.token_fees() {
+        assert_fee_schedule(&token_fee.fee)?;
+    }
+    if let Some(effective_at) = effective_at.filter(|time| *time > env.block.time) {
+        let id = SCHEDULED_FEE_CHANGE_COUNT
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            + 1;
+        SCHEDULED_FEE_CHANGE_COUNT.save(deps.storage, &id)?;
+        SCHEDULED_FEE_CHANGE_TIMES.save(deps.storage, id, &effective_at)?;
+        SCHEDULED_FEE_CHANGES.save(
+            deps.storage,
+            (effective_at.nanos(), id),
+            &ScheduledFeeChange {
+                effective_at,
+                change,
+            },
+        )?;
+        return Ok(Response::new().add_attributes(vec![
+            ("action", "schedule_fee_change"),
+            ("id", &id.to_string()),
+            ("effective_at", &effective_at.seconds().to_string()),
+        ]));
+    }
+    // changes scheduled before this one must not override it
+    apply_scheduled_fee_changes(deps.storage, env.block.time)?;
+    apply_fee_change(deps.storage, &change)?;
+    Ok(Response::new().add_attribute("action", "change_fees"))
+}
 
-    let msg: TransferBackMsg = from_json(&wrapper.msg)?;
-    execute_transfer_back_to_remote_chain(
-        deps,
-        env,
-        msg,
-        amount,
-        api.addr_validate(&wrapper.sender)?,
-    )
+pub fn execute_cancel_scheduled_fee_change(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let Some(effective_at) = SCHEDULED_FEE_CHANGE_TIMES.may_load(deps.storage, id)? else {
+        return Err(StdError::not_found("scheduled fee change").into());
+    };
+    SCHEDULED_FEE_CHANGE_TIMES.remove(deps.storage, id);
+    SCHEDULED_FEE_CHANGES.remove(deps.storage, (effective_at.nanos(), id));
+    Ok(Response::new().add_attributes(vec![
+        ("action", "cancel_scheduled_fee_change"),
+        ("id", &id.to_string()),
+    ]))
 }
 
-// pub fn execute_transfer(
+pub fn update_relayer_fee_pricing(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    pricing: Option<RelayerFeePricing>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let key = asset_info.to_string();
+    let source = match pricing {
+        Some(pricing) => {
+            match &pricing.source {
+                RelayerFeePriceSource::SwapRoute { operations } if operations.is_empty() => {
+                    return Err(ContractError::EmptySwapRoute {});
+                }
+                RelayerFeePriceSource::Oracle { contract } => {
+                    deps.api.addr_validate(contract)?;
+                }
+                _ => {}
+            }
+            RELAYER_FEE_PRICING.save(deps.storage, &key, &pricing)?;
+            pricing.source.name()
+        }
+        None => {
+            RELAYER_FEE_PRICING.remove(deps.storage, &key);
+            "default"
+        }
+    };
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_relayer_fee_pricing"),
+        ("asset_info", &key),
+        ("source", source),
+    ]))
+}
+
+pub fn execute_check_solvency(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mapping_key: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let solvency = get_solvency(deps.as_ref(), &env, mapping_key.clone())?;
+    let mut response = Response::new().add_attributes(vec![
+        ("action", "check_solvency"),
+        ("mapping_key", &mapping_key),
+        ("outstanding", &solvency.outstanding.to_string()),
+        ("holdings", &solvency.holdings.to_string()),
+        ("surplus", &solvency.surplus.to_string()),
+        ("deficit", &solvency.deficit.to_string()),
+    ]);
+    if !solvency.deficit.is_zero() && !solvency.is_halted {
+        HALTED_MAPPINGS.save(deps.storage, &mapping_key, &env.block.height)?;
+        response = response.add_attribute("halted", "true");
+    }
+    Ok(response)
+}
+
+pub fn execute_resume_mapping(
+    deps: DepsMut,
+    info: MessageInfo,
+    mapping_key: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    HALTED_MAPPINGS.remove(deps.storage, &mapping_key);
+    Ok(Response::new().add_attributes(vec![
+        ("action", "resume_mapping"),
+        ("mapping_key", &mapping_key),
+    ]))
+}
+
+pub fn execute_update_mapping_status(
+    deps: DepsMut,
+    info: MessageInfo,
+    mapping_key: String,
+    status: MappingStatus,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut mapping = ics20_denoms()
+        .load(deps.storage, &mapping_key)
+        .map_err(|_| ContractError::MappingPairNotFound {})?;
+    mapping.status = status;
+    ics20_denoms().save(deps.storage, &mapping_key, &mapping)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_mapping_status"),
+        ("mapping_key", &mapping_key),
+        ("status", &format!("{:?}", status)),
+    ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_migrate_mapping_asset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mapping_key: String,
+    new_asset_info: AssetInfo,
+    new_asset_info_decimals: u8,
+    is_mint_burn: Option<bool>,
+    grace_period: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut mapping = ics20_denoms()
+        .load(deps.storage, &mapping_key)
+        .map_err(|_| ContractError::MappingPairNotFound {})?;
+    if mapping.asset_info == new_asset_info {
+        return Err(ContractError::MappingAssetUnchanged { key: mapping_key });
+    }
+    if !decimals_convertible(mapping.remote_decimals, new_asset_info_decimals) {
+        return Err(ContractError::InvalidMappingDecimals {
+            remote_decimals: mapping.remote_decimals,
+            local_decimals: new_asset_info_decimals,
+        });
+    }
+    if let Some(migration) = MAPPING_MIGRATIONS.may_load(deps.storage, &mapping_key)? {
+        if env.block.time < migration.grace_period_end {
+            return Err(ContractError::MappingMigrationInProgress { key: mapping_key });
+        }
+    }
+
+    let migration = MappingMigration {
+        old_asset_info: mapping.asset_info,
+        old_asset_info_decimals: mapping.asset_info_decimals,
+        old_is_mint_burn: mapping.is_mint_burn,
+        started_at: env.block.time,
+        grace_period_end: env.block.time.plus_seconds(grace_period),
+        converted_amount: Uint128::zero(),
+        converted_transfers: 0,
+    };
+    MAPPING_MIGRATIONS.save(deps.storage, &mapping_key, &migration)?;
+    mapping.asset_info = new_asset_info;
+    mapping.asset_info_decimals = new_asset_info_decimals;
+    mapping.is_mint_burn = is_mint_burn.unwrap_or_default();
+    ics20_denoms().save(deps.storage, &mapping_key, &mapping)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate_mapping_asset"),
+        ("mapping_key", &mapping_key),
+        ("old_asset_info", &migration.old_asset_info.to_string()),
+        ("new_asset_info", &mapping.asset_info.to_string()),
+        (
+            "grace_period_end",
+            &migration.grace_period_end.seconds().to_string(),
+        ),
+    ]))
+}
+
+/// converts the old asset of a migrated mapping pair at par into its new asset, during the grace period of the
+/// migration. The old asset is burned if it was minted by the bridge and kept in escrow otherwise. The new asset is
+/// minted if it is mint-burn, and taken from the escrow of the contract otherwise
+fn convert_migrated_asset(
+    storage: &mut dyn Storage,
+    env: &Env,
+    token_factory: &str,
+    mapping_key: &str,
+    amount: &Amount,
+    sent_asset_info: &AssetInfo,
+) -> Result<Option<(PairQuery, Amount, Vec<CosmosMsg>)>, ContractError> {
+    let Some(mut migration) = MAPPING_MIGRATIONS.may_load(storage, mapping_key)? else {
+        return Ok(None);
+    };
+    if migration.old_asset_info != *sent_asset_info || env.block.time >= migration.grace_period_end
+    {
+        return Ok(None);
+    }
+    let pair_mapping = ics20_denoms().load(storage, mapping_key)?;
+    let converted = convert_remote_to_local(
+        amount.amount(),
+        migration.old_asset_info_decimals,
+        pair_mapping.asset_info_decimals,
+    )?;
+    if converted.is_zero() {
+        return Err(ContractError::NoFunds {});
+    }
+
+    let contract_addr = env.contract.address.to_string();
+    let mut msgs = vec![];
+    if let Some(burn_msg) = build_burn_mapping_msg(
+        token_factory.to_string(),
+        migration.old_is_mint_burn,
+        migration.old_asset_info.clone(),
+        amount.amount(),
+        contract_addr.clone(),
+    )? {
+        msgs.push(burn_msg);
+    }
+    if let Some(mint_msg) = build_mint_mapping_msg(
+        token_factory.to_string(),
+        pair_mapping.is_mint_burn,
+        pair_mapping.asset_info.clone(),
+        converted,
+        contract_addr,
+    )? {
+        msgs.push(mint_msg);
+    }
+
+    migration.converted_amount = migration.converted_amount.checked_add(amount.amount())?;
+    migration.converted_transfers += 1;
+    MAPPING_MIGRATIONS.save(storage, mapping_key, &migration)?;
+
+    let converted_amount =
+        Amount::from_parts(parse_asset_info_denom(&pair_mapping.asset_info), converted);
+    Ok(Some((
+        PairQuery {
+            key: mapping_key.to_string(),
+            pair_mapping,
+        },
+        converted_amount,
+        msgs,
+    )))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_override_channel_balance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    ibc_denom: String,
+    outstanding: Uint128,
+    total_sent: Option<Uint128>,
+    reason: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let request = PendingChannelBalanceOverride {
+        channel_id,
+        ibc_denom,
+        outstanding,
+        total_sent,
+        reason,
+        admin: info.sender,
+        height: env.block.height,
+    };
+
+    // large overrides need a second confirmation from the admin
+    if let Some(max_delta) = OVERRIDE_MAX_DELTA.may_load(deps.storage)? {
+        let old = CHANNEL_REVERSE_STATE
+            .may_load(deps.storage, (&request.channel_id, &request.ibc_denom))?
+            .unwrap_or_default();
+        let total_sent_delta = request
+            .total_sent
+            .map(|total_sent| total_sent.abs_diff(old.total_sent))
+            .unwrap_or_default();
+        if request.outstanding.abs_diff(old.outstanding) > max_delta || total_sent_delta > max_delta
+        {
+            let id = PENDING_CHANNEL_BALANCE_OVERRIDE_COUNT
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                + 1;
+            PENDING_CHANNEL_BALANCE_OVERRIDE_COUNT.save(deps.storage, &id)?;
+            PENDING_CHANNEL_BALANCE_OVERRIDES.save(deps.storage, id, &request)?;
+            return Ok(Response::new().add_attributes(vec![
+                ("action", "propose_override_channel_balance"),
+                ("id", &id.to_string()),
+                ("channel_id", &request.channel_id),
+                ("ibc_denom", &request.ibc_denom),
+                ("new_outstanding", &request.outstanding.to_string()),
+                (
+                    "total_sent",
+                    &request.total_sent.unwrap_or_default().to_string(),
+                ),
+            ]));
+        }
+    }
+
+    apply_channel_balance_override(deps.storage, request, None, env.block.height)
+}
+
+pub fn confirm_override_channel_balance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let request = PENDING_CHANNEL_BALANCE_OVERRIDES
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NoSuchOverride { id })?;
+    if env.block.height <= request.height {
+        return Err(ContractError::OverrideConfirmationTooEarly { id });
+    }
+    PENDING_CHANNEL_BALANCE_OVERRIDES.remove(deps.storage, id);
+    apply_channel_balance_override(deps.storage, request, Some(info.sender), env.block.height)
+}
+
+fn apply_channel_balance_override(
+    storage: &mut dyn Storage,
+    request: PendingChannelBalanceOverride,
+    confirmed_by: Option<Addr>,
+    height: u64,
+) -> Result<Response, ContractError> {
+    let old = CHANNEL_REVERSE_STATE
+        .may_load(storage, (&request.channel_id, &request.ibc_denom))?
+        .unwrap_or_default();
+    override_channel_balance(
+        storage,
+        &request.channel_id,
+        &request.ibc_denom,
+        request.outstanding,
+        request.total_sent,
+        height,
+    )?;
+    let new = CHANNEL_REVERSE_STATE.load(storage, (&request.channel_id, &request.ibc_denom))?;
+
+    let id = CHANNEL_BALANCE_OVERRIDE_COUNT
+        .may_load(storage)?
+        .unwrap_or_default()
+        + 1;
+    CHANNEL_BALANCE_OVERRIDE_COUNT.save(storage, &id)?;
+    CHANNEL_BALANCE_OVERRIDES.save(
+        storage,
+        id,
+        &ChannelBalanceOverride {
+            channel_id: request.channel_id.clone(),
+            ibc_denom: request.ibc_denom.clone(),
+            old,
+            new,
+            reason: request.reason,
+            admin: request.admin,
+            confirmed_by,
+            height,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "override_channel_balance"),
+        ("id", &id.to_string()),
+        ("channel_id", &request.channel_id),
+        ("ibc_denom", &request.ibc_denom),
+        ("new_outstanding", &request.outstanding.to_string()),
+        (
+            "total_sent",
+            &request.total_sent.unwrap_or_default().to_string(),
+        ),
+    ]))
+}
+
+pub fn update_fee_epoch_duration(
+    deps: DepsMut,
+    info: MessageInfo,
+    duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match duration {
+        Some(0) => return Err(ContractError::InvalidFeeEpochDuration {}),
+        Some(duration) => FEE_EPOCH_DURATION.save(deps.storage, &duration)?,
+        None => FEE_EPOCH_DURATION.remove(deps.storage),
+    }
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_fee_epoch_duration"),
+        (
+            "duration",
+            &duration
+                .map(|duration| duration.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+        ),
+    ]))
+}
+
+pub fn update_override_max_delta(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_delta: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match max_delta {
+        Some(max_delta) => OVERRIDE_MAX_DELTA.save(deps.storage, &max_delta)?,
+        None => OVERRIDE_MAX_DELTA.remove(deps.storage),
+    }
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_override_max_delta"),
+        (
+            "max_delta",
+            &max_delta
+                .map(|max_delta| max_delta.to_string())
+                .unwrap_or_default(),
+        ),
+    ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_increase_channel_balance_ibc_receive(
+    deps: DepsMut,
+    caller: Addr,
+    contract_addr: Addr,
+    height: u64,
+    dst_channel_id: String,
+    ibc_denom: String,
+    remote_amount: Uint128,
+    local_receiver: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    is_caller_contract(caller, contract_addr.clone())?;
+    let pair_mapping = ics20_denoms()
+        .load(deps.storage, &ibc_denom)
+        .map_err(|_| ContractError::NotOnMappingList {})?;
+    assert_outstanding_cap(
+        deps.storage,
+        &dst_channel_id,
+        &ibc_denom,
+        &pair_mapping,
+        remote_amount,
+    )?;
+    // will have to increase balance here because if this tx fails then it will be reverted, and the balance on the remote chain will also be reverted
+    increase_channel_balance(
+        deps.storage,
+        &dst_channel_id,
+        &ibc_denom,
+        remote_amount,
+        height,
+    )?;
+    update_channel_stats(deps.storage, &dst_channel_id, &ibc_denom, |stats| {
+        stats.total_received = stats.total_received.checked_add(remote_amount)?;
+        stats.received_packets += 1;
+        Ok(())
+    })?;
+
+    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
+
+    let mint_amount = convert_remote_to_local(
+        remote_amount,
+        pair_mapping.remote_decimals,
+        pair_mapping.asset_info_decimals,
+    )?;
+    let mint_msg = build_mint_mapping_msg(
+        config.token_factory_addr.to_string(),
+        pair_mapping.is_mint_burn,
+        pair_mapping.asset_info,
+        mint_amount,
+        contract_addr.to_string(),
+    )?;
+
+    if let Some(mint_msg) = mint_msg {
+        cosmos_msgs.push(mint_msg);
+    }
+
+    // we need to save the data to update the balances in reply
+    let reply_args = ReplyArgs {
+        channel: dst_channel_id.clone(),
+        denom: ibc_denom.clone(),
+        amount: remote_amount,
+        local_receiver: local_receiver.clone(),
+    };
+    REPLY_ARGS.save(deps.storage, &reply_args)?;
+    Ok(Response::default()
+        .add_attributes(vec![
+            ("action", "increase_channel_balance_ibc_receive"),
+            ("channel_id", dst_channel_id.as_str()),
+            ("ibc_denom", ibc_denom.as_str()),
+            ("amount", remote_amount.to_string().as_str()),
+            ("local_receiver", local_receiver.as_str()),
+        ])
+        .add_messages(cosmos_msgs))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_reduce_channel_balance_ibc_receive(
+    storage: &mut dyn Storage,
+    caller: Addr,
+    contract_addr: Addr,
+    height: u64,
+    src_channel_id: String,
+    ibc_denom: String,
+    remote_amount: Uint128,
+    local_receiver: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(storage)?;
+    is_caller_contract(caller, contract_addr.clone())?;
+    // because we are transferring back, we reduce the channel's balance
+    reduce_channel_balance(
+        storage,
+        src_channel_id.as_str(),
+        &ibc_denom,
+        remote_amount,
+        height,
+    )
+    .map_err(|err| StdError::generic_err(err.to_string()))?;
+    update_channel_stats(storage, &src_channel_id, &ibc_denom, |stats| {
+        stats.total_returned = stats.total_returned.checked_add(remote_amount)?;
+        stats.returned_packets += 1;
+        Ok(())
+    })?;
+
+    // keep track of the single-step reply since we need ibc data to undo reducing channel balance and local data for refunding.
+    // we use a different item to not override REPLY_ARGS
+    SINGLE_STEP_REPLY_ARGS.save(
+        storage,
+        &ReplyArgs {
+            channel: src_channel_id.to_string(),
+            denom: ibc_denom.clone(),
+            amount: remote_amount,
+            local_receiver: local_receiver.to_string(),
+        },
+    )?;
+
+    //  burn cw20 token if the mechanism is mint burn
+
+    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
+    let pair_mapping = ics20_denoms()
+        .load(storage, &ibc_denom)
+        .map_err(|_| ContractError::NotOnMappingList {})?;
+    let burn_amount = convert_remote_to_local(
+        remote_amount,
+        pair_mapping.remote_decimals,
+        pair_mapping.asset_info_decimals,
+    )?;
+    let burn_msg = build_burn_mapping_msg(
+        config.token_factory_addr.to_string(),
+        pair_mapping.is_mint_burn,
+        pair_mapping.asset_info,
+        burn_amount,
+        contract_addr.to_string(),
+    )?;
+    if let Some(burn_msg) = burn_msg {
+        cosmos_msgs.push(burn_msg);
+    }
+
+    Ok(Response::default()
+        .add_attributes(vec![
+            ("action", "reduce_channel_balance_ibc_receive"),
+            ("channel_id", src_channel_id.as_str()),
+            ("ibc_denom", ibc_denom.as_str()),
+            ("amount", remote_amount.to_string().as_str()),
+            ("local_receiver", local_receiver.as_str()),
+        ])
+        .add_messages(cosmos_msgs))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    default_timeout: Option<u64>,
+    default_gas_limit: Option<u64>,
+    swap_router_contract: Option<String>,
+    admin: Option<String>,
+    token_fee: Option<Vec<TokenFee>>,
+    fee_receiver: Option<String>,
+    relayer_fee_receiver: Option<String>,
+    relayer_fee: Option<Vec<RelayerFee>>,
+    channel_relayer_fee: Option<Vec<ChannelRelayerFee>>,
+    converter_contract: Option<String>,
+    osor_entrypoint_contract: Option<String>,
+    token_factory_addr: Option<String>,
+    token_fee_shares: Option<Vec<FeeShare>>,
+    relayer_fee_shares: Option<Vec<FeeShare>>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    for shares in [&token_fee_shares, &relayer_fee_shares]
+        .into_iter()
+        .flatten()
+    {
+        validate_fee_shares(deps.api, shares)?;
+    }
+    for fee in token_fee.iter().flatten() {
+        assert_fee_schedule(&fee.fee)?;
+    }
+    if let Some(token_fee) = token_fee {
+        for fee in token_fee {
+            save_token_fee(deps.storage, &fee)?;
+        }
+    }
+    if let Some(relayer_fee) = relayer_fee {
+        for fee in relayer_fee {
+            RELAYER_FEE.save(deps.storage, &fee.prefix, &fee.fee)?;
+        }
+    }
+    if let Some(channel_relayer_fee) = channel_relayer_fee {
+        for fee in channel_relayer_fee {
+            CHANNEL_RELAYER_FEE.save(
+                deps.storage,
+                (&fee.channel_id, &fee.destination),
+                &fee.fee,
+            )?;
+        }
+    }
+    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
+        if let Some(default_timeout) = default_timeout {
+            config.default_timeout = default_timeout;
+        }
+        if let Some(swap_router_contract) = swap_router_contract {
+            config.swap_router_contract = RouterController(swap_router_contract);
+        }
+        if let Some(fee_receiver) = fee_receiver {
+            config.token_fee_receiver = deps.api.addr_validate(&fee_receiver)?;
+        }
+        if let Some(relayer_fee_receiver) = relayer_fee_receiver {
+            config.relayer_fee_receiver = deps.api.addr_validate(&relayer_fee_receiver)?;
+        }
+        if let Some(converter_contract) = converter_contract {
+            config.converter_contract = ConverterController(converter_contract);
+        }
+        if let Some(osor_entrypoint_contract) = osor_entrypoint_contract {
+            config.osor_entrypoint_contract = osor_entrypoint_contract;
+        }
+        if let Some(token_factory_addr) = token_factory_addr {
+            config.token_factory_addr = deps.api.addr_validate(&token_factory_addr)?;
+        }
+        if let Some(token_fee_shares) = token_fee_shares {
+            config.token_fee_shares = token_fee_shares;
+        }
+        if let Some(relayer_fee_shares) = relayer_fee_shares {
+            config.relayer_fee_shares = relayer_fee_shares;
+        }
+        config.default_gas_limit = default_gas_limit;
+        Ok(config)
+    })?;
+    if let Some(admin) = admin {
+        let admin = deps.api.addr_validate(&admin)?;
+        ADMIN.execute_update_admin::<Empty, Empty>(deps, info, Some(admin))?;
+    }
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
+fn validate_fee_shares(api: &dyn Api, shares: &[FeeShare]) -> Result<(), ContractError> {
+    if shares.is_empty() {
+        return Ok(());
+    }
+    if shares.iter().map(|share| share.weight).sum::<u64>() == 0 {
+        return Err(ContractError::InvalidFeeShares {});
+    }
+    for share in shares {
+        if let FeeRecipient::Address(address) = &share.recipient {
+            api.addr_validate(address)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let amount = Amount::cw20(wrapper.amount, info.sender);
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+    // the cw20 contract calls us without funds, so the relayer fee is paid from the ORAI deposit of the sender if it
+    // covers the fee
+    let relayer_fee_payment = RELAYER_FEE_DEPOSITS
+        .may_load(deps.storage, &sender)?
+        .map(RelayerFeePayment::Deposit);
+
+    let msg: TransferBackMsg = from_json(&wrapper.msg)?;
+    execute_transfer_back_to_remote_chain(deps, env, msg, amount, sender, relayer_fee_payment)
+}
+
+/// ORAI paying the relayer fee of an outbound transfer instead of the transferred tokens
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayerFeePayment {
+    /// attached to the transfer, the excess is refunded
+    Attached(Uint128),
+    /// the relayer fee deposit of the sender, the excess stays deposited
+    Deposit(Uint128),
+}
+
+impl RelayerFeePayment {
+    pub fn amount(&self) -> Uint128 {
+        match self {
+            RelayerFeePayment::Attached(amount) | RelayerFeePayment::Deposit(amount) => *amount,
+        }
+    }
+}
+
+pub fn deposit_relayer_fee(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let amount = must_pay(&info, "orai")?;
+    let deposit =
+        RELAYER_FEE_DEPOSITS.update(deps.storage, &info.sender, |deposit| -> StdResult<_> {
+            Ok(deposit.unwrap_or_default().checked_add(amount)?)
+        })?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "deposit_relayer_fee"),
+        attr("sender", info.sender.as_str()),
+        attr("amount", amount),
+        attr("deposit", deposit),
+    ]))
+}
+
+pub fn withdraw_relayer_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let deposit = RELAYER_FEE_DEPOSITS
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    let amount = amount.unwrap_or(deposit);
+    if amount.is_zero() {
+        return Err(ContractError::NoFunds {});
+    }
+    let remaining = deposit
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientRelayerFeeDeposit { amount, deposit })?;
+    if remaining.is_zero() {
+        RELAYER_FEE_DEPOSITS.remove(deps.storage, &info.sender);
+    } else {
+        RELAYER_FEE_DEPOSITS.save(deps.storage, &info.sender, &remaining)?;
+    }
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(amount.u128(), "orai"),
+        })
+        .add_attributes(vec![
+            attr("action", "withdraw_relayer_fee"),
+            attr("sender", info.sender.as_str()),
+            attr("amount", amount),
+            attr("deposit", remaining),
+        ]))
+}
+
+// pub fn execute_transfer(
 //     deps: DepsMut,
 //     env: Env,
 //     msg: TransferMsg,
@@ -501,70 +1439,166 @@ pub fn execute_transfer_back_to_remote_chain(
     msg: TransferBackMsg,
     amount: Amount,
     sender: Addr,
+    relayer_fee_payment: Option<RelayerFeePayment>,
 ) -> Result<Response, ContractError> {
     if amount.is_empty() {
         return Err(ContractError::NoFunds {});
     }
     let config = CONFIG.load(deps.storage)?;
+    apply_scheduled_fee_changes(deps.storage, env.block.time)?;
 
     // should be in form port/channel/denom
-    let mappings =
-        get_mappings_from_asset_info(deps.as_ref().storage, amount.into_asset_info(deps.api)?)?;
+    let sent_asset_info = amount.into_asset_info(deps.api)?;
+    let mappings = get_mappings_from_asset_info(deps.as_ref().storage, sent_asset_info.clone())?;
+    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
 
     // parse denom & compare with user input. Should not use string.includes() because hacker can fake a port that has the same remote denom to return true
-    let mapping = mappings
-        .into_iter()
-        .find(|pair| -> bool {
-            match parse_voucher_denom(
-                pair.key.as_str(),
-                &IbcEndpoint {
-                    port_id: parse_ibc_wasm_port_id(env.contract.address.as_str()),
-                    channel_id: msg.local_channel_id.clone(), // also verify local channel id
-                },
-            ) {
-                Ok((denom, false)) => msg.remote_denom.eq(denom),
-                _ => false,
-            }
-        })
-        .ok_or(ContractError::MappingPairNotFound {})?;
+    let found_mapping = mappings.into_iter().find(|pair| -> bool {
+        match parse_voucher_denom(
+            pair.key.as_str(),
+            &IbcEndpoint {
+                port_id: parse_ibc_wasm_port_id(env.contract.address.as_str()),
+                channel_id: msg.local_channel_id.clone(), // also verify local channel id
+            },
+        ) {
+            Ok((denom, false)) => msg.remote_denom.eq(denom),
+            _ => false,
+        }
+    });
+    let (mapping, amount) = match found_mapping {
+        Some(mapping) => (mapping, amount),
+        None => {
+            let mapping_key = get_key_ics20_ibc_denom(
+                &parse_ibc_wasm_port_id(env.contract.address.as_str()),
+                &msg.local_channel_id,
+                &msg.remote_denom,
+            );
+            let (mapping, converted_amount, conversion_msgs) = convert_migrated_asset(
+                deps.storage,
+                &env,
+                config.token_factory_addr.as_str(),
+                &mapping_key,
+                &amount,
+                &sent_asset_info,
+            )?
+            .ok_or(ContractError::MappingPairNotFound {})?;
+            cosmos_msgs.extend(conversion_msgs);
+            (mapping, converted_amount)
+        }
+    };
+    assert_mapping_not_halted(deps.storage, &mapping.key)?;
+    assert_mapping_status(&mapping.key, &mapping.pair_mapping, FeeDirection::Outbound)?;
+    if mapping.pair_mapping.min_amount.is_some() || mapping.pair_mapping.max_amount.is_some() {
+        // amounts too small to be converted are zero in remote decimals
+        let remote_amount = convert_local_to_remote(
+            amount.amount(),
+            mapping.pair_mapping.remote_decimals,
+            mapping.pair_mapping.asset_info_decimals,
+        )
+        .unwrap_or_default();
+        assert_transfer_amount(&mapping.key, &mapping.pair_mapping, remote_amount)?;
+    }
 
     // if found mapping, then deduct fee based on mapping
-    let fee_data = process_deduct_fee(
-        deps.storage,
-        &deps.querier,
-        deps.api,
-        &msg.remote_address,
-        &msg.remote_denom,
-        amount,
-        &config.swap_router_contract,
-    )?;
-
-    let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
-    if !fee_data.token_fee.is_empty() {
-        cosmos_msgs.push(
-            fee_data
-                .token_fee
-                .send_amount(config.token_fee_receiver.into_string(), None),
+    let deduct_fee = |relayer_fee_mode: RelayerFeeMode| {
+        process_deduct_fee(
+            deps.storage,
+            &deps.querier,
+            deps.api,
+            FeeDirection::Outbound,
+            &msg.local_channel_id,
+            sender.as_str(),
+            &msg.remote_address,
+            &msg.remote_denom,
+            amount.clone(),
+            relayer_fee_mode,
+            &config.swap_router_contract,
+            env.block.time,
         )
+    };
+    let mut relayer_fee_payment = relayer_fee_payment;
+    let mut fee_data = deduct_fee(if relayer_fee_payment.is_some() {
+        RelayerFeeMode::PaidInOrai
+    } else {
+        RelayerFeeMode::Deducted
+    })?;
+    // a deposit too small for the relayer fee is left untouched and the fee is deducted from the tokens instead
+    if let Some(RelayerFeePayment::Deposit(deposit)) = relayer_fee_payment {
+        if deposit < fee_data.relayer_fee.amount() {
+            relayer_fee_payment = None;
+            fee_data = deduct_fee(RelayerFeeMode::Deducted)?;
+        }
+    }
+
+    let mut relayer_fee_refund = Uint128::zero();
+    if let Some(payment) = relayer_fee_payment {
+        let required = fee_data.relayer_fee.amount();
+        let paid = payment.amount();
+        let excess = paid
+            .checked_sub(required)
+            .map_err(|_| ContractError::InsufficientRelayerFeePayment { required, paid })?;
+        match payment {
+            RelayerFeePayment::Attached(_) => relayer_fee_refund = excess,
+            RelayerFeePayment::Deposit(_) if excess.is_zero() => {
+                RELAYER_FEE_DEPOSITS.remove(deps.storage, &sender)
+            }
+            RelayerFeePayment::Deposit(_) => {
+                RELAYER_FEE_DEPOSITS.save(deps.storage, &sender, &excess)?
+            }
+        }
     }
-    if !fee_data.relayer_fee.is_empty() {
+    cosmos_msgs.extend(build_fee_msgs(
+        deps.api,
+        &fee_data.token_fee,
+        &config.token_fee_shares,
+        config.token_fee_receiver.as_str(),
+        msg.referral.as_deref(),
+    ));
+    cosmos_msgs.extend(build_fee_msgs(
+        deps.api,
+        &fee_data.relayer_fee,
+        &config.relayer_fee_shares,
+        config.relayer_fee_receiver.as_str(),
+        msg.referral.as_deref(),
+    ));
+    if !relayer_fee_refund.is_zero() {
         cosmos_msgs.push(
-            fee_data
-                .relayer_fee
-                .send_amount(config.relayer_fee_receiver.into_string(), None),
+            BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: coins(relayer_fee_refund.u128(), "orai"),
+            }
+            .into(),
         )
     }
 
     // send response
-    let token_fee_str = fee_data.token_fee.amount().to_string();
-    let relayer_fee_str = fee_data.relayer_fee.amount().to_string();
-    let attributes = vec![
-        ("action", "transfer_back_to_remote_chain"),
-        ("sender", sender.as_str()),
-        ("receiver", &msg.remote_address),
-        ("token_fee", &token_fee_str),
-        ("relayer_fee", &relayer_fee_str),
+    let mut attributes = vec![
+        attr("action", "transfer_back_to_remote_chain"),
+        attr("sender", sender.as_str()),
+        attr("receiver", &msg.remote_address),
     ];
+    attributes.extend(fee_data.attributes());
+    attributes.push(attr("relayer_fee_refund", relayer_fee_refund));
+
+    let ibc_denom = mapping.key;
+    update_channel_stats(deps.storage, &msg.local_channel_id, &ibc_denom, |stats| {
+        stats.total_token_fee = stats
+            .total_token_fee
+            .checked_add(fee_data.token_fee.amount())?;
+        // relayer fees paid in ORAI are not in the unit of the mapping
+        if relayer_fee_payment.is_none() {
+            stats.total_relayer_fee = stats
+                .total_relayer_fee
+                .checked_add(fee_data.relayer_fee.amount())?;
+        }
+        Ok(())
+    })?;
+    record_fee_revenue(
+        deps.storage,
+        env.block.time,
+        &msg.local_channel_id,
+        &fee_data,
+    )?;
 
     // if our fees have drained the initial amount entirely, then we just get all the fees and that's it
     if fee_data.deducted_amount.is_zero() {
@@ -573,13 +1607,12 @@ pub fn execute_transfer_back_to_remote_chain(
             .add_attributes(attributes));
     }
 
-    let ibc_denom = mapping.key;
     // ensure the requested channel is registered
-    if !CHANNEL_INFO.has(deps.storage, &msg.local_channel_id) {
+    let Some(channel_info) = CHANNEL_INFO.may_load(deps.storage, &msg.local_channel_id)? else {
         return Err(ContractError::NoSuchChannel {
             id: msg.local_channel_id,
         });
-    }
+    };
 
     // delta from user is in seconds
     let timeout = match msg.timeout {
@@ -601,7 +1634,13 @@ pub fn execute_transfer_back_to_remote_chain(
         &msg.local_channel_id,
         &ibc_denom,
         amount_remote,
+        env.block.height,
     )?;
+    update_channel_stats(deps.storage, &msg.local_channel_id, &ibc_denom, |stats| {
+        stats.total_returned = stats.total_returned.checked_add(amount_remote)?;
+        stats.returned_packets += 1;
+        Ok(())
+    })?;
 
     // prepare ibc message
     let ibc_msg = build_ibc_send_packet(
@@ -612,6 +1651,7 @@ pub fn execute_transfer_back_to_remote_chain(
         msg.memo,
         &msg.local_channel_id,
         timeout.into(),
+        channel_info.version.as_deref().unwrap_or(ICS20_VERSION),
     )?;
 
     // build burn msg if the mechanism is mint/burn
@@ -763,15 +1803,206 @@ pub fn execute_update_mapping_pair(
 ) -> Result<Response, ContractError> {
     ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
 
+    let (_, denom_hash) = save_mapping_pair(deps, &env, &mapping_pair_msg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "execute_update_mapping_pair")
+        .add_attribute("denom", mapping_pair_msg.denom)
+        .add_attribute("denom_hash", denom_hash)
+        .add_attribute(
+            "new_asset_info",
+            mapping_pair_msg.local_asset_info.to_string(),
+        );
+    Ok(res)
+}
+
+pub fn execute_delete_mapping_pair(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mapping_pair_msg: DeletePairMsg,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let ibc_denom = assert_mapping_pair_deletable(deps.as_ref(), &env, &mapping_pair_msg)?;
+    remove_mapping_pair(deps.storage, &ibc_denom)?;
+
+    let res = Response::new()
+        .add_attribute("action", "execute_delete_mapping_pair")
+        .add_attribute("local_channel_id", mapping_pair_msg.local_channel_id)
+        .add_attribute("original_denom", mapping_pair_msg.denom);
+    Ok(res)
+}
+
+/// validates the whole batch before applying it, so that either every pair is saved or none is
+pub fn execute_batch_update_mapping_pairs(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pairs: Vec<UpdatePairMsg>,
+    token_fees: Vec<TokenFee>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if pairs.is_empty() && token_fees.is_empty() {
+        return Err(ContractError::EmptyBatch {});
+    }
+    assert_unique_mapping_keys(
+        &env,
+        pairs
+            .iter()
+            .map(|pair| (pair.local_channel_id.as_str(), pair.denom.as_str())),
+    )?;
+    for token_fee in token_fees.iter() {
+        assert_fee_schedule(&token_fee.fee)?;
+    }
+
+    for pair in pairs.iter() {
+        save_mapping_pair(deps.branch(), &env, pair)?;
+    }
+    for token_fee in token_fees.iter() {
+        save_token_fee(deps.storage, token_fee)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "batch_update_mapping_pairs"),
+        ("pairs", &pairs.len().to_string()),
+        ("token_fees", &token_fees.len().to_string()),
+    ]))
+}
+
+/// validates the whole batch before applying it, so that either every pair is deleted or none is
+pub fn execute_batch_delete_mapping_pairs(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pairs: Vec<DeletePairMsg>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if pairs.is_empty() {
+        return Err(ContractError::EmptyBatch {});
+    }
+    assert_unique_mapping_keys(
+        &env,
+        pairs
+            .iter()
+            .map(|pair| (pair.local_channel_id.as_str(), pair.denom.as_str())),
+    )?;
+
+    let mapping_keys = pairs
+        .iter()
+        .map(|pair| assert_mapping_pair_deletable(deps.as_ref(), &env, pair))
+        .collect::<Result<Vec<_>, _>>()?;
+    for mapping_key in mapping_keys {
+        remove_mapping_pair(deps.storage, &mapping_key)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "batch_delete_mapping_pairs"),
+        ("pairs", &pairs.len().to_string()),
+    ]))
+}
+
+fn assert_unique_mapping_keys<'a>(
+    env: &Env,
+    pairs: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Result<(), ContractError> {
+    let port = parse_ibc_wasm_port_id(env.contract.address.as_str());
+    let mut keys = HashSet::new();
+    for (local_channel_id, denom) in pairs {
+        let key = get_key_ics20_ibc_denom(&port, local_channel_id, denom);
+        if !keys.insert(key.clone()) {
+            return Err(ContractError::DuplicateMappingPair { key });
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_reindex_mappings(
+    deps: DepsMut,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mappings = ics20_denoms()
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let last_key = mappings
+        .last()
+        .map(|(key, _)| key.clone())
+        .unwrap_or_default();
+    for (mapping_key, mapping) in mappings.iter() {
+        ics20_denoms().save(deps.storage, mapping_key, mapping)?;
+        save_denom_trace(deps.storage, mapping_key)?;
+    }
+    Ok(Response::new().add_attributes(vec![
+        ("action", "reindex_mappings"),
+        ("reindexed", &mappings.len().to_string()),
+        ("last_key", &last_key),
+    ]))
+}
+
+/// saves the mapping pair, keeping the status of the pair it replaces. Returns its key and denom hash
+fn save_mapping_pair(
+    deps: DepsMut,
+    env: &Env,
+    mapping_pair_msg: &UpdatePairMsg,
+) -> Result<(String, String), ContractError> {
     let ibc_denom = get_key_ics20_ibc_denom(
         &parse_ibc_wasm_port_id(env.contract.address.as_str()),
         &mapping_pair_msg.local_channel_id,
         &mapping_pair_msg.denom,
     );
 
-    // if pair already exists in list, remove it and create a new one
-    if ics20_denoms().load(deps.storage, &ibc_denom).is_ok() {
-        ics20_denoms().remove(deps.storage, &ibc_denom)?;
+    if !decimals_convertible(
+        mapping_pair_msg.remote_decimals,
+        mapping_pair_msg.local_asset_info_decimals,
+    ) {
+        return Err(ContractError::InvalidMappingDecimals {
+            remote_decimals: mapping_pair_msg.remote_decimals,
+            local_decimals: mapping_pair_msg.local_asset_info_decimals,
+        });
+    }
+    if let (Some(min_amount), Some(max_amount)) =
+        (mapping_pair_msg.min_amount, mapping_pair_msg.max_amount)
+    {
+        if min_amount > max_amount {
+            return Err(ContractError::InvalidTransferAmountBounds {
+                min_amount,
+                max_amount,
+            });
+        }
+    }
+    if !mapping_pair_msg.override_decimals.unwrap_or_default() {
+        assert_asset_decimals(
+            &deps.querier,
+            &mapping_pair_msg.local_asset_info,
+            mapping_pair_msg.local_asset_info_decimals,
+        )?;
+    }
+
+    // if pair already exists in list, remove it and create a new one, keeping its status unless given
+    let status = match ics20_denoms().may_load(deps.storage, &ibc_denom)? {
+        Some(mapping) => {
+            ics20_denoms().remove(deps.storage, &ibc_denom)?;
+            mapping.status
+        }
+        None => MappingStatus::Active,
+    };
+    let status = mapping_pair_msg.status.unwrap_or(status);
+    match mapping_pair_msg.halted {
+        Some(true) if !HALTED_MAPPINGS.has(deps.storage, &ibc_denom) => {
+            HALTED_MAPPINGS.save(deps.storage, &ibc_denom, &env.block.height)?
+        }
+        Some(false) => HALTED_MAPPINGS.remove(deps.storage, &ibc_denom),
+        _ => {}
     }
 
     ics20_denoms().save(
@@ -782,45 +2013,93 @@ pub fn execute_update_mapping_pair(
             remote_decimals: mapping_pair_msg.remote_decimals,
             asset_info_decimals: mapping_pair_msg.local_asset_info_decimals,
             is_mint_burn: mapping_pair_msg.is_mint_burn.unwrap_or_default(),
-        },
-    )?;
-
-    let res = Response::new()
-        .add_attribute("action", "execute_update_mapping_pair")
-        .add_attribute("denom", mapping_pair_msg.denom)
-        .add_attribute(
-            "new_asset_info",
-            mapping_pair_msg.local_asset_info.to_string(),
-        );
-    Ok(res)
+            max_outstanding: mapping_pair_msg.max_outstanding,
+            status,
+            min_amount: mapping_pair_msg.min_amount,
+            max_amount: mapping_pair_msg.max_amount,
+        },
+    )?;
+    let denom_hash = save_denom_trace(deps.storage, &ibc_denom)?;
+    Ok((ibc_denom, denom_hash))
 }
 
-pub fn execute_delete_mapping_pair(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    mapping_pair_msg: DeletePairMsg,
-) -> Result<Response, ContractError> {
-    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+/// checks the decimals against the cw20 token info, or the display unit of the bank denom metadata, which token
+/// factory denoms register as well. Native denoms without metadata cannot be checked and are accepted as is
+fn assert_asset_decimals(
+    querier: &QuerierWrapper,
+    asset_info: &AssetInfo,
+    decimals: u8,
+) -> Result<(), ContractError> {
+    let on_chain = match asset_info {
+        AssetInfo::Token { contract_addr } => Some(
+            querier
+                .query_wasm_smart::<TokenInfoResponse>(contract_addr, &Cw20QueryMsg::TokenInfo {})?
+                .decimals as u32,
+        ),
+        AssetInfo::NativeToken { denom } => querier
+            .query_denom_metadata(denom)
+            .ok()
+            .and_then(|metadata| {
+                metadata
+                    .denom_units
+                    .into_iter()
+                    .find(|unit| unit.denom == metadata.display)
+            })
+            .map(|unit| unit.exponent),
+    };
+    match on_chain {
+        Some(on_chain) if on_chain != decimals as u32 => {
+            Err(ContractError::MappingDecimalsMismatch {
+                asset: asset_info.to_string(),
+                decimals,
+                on_chain,
+            })
+        }
+        _ => Ok(()),
+    }
+}
 
+/// returns the key of the mapping pair if it can be deleted
+fn assert_mapping_pair_deletable(
+    deps: Deps,
+    env: &Env,
+    mapping_pair_msg: &DeletePairMsg,
+) -> Result<String, ContractError> {
     let ibc_denom = get_key_ics20_ibc_denom(
         &parse_ibc_wasm_port_id(env.contract.address.as_str()),
         &mapping_pair_msg.local_channel_id,
         &mapping_pair_msg.denom,
     );
 
-    ics20_denoms().remove(deps.storage, &ibc_denom)?;
+    // the balance is still needed to process refunds, deprecate the mapping instead
+    let outstanding = CHANNEL_REVERSE_STATE
+        .may_load(
+            deps.storage,
+            (&mapping_pair_msg.local_channel_id, &ibc_denom),
+        )?
+        .map(|state| state.outstanding)
+        .unwrap_or_default();
+    if !outstanding.is_zero() {
+        return Err(ContractError::MappingHasOutstandingBalance {
+            key: ibc_denom,
+            outstanding,
+        });
+    }
 
-    let res = Response::new()
-        .add_attribute("action", "execute_delete_mapping_pair")
-        .add_attribute("local_channel_id", mapping_pair_msg.local_channel_id)
-        .add_attribute("original_denom", mapping_pair_msg.denom);
-    Ok(res)
+    Ok(ibc_denom)
+}
+
+fn remove_mapping_pair(storage: &mut dyn Storage, mapping_key: &str) -> StdResult<()> {
+    ics20_denoms().remove(storage, mapping_key)?;
+    DENOM_TRACES.remove(storage, &DenomTrace::parse(mapping_key).hash());
+    MAPPING_MIGRATIONS.remove(storage, mapping_key);
+    Ok(())
 }
 
 #[entry_point]
 pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
     // we don't need to save anything if migrating from the same version
+    let previous_cfg = CONFIG.may_load(deps.storage)?;
     let cfg = Config {
         default_timeout: msg.default_timeout,
         default_gas_limit: msg.default_gas_limit,
@@ -831,20 +2110,58 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, Co
         converter_contract: ConverterController(msg.converter_contract),
         osor_entrypoint_contract: msg.osor_entrypoint_contract,
         token_factory_addr: msg.token_factory_addr,
+        // the fee shares are set with UpdateConfig, keep them across migrations
+        token_fee_shares: previous_cfg
+            .as_ref()
+            .map(|cfg| cfg.token_fee_shares.clone())
+            .unwrap_or_default(),
+        relayer_fee_shares: previous_cfg
+            .map(|cfg| cfg.relayer_fee_shares)
+            .unwrap_or_default(),
     };
     CONFIG.save(deps.storage, &cfg)?;
 
+    migrate_legacy_token_fees(deps.storage)?;
+    // the mappings are re-saved by pages with ReindexMappings, as all of them may not fit in a single transaction
+
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new())
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Port {} => to_json_binary(&query_port(deps)?),
-        QueryMsg::ListChannels {} => to_json_binary(&query_list(deps)?),
-        QueryMsg::Channel { id } => to_json_binary(&query_channel(deps, id)?),
+        QueryMsg::ListChannels {
+            start_after,
+            limit,
+            connection_id,
+            counterparty_chain_id,
+            denom,
+        } => to_json_binary(&query_list(
+            deps,
+            start_after,
+            limit,
+            connection_id,
+            counterparty_chain_id,
+            denom,
+        )?),
+        QueryMsg::Channel {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_channel(deps, id, start_after, limit)?),
+        QueryMsg::ChannelBalanceAt {
+            channel_id,
+            denom,
+            height,
+        } => to_json_binary(&query_channel_balance_at(deps, channel_id, denom, height)?),
+        QueryMsg::ListChannelBalances {
+            denom,
+            start_after,
+            limit,
+        } => to_json_binary(&query_channel_balances(deps, denom, start_after, limit)?),
         QueryMsg::ChannelWithKey { channel_id, denom } => {
             to_json_binary(&query_channel_with_key(deps, channel_id, denom)?)
         }
@@ -864,10 +2181,91 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::PairMappingsFromAssetInfo { asset_info } => {
             to_json_binary(&get_mappings_from_asset_info(deps.storage, asset_info)?)
         }
+        QueryMsg::PairMappingsByChannel {
+            channel_id,
+            start_after,
+            limit,
+        } => to_json_binary(&list_channel_mappings(
+            deps,
+            channel_id,
+            start_after,
+            limit,
+        )?),
         QueryMsg::Admin {} => to_json_binary(&ADMIN.query_admin(deps)?),
-        QueryMsg::GetTransferTokenFee { remote_token_denom } => {
-            to_json_binary(&TOKEN_FEE.load(deps.storage, &remote_token_denom)?)
+        QueryMsg::GetTransferTokenFee {
+            remote_token_denom,
+            direction,
+            channel_id,
+        } => to_json_binary(
+            &get_token_fee(
+                deps.storage,
+                &due_fee_changes(deps.storage, env.block.time)?,
+                direction,
+                channel_id.as_deref(),
+                &remote_token_denom,
+            )?
+            .unwrap_or_default(),
+        ),
+        QueryMsg::ChannelBalanceOverrides {
+            start_after,
+            limit,
+            order,
+        } => to_json_binary(&list_channel_balance_overrides(
+            deps,
+            start_after,
+            limit,
+            order,
+        )?),
+        QueryMsg::PendingChannelBalanceOverrides { start_after, limit } => to_json_binary(
+            &list_pending_channel_balance_overrides(deps, start_after, limit)?,
+        ),
+        QueryMsg::RelayerFeePricing { asset_info } => {
+            to_json_binary(&get_relayer_fee_pricing(deps.storage, &asset_info)?)
+        }
+        QueryMsg::FeeExemptions {
+            direction,
+            start_after,
+            limit,
+        } => to_json_binary(&list_fee_exemptions(deps, direction, start_after, limit)?),
+        QueryMsg::ChannelUtilization { channel_id } => {
+            to_json_binary(&get_channel_utilization(deps.storage, channel_id)?)
+        }
+        QueryMsg::CheckSolvency { mapping_key } => {
+            to_json_binary(&get_solvency(deps, &env, mapping_key)?)
+        }
+        QueryMsg::Fees {} => to_json_binary(&query_fees(deps)?),
+        QueryMsg::FeeStats {
+            fee_type,
+            epoch,
+            start_after,
+            limit,
+        } => to_json_binary(&list_fee_stats(deps, fee_type, epoch, start_after, limit)?),
+        QueryMsg::FeeEpoch {} => to_json_binary(&query_fee_epoch(deps, &env)?),
+        QueryMsg::SimulateReceive {
+            packet,
+            dest_channel,
+        } => to_json_binary(&simulate_receive(deps, env, packet, dest_channel)?),
+        QueryMsg::DenomTrace { hash } => to_json_binary(&query_denom_trace(deps, hash)?),
+        QueryMsg::ExportMappingPairs { start_after, limit } => {
+            to_json_binary(&export_mapping_pairs(deps, start_after, limit)?)
         }
+        QueryMsg::MappingMigration { mapping_key } => {
+            to_json_binary(&query_mapping_migration(deps, &env, mapping_key)?)
+        }
+        QueryMsg::MappingMigrations { start_after, limit } => {
+            to_json_binary(&list_mapping_migrations(deps, &env, start_after, limit)?)
+        }
+        QueryMsg::DenomTraces { start_after, limit } => {
+            to_json_binary(&list_denom_traces(deps, start_after, limit)?)
+        }
+        QueryMsg::TokenFactoryDenoms { start_after, limit } => {
+            to_json_binary(&list_token_factory_denoms(deps, start_after, limit)?)
+        }
+        QueryMsg::RelayerFeeDeposit { address } => to_json_binary(
+            &RELAYER_FEE_DEPOSITS
+                .may_load(deps.storage, &deps.api.addr_validate(&address)?)?
+                .unwrap_or_default(),
+        ),
     }
 }
 
@@ -877,22 +2275,73 @@ fn query_port(deps: Deps) -> StdResult<PortResponse> {
     Ok(PortResponse { port_id })
 }
 
-fn query_list(deps: Deps) -> StdResult<ListChannelsResponse> {
+fn query_list(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    connection_id: Option<String>,
+    counterparty_chain_id: Option<String>,
+    denom: Option<String>,
+) -> StdResult<ListChannelsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    // channels having a mapping pair of the local asset
+    let denom_channels = denom
+        .map(|denom| -> StdResult<HashSet<String>> {
+            ics20_denoms()
+                .idx
+                .asset_info
+                .prefix(denom)
+                .keys(deps.storage, None, None, Order::Ascending)
+                .map(|key| {
+                    let ibc_denom = key?;
+                    Ok(parse_ibc_channel_without_sanity_checks(&ibc_denom)?.to_string())
+                })
+                .collect()
+        })
+        .transpose()?;
+
     let channels = CHANNEL_INFO
-        .range_raw(deps.storage, None, None, Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| {
+            let Ok((channel_id, info)) = item else {
+                // let the error through
+                return true;
+            };
+            connection_id
+                .as_ref()
+                .map_or(true, |connection_id| info.connection_id.eq(connection_id))
+                && counterparty_chain_id.as_ref().map_or(true, |chain_id| {
+                    info.metadata
+                        .as_ref()
+                        .map_or(false, |metadata| metadata.chain_id.eq(chain_id))
+                })
+                && denom_channels
+                    .as_ref()
+                    .map_or(true, |channels| channels.contains(channel_id))
+        })
+        .take(limit)
         .map(|r| r.map(|(_, v)| v))
         .collect::<StdResult<_>>()?;
     Ok(ListChannelsResponse { channels })
 }
 
 // make public for ibc tests
-pub fn query_channel(deps: Deps, id: String) -> StdResult<ChannelResponse> {
+pub fn query_channel(
+    deps: Deps,
+    id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ChannelResponse> {
     let info = CHANNEL_INFO.load(deps.storage, &id)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
     // this returns Vec<(outstanding, total)>
     let channel_state = CHANNEL_REVERSE_STATE;
     let state = channel_state
         .prefix(&id)
-        .range(deps.storage, None, None, Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
         .map(|r| {
             // this denom is
             r.map(|(denom, v)| {
@@ -912,12 +2361,101 @@ pub fn query_channel(deps: Deps, id: String) -> StdResult<ChannelResponse> {
     })
 }
 
+fn list_channel_mappings(
+    deps: Deps,
+    channel_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListChannelMappingsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let pairs = ics20_denoms()
+        .idx
+        .channel
+        .prefix(channel_id.clone())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (key, pair_mapping) = item?;
+            let balance = CHANNEL_REVERSE_STATE
+                .may_load(deps.storage, (&channel_id, &key))?
+                .unwrap_or_default();
+            Ok(ChannelMappingResponse {
+                key,
+                pair_mapping,
+                balance,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListChannelMappingsResponse { channel_id, pairs })
+}
+
+fn query_channel_balances(
+    deps: Deps,
+    denom: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListChannelBalancesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mut balances = vec![];
+    let mut total_outstanding = Uint128::zero();
+    let mut total_sent = Uint128::zero();
+    let mappings = ics20_denoms().idx.asset_info.prefix(denom.clone()).range(
+        deps.storage,
+        start_after.map(Bound::exclusive),
+        None,
+        Order::Ascending,
+    );
+    for mapping in mappings {
+        if balances.len() == limit {
+            break;
+        }
+        let (ibc_denom, mapping) = mapping?;
+        let channel_id = parse_ibc_channel_without_sanity_checks(&ibc_denom)?;
+        // mappings without a channel balance yet are skipped, so that a page is only short when it is the last one
+        let Some(state) = CHANNEL_REVERSE_STATE.may_load(deps.storage, (channel_id, &ibc_denom))?
+        else {
+            continue;
+        };
+        // each mapping may have different remote decimals, so the totals are summed in local decimals
+        total_outstanding += convert_remote_to_local(
+            state.outstanding,
+            mapping.remote_decimals,
+            mapping.asset_info_decimals,
+        )?;
+        total_sent += convert_remote_to_local(
+            state.total_sent,
+            mapping.remote_decimals,
+            mapping.asset_info_decimals,
+        )?;
+        balances.push(ChannelBalance {
+            channel_id: channel_id.to_string(),
+            ibc_denom,
+            outstanding: state.outstanding,
+            total_sent: state.total_sent,
+        });
+    }
+    Ok(ListChannelBalancesResponse {
+        denom,
+        balances,
+        total_outstanding,
+        total_sent,
+    })
+}
+
 pub fn query_channel_with_key(
     deps: Deps,
     channel_id: String,
     denom: String,
 ) -> StdResult<ChannelWithKeyResponse> {
     let info = CHANNEL_INFO.load(deps.storage, &channel_id)?;
+    let stats = CHANNEL_STATS
+        .may_load(deps.storage, (&channel_id, &denom))?
+        .unwrap_or_default();
     // this returns Vec<(outstanding, total)>
     let (balance, total_sent) = CHANNEL_REVERSE_STATE
         .load(deps.storage, (&channel_id, &denom))
@@ -931,6 +2469,22 @@ pub fn query_channel_with_key(
         info,
         balance,
         total_sent,
+        stats,
+    })
+}
+
+fn query_channel_balance_at(
+    deps: Deps,
+    channel_id: String,
+    denom: String,
+    height: u64,
+) -> StdResult<ChannelBalanceAtResponse> {
+    let state =
+        channel_balance_at_height(deps.storage, &channel_id, &denom, height)?.unwrap_or_default();
+    Ok(ChannelBalanceAtResponse {
+        height,
+        balance: Amount::from_parts(denom.clone(), state.outstanding),
+        total_sent: Amount::from_parts(denom, state.total_sent),
     })
 }
 
@@ -945,26 +2499,61 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         gov_contract: admin.into(),
         relayer_fee_receiver: cfg.relayer_fee_receiver,
         token_fee_receiver: cfg.token_fee_receiver,
-        token_fees: TOKEN_FEE
-            .range(deps.storage, None, None, Order::Ascending)
-            .map(|data_result| {
-                let (token_denom, ratio) = data_result?;
-                Ok(TokenFee { token_denom, ratio })
-            })
-            .collect::<StdResult<_>>()?,
-        relayer_fees: RELAYER_FEE
-            .range(deps.storage, None, None, Order::Ascending)
-            .map(|data_result| {
-                let (prefix, amount) = data_result?;
-                Ok(RelayerFeeResponse { prefix, amount })
-            })
-            .collect::<StdResult<_>>()?,
+        token_fees: list_token_fees(deps.storage)?,
+        relayer_fees: list_relayer_fees(deps.storage)?,
+        channel_relayer_fees: list_channel_relayer_fees(deps.storage)?,
         converter_contract: cfg.converter_contract.addr(),
         osor_entrypoint_contract: cfg.osor_entrypoint_contract,
+        token_fee_shares: cfg.token_fee_shares,
+        relayer_fee_shares: cfg.relayer_fee_shares,
     };
     Ok(res)
 }
 
+fn list_relayer_fees(storage: &dyn Storage) -> StdResult<Vec<RelayerFeeResponse>> {
+    RELAYER_FEE
+        .range(storage, None, None, Order::Ascending)
+        .map(|data_result| {
+            let (prefix, amount) = data_result?;
+            Ok(RelayerFeeResponse { prefix, amount })
+        })
+        .collect()
+}
+
+fn list_channel_relayer_fees(storage: &dyn Storage) -> StdResult<Vec<ChannelRelayerFee>> {
+    CHANNEL_RELAYER_FEE
+        .range(storage, None, None, Order::Ascending)
+        .map(|data_result| {
+            let ((channel_id, destination), fee) = data_result?;
+            Ok(ChannelRelayerFee {
+                channel_id,
+                destination,
+                fee,
+            })
+        })
+        .collect()
+}
+
+fn query_fees(deps: Deps) -> StdResult<FeesResponse> {
+    let scheduled = SCHEDULED_FEE_CHANGES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let ((_, id), scheduled) = item?;
+            Ok(ScheduledFeeChangeResponse {
+                id,
+                effective_at: scheduled.effective_at,
+                change: scheduled.change,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(FeesResponse {
+        token_fees: list_token_fees(deps.storage)?,
+        relayer_fees: list_relayer_fees(deps.storage)?,
+        channel_relayer_fees: list_channel_relayer_fees(deps.storage)?,
+        scheduled,
+    })
+}
+
 fn query_allowed(deps: Deps, contract: String) -> StdResult<AllowedResponse> {
     let addr = deps.api.addr_validate(&contract)?;
     let info = ALLOW_LIST.may_load(deps.storage, &addr)?;
@@ -1032,6 +2621,301 @@ fn list_cw20_mapping(
     Ok(ListMappingResponse { pairs })
 }
 
+fn list_channel_balance_overrides(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order: Option<u8>,
+) -> StdResult<ListChannelBalanceOverridesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let order = map_order(order);
+    let (min, max) = match order {
+        Order::Ascending => (start_after.map(Bound::exclusive), None),
+        Order::Descending => (None, start_after.map(Bound::exclusive)),
+    };
+    let overrides = CHANNEL_BALANCE_OVERRIDES
+        .range(deps.storage, min, max, order)
+        .take(limit)
+        .map(|item| {
+            item.map(
+                |(id, channel_balance_override)| ChannelBalanceOverrideResponse {
+                    id,
+                    channel_balance_override,
+                },
+            )
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListChannelBalanceOverridesResponse {
+        max_delta: OVERRIDE_MAX_DELTA.may_load(deps.storage)?,
+        overrides,
+    })
+}
+
+fn list_pending_channel_balance_overrides(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ListPendingChannelBalanceOverridesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let overrides = PENDING_CHANNEL_BALANCE_OVERRIDES
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            item.map(
+                |(id, pending_override)| PendingChannelBalanceOverrideResponse {
+                    id,
+                    pending_override,
+                },
+            )
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListPendingChannelBalanceOverridesResponse { overrides })
+}
+
+fn list_fee_exemptions(
+    deps: Deps,
+    direction: FeeDirection,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListFeeExemptionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let exemptions = FEE_EXEMPTIONS
+        .prefix(direction.key())
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(address, exemption)| FeeExemptionResponse { address, exemption }))
+        .collect::<StdResult<_>>()?;
+    Ok(ListFeeExemptionsResponse {
+        direction,
+        exemptions,
+    })
+}
+
+fn list_fee_stats(
+    deps: Deps,
+    fee_type: FeeType,
+    epoch: Option<u64>,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<ListFeeStatsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let revenue = match epoch {
+        Some(epoch) => EPOCH_FEE_REVENUE.sub_prefix((epoch, fee_type.key())),
+        None => FEE_REVENUE.sub_prefix(fee_type.key()),
+    };
+    let stats = revenue
+        .range(
+            deps.storage,
+            start_after
+                .as_ref()
+                .map(|(denom, channel_id)| Bound::exclusive((denom.as_str(), channel_id.as_str()))),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            item.map(|((denom, channel_id), amount)| FeeRevenue {
+                denom,
+                channel_id,
+                amount,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListFeeStatsResponse {
+        fee_type,
+        epoch,
+        stats,
+    })
+}
+
+fn query_fee_epoch(deps: Deps, env: &Env) -> StdResult<FeeEpochResponse> {
+    let duration = FEE_EPOCH_DURATION.may_load(deps.storage)?;
+    Ok(FeeEpochResponse {
+        duration,
+        current_epoch: duration.map(|duration| env.block.time.seconds() / duration),
+    })
+}
+
+fn export_mapping_pairs(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ExportMappingPairsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mappings = ics20_denoms()
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let all_token_fees = list_token_fees(deps.storage)?;
+
+    let mut response = ExportMappingPairsResponse {
+        pairs: vec![],
+        token_fees: vec![],
+        states: vec![],
+    };
+    for (key, mapping) in mappings {
+        let (_, channel_id, denom) = parse_ibc_info_without_sanity_checks(&key)?;
+        // the fees of every channel of the denom, and the ones of the channel of the pair
+        for token_fee in all_token_fees.iter().filter(|fee| {
+            fee.token_denom == denom
+                && fee
+                    .channel_id
+                    .as_deref()
+                    .map_or(true, |fee_channel| fee_channel == channel_id)
+        }) {
+            if !response.token_fees.contains(token_fee) {
+                response.token_fees.push(token_fee.clone());
+            }
+        }
+        response.pairs.push(UpdatePairMsg {
+            local_channel_id: channel_id.to_string(),
+            denom: denom.to_string(),
+            local_asset_info: mapping.asset_info,
+            remote_decimals: mapping.remote_decimals,
+            local_asset_info_decimals: mapping.asset_info_decimals,
+            is_mint_burn: Some(mapping.is_mint_burn),
+            max_outstanding: mapping.max_outstanding,
+            override_decimals: None,
+            min_amount: mapping.min_amount,
+            max_amount: mapping.max_amount,
+            status: Some(mapping.status),
+            halted: Some(HALTED_MAPPINGS.has(deps.storage, &key)),
+        });
+        response.states.push(MappingPairState {
+            balance: CHANNEL_REVERSE_STATE
+                .may_load(deps.storage, (channel_id, &key))?
+                .unwrap_or_default(),
+            key,
+        });
+    }
+    Ok(response)
+}
+
+fn mapping_migration_response(
+    deps: Deps,
+    env: &Env,
+    mapping_key: String,
+    migration: MappingMigration,
+) -> StdResult<MappingMigrationResponse> {
+    let mapping = ics20_denoms().load(deps.storage, &mapping_key)?;
+    Ok(MappingMigrationResponse {
+        mapping_key,
+        new_asset_info: mapping.asset_info,
+        in_grace_period: env.block.time < migration.grace_period_end,
+        migration,
+    })
+}
+
+fn query_mapping_migration(
+    deps: Deps,
+    env: &Env,
+    mapping_key: String,
+) -> StdResult<MappingMigrationResponse> {
+    let migration = MAPPING_MIGRATIONS.load(deps.storage, &mapping_key)?;
+    mapping_migration_response(deps, env, mapping_key, migration)
+}
+
+fn list_mapping_migrations(
+    deps: Deps,
+    env: &Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListMappingMigrationsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let migrations = MAPPING_MIGRATIONS
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (mapping_key, migration) = item?;
+            mapping_migration_response(deps, env, mapping_key, migration)
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListMappingMigrationsResponse { migrations })
+}
+
+fn list_token_factory_denoms(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListTokenFactoryDenomsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let denoms = TOKEN_FACTORY_DENOMS
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (denom, subdenom) = item?;
+            let supply = deps.querier.query_supply(&denom)?.amount;
+            Ok(TokenFactoryDenomResponse {
+                denom,
+                subdenom,
+                supply,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListTokenFactoryDenomsResponse { denoms })
+}
+
+/// the hash may be given as ibc/{hash}, in any case
+fn query_denom_trace(deps: Deps, hash: String) -> StdResult<DenomTraceResponse> {
+    let hash = hash.trim_start_matches("ibc/").to_uppercase();
+    let denom_trace = DENOM_TRACES.load(deps.storage, &hash)?;
+    Ok(DenomTraceResponse {
+        mapping_key: denom_trace.full_path(),
+        hash,
+        denom_trace,
+    })
+}
+
+fn list_denom_traces(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListDenomTracesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let traces = DENOM_TRACES
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            item.map(|(hash, denom_trace)| DenomTraceResponse {
+                mapping_key: denom_trace.full_path(),
+                hash,
+                denom_trace,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ListDenomTracesResponse { traces })
+}
+
 fn get_mapping_from_key(deps: Deps, ibc_denom: String) -> StdResult<PairQuery> {
     let result = ics20_denoms().load(deps.storage, &ibc_denom)?;
     Ok(PairQuery {
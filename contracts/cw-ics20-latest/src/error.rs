@@ -2,7 +2,7 @@ use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
-use cosmwasm_std::{OverflowError, StdError};
+use cosmwasm_std::{OverflowError, StdError, Uint128};
 use cw_controllers::AdminError;
 use cw_utils::PaymentError;
 
@@ -36,7 +36,7 @@ pub enum ContractError {
     #[error("Amount larger than 2**64, not supported by ics20 packets")]
     AmountOverflow {},
 
-    #[error("Only supports channel with ibc version ics20-1, got {version}")]
+    #[error("Only supports channel with ibc version ics20-1 or ics20-2, got {version}")]
     InvalidIbcVersion { version: String },
 
     #[error("Only supports unordered channel")]
@@ -86,6 +86,119 @@ pub enum ContractError {
 
     #[error("User cannot close channel")]
     CannotClose {},
+
+    #[error("Forwarding ics20-2 packets through this chain is not supported")]
+    ForwardingNotSupported {},
+
+    #[error("Denom {denom} appears more than once in the packet")]
+    DuplicatePacketDenom { denom: String },
+
+    #[error("Mapping pair {key} is halted")]
+    MappingHalted { key: String },
+
+    #[error("Mapping pair {key} does not accept {direction} transfers")]
+    MappingDirectionDisabled { key: String, direction: String },
+
+    #[error("The batch is empty")]
+    EmptyBatch {},
+
+    #[error("Mapping pair {key} is duplicated in the batch")]
+    DuplicateMappingPair { key: String },
+
+    #[error("Mapping pair {key} is already mapped to this asset")]
+    MappingAssetUnchanged { key: String },
+
+    #[error("Mapping pair {key} is still migrating to its new asset")]
+    MappingMigrationInProgress { key: String },
+
+    #[error("{denom} is not a token factory denom created by the bridge")]
+    UnknownTokenFactoryDenom { denom: String },
+
+    #[error("{denom} is not a denom of the token factory contract")]
+    InvalidTokenFactoryDenom { denom: String },
+
+    #[error("Cannot convert amounts between {remote_decimals} remote decimals and {local_decimals} local decimals")]
+    InvalidMappingDecimals {
+        remote_decimals: u8,
+        local_decimals: u8,
+    },
+
+    #[error("{asset} has {on_chain} decimals on chain, not {decimals}")]
+    MappingDecimalsMismatch {
+        asset: String,
+        decimals: u8,
+        on_chain: u32,
+    },
+
+    #[error("Mapping pair {key} still has an outstanding balance of {outstanding}")]
+    MappingHasOutstandingBalance { key: String, outstanding: Uint128 },
+
+    #[error("Outstanding balance cap exceeded on channel {id}, {denom}")]
+    OutstandingCapExceeded { id: String, denom: String },
+
+    #[error(
+        "Transfer amount {amount} of mapping pair {key} is below the minimum amount {min_amount}"
+    )]
+    TransferAmountTooSmall {
+        key: String,
+        amount: Uint128,
+        min_amount: Uint128,
+    },
+
+    #[error(
+        "Transfer amount {amount} of mapping pair {key} is above the maximum amount {max_amount}"
+    )]
+    TransferAmountTooLarge {
+        key: String,
+        amount: Uint128,
+        max_amount: Uint128,
+    },
+
+    #[error("The minimum amount {min_amount} is above the maximum amount {max_amount}")]
+    InvalidTransferAmountBounds {
+        min_amount: Uint128,
+        max_amount: Uint128,
+    },
+
+    #[error("Channel balance override {id} doesn't exist")]
+    NoSuchOverride { id: u64 },
+
+    #[error("Channel balance override {id} must be confirmed in a later block")]
+    OverrideConfirmationTooEarly { id: u64 },
+
+    #[error("Fee discount cannot exceed 10000 basis points")]
+    InvalidFeeDiscount {},
+
+    #[error("Cannot price the relayer fee in {asset}: {error}")]
+    RelayerFeePricingFailed { asset: String, error: String },
+
+    #[error("The attached {paid}orai does not cover the relayer fee of {required}orai")]
+    InsufficientRelayerFeePayment { required: Uint128, paid: Uint128 },
+
+    #[error("Token fee ratio {nominator}/{denominator} of {denom} is not a whole number of basis points up to 100%")]
+    LossyTokenFeeRatio {
+        denom: String,
+        nominator: u64,
+        denominator: u64,
+    },
+
+    #[error("Fee of {bps} bps is above 100%")]
+    InvalidFeeBps { bps: u16 },
+
+    #[error("Minimum fee {min} is above the maximum fee {max}")]
+    InvalidFeeBounds { min: Uint128, max: Uint128 },
+
+    #[error("Cannot withdraw {amount} ORAI, only {deposit} is deposited")]
+    InsufficientRelayerFeeDeposit { amount: Uint128, deposit: Uint128 },
+
+    #[error("Fee shares must have a positive total weight")]
+    InvalidFeeShares {},
+
+    #[error("The fee epoch duration must be positive")]
+    InvalidFeeEpochDuration {},
+
+    #[error("The swap route of the relayer fee is empty")]
+    EmptySwapRoute {},
 }
 
 impl From<FromUtf8Error> for ContractError {
@@ -1,4 +1,4 @@
-use std::ops::Mul;
+use std::collections::HashSet;
 
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
@@ -7,29 +7,36 @@ use cosmwasm_std::{
     IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcMsg, IbcOrder, IbcPacket,
     IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout,
     Order, QuerierWrapper, Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult,
-    Uint128,
+    Timestamp, Uint128,
 };
 
 use cw20_ics20_msg::helper::{
-    denom_to_asset_info, get_prefix_decode_bech32, parse_asset_info_denom,
+    denom_to_asset_info, get_prefix_decode_bech32, parse_asset_info_denom, parse_ibc_wasm_port_id,
 };
 use cw_storage_plus::Map;
 use oraiswap::asset::AssetInfo;
-use oraiswap::router::{RouterController, SwapOperation};
+use oraiswap::router::RouterController;
 use skip::entry_point::ExecuteMsg as EntryPointExecuteMsg;
 
 use crate::contract::build_mint_mapping_msg;
 use crate::error::{ContractError, Never};
-use crate::msg::ExecuteMsg;
+use crate::msg::{ExecuteMsg, PriceOracleQueryMsg, PriceResponse, SimulateReceiveResponse};
 use crate::state::{
-    get_key_ics20_ibc_denom, ics20_denoms, undo_reduce_channel_balance, ALLOW_LIST, CHANNEL_INFO,
-    CONFIG, RELAYER_FEE, TOKEN_FEE,
+    apply_scheduled_fee_changes, assert_mapping_not_halted, assert_mapping_status,
+    assert_outstanding_cap, assert_transfer_amount, due_fee_changes, effective_fee,
+    get_key_ics20_ibc_denom, get_relayer_fee_pricing, get_token_fee, ics20_denoms,
+    record_fee_revenue, undo_reduce_channel_balance, update_channel_stats, FeeChange, FeeUpdate,
+    PricingFailure, RelayerFeePriceSource, ALLOW_LIST, CHANNEL_INFO, CHANNEL_RELAYER_FEE, CONFIG,
+    FEE_EXEMPTIONS, RELAYER_FEE,
 };
 use cw20_ics20_msg::amount::{convert_remote_to_local, Amount};
-use cw20_ics20_msg::msg::FeeData;
-use cw20_ics20_msg::state::{ChannelInfo, Ratio};
+use cw20_ics20_msg::msg::{Denom, DenomTrace, FeeData, Hop, Ics20PacketV2, ReferralMemo, Token};
+use cw20_ics20_msg::state::{
+    ChannelInfo, FeeDirection, FeeRecipient, FeeSchedule, FeeShare, MAX_BPS,
+};
 
 pub const ICS20_VERSION: &str = "ics20-1";
+pub const ICS20_VERSION_V2: &str = "ics20-2";
 pub const ICS20_ORDERING: IbcOrder = IbcOrder::Unordered;
 pub const ORAIBRIDGE_PREFIX: &str = "oraib";
 
@@ -68,6 +75,58 @@ impl Ics20Packet {
     }
 }
 
+/// Decodes the packet data based on the ics20 version negotiated on the channel: JSON for ics20-1 and protobuf for
+/// ics20-2. An ics20-2 packet is flattened into one Ics20Packet per token, with the denom trace encoded as a path
+pub fn parse_packet_data(
+    storage: &dyn Storage,
+    channel_id: &str,
+    data: &Binary,
+) -> Result<Vec<Ics20Packet>, ContractError> {
+    let version = CHANNEL_INFO
+        .may_load(storage, channel_id)?
+        .and_then(|info| info.version);
+    if version.as_deref() != Some(ICS20_VERSION_V2) {
+        return Ok(vec![from_json(data)?]);
+    }
+
+    let packet = Ics20PacketV2::decode(data)?;
+    if packet.tokens.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+    // we do not act as an intermediate hop of ics20-2 forwarding
+    if let Some(forwarding) = packet.forwarding {
+        if !forwarding.hops.is_empty() {
+            return Err(ContractError::ForwardingNotSupported {});
+        }
+    }
+    // the caps are checked token by token against the stored balances, so a denom may only appear once
+    let mut denoms = HashSet::new();
+    for token in &packet.tokens {
+        let denom = token.denom.path();
+        if !denoms.insert(denom.clone()) {
+            return Err(ContractError::DuplicatePacketDenom { denom });
+        }
+    }
+    let memo = if packet.memo.is_empty() {
+        None
+    } else {
+        Some(packet.memo)
+    };
+    Ok(packet
+        .tokens
+        .into_iter()
+        .map(|token| {
+            Ics20Packet::new(
+                token.amount,
+                token.denom.path(),
+                &packet.sender,
+                &packet.receiver,
+                memo.clone(),
+            )
+        })
+        .collect())
+}
+
 /// This is a generic ICS acknowledgement format.
 /// Proto defined here: https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147
 /// This is compatible with the JSON serialization
@@ -90,6 +149,8 @@ pub fn ack_fail(err: String) -> Binary {
 }
 
 pub const NATIVE_RECEIVE_ID: u64 = 1338;
+/// relayer of the simulated packets, who earns their inbound relayer fees
+pub const SIMULATED_RELAYER: &str = "relayer";
 pub const REFUND_FAILURE_ID: u64 = 1340;
 pub const UNIVERSAL_SWAP_ERROR_ID: u64 = 1344;
 
@@ -151,6 +212,8 @@ pub fn ibc_channel_connect(
         id: channel.endpoint.channel_id,
         counterparty_endpoint: channel.counterparty_endpoint,
         connection_id: channel.connection_id,
+        version: Some(channel.version),
+        metadata: None,
     };
     CHANNEL_INFO.save(deps.storage, &info.id, &info)?;
 
@@ -161,13 +224,14 @@ fn enforce_order_and_version(
     channel: &IbcChannel,
     counterparty_version: Option<&str>,
 ) -> Result<(), ContractError> {
-    if channel.version != ICS20_VERSION {
+    if channel.version != ICS20_VERSION && channel.version != ICS20_VERSION_V2 {
         return Err(ContractError::InvalidIbcVersion {
             version: channel.version.clone(),
         });
     }
+    // both ends must speak the same ics20 version
     if let Some(version) = counterparty_version {
-        if version != ICS20_VERSION {
+        if version != channel.version {
             return Err(ContractError::InvalidIbcVersion {
                 version: version.to_string(),
             });
@@ -223,8 +287,9 @@ pub fn ibc_packet_receive(
     })
 }
 
-// Returns local denom if the denom is an encoded voucher from the expected endpoint
-// Otherwise, error
+// Returns the denom without the endpoint hop and false if the denom is a voucher of the expected endpoint.
+// Returns the full denom and true if it is native to the remote chain, or a voucher the remote chain received
+// through other hops, eg: transfer/channel-5/uatom. Otherwise, error
 pub fn parse_voucher_denom<'a>(
     voucher_denom: &'a str,
     remote_endpoint: &IbcEndpoint,
@@ -235,22 +300,25 @@ pub fn parse_voucher_denom<'a>(
     if split_denom.len() == 1 {
         return Ok((voucher_denom, true));
     }
-    if split_denom.len() != 3 {
+    if split_denom.len() != 3 || split_denom[2].is_empty() {
         return Err(ContractError::NoForeignTokens {});
     }
+    if split_denom[0] == remote_endpoint.port_id && split_denom[1] == remote_endpoint.channel_id {
+        return Ok((split_denom[2], false));
+    }
+    // the first hop names a channel of the remote chain other than the endpoint
+    if !DenomTrace::parse(voucher_denom).path.is_empty() {
+        return Ok((voucher_denom, true));
+    }
     // a few more sanity checks
     if split_denom[0] != remote_endpoint.port_id {
         return Err(ContractError::FromOtherPort {
             port: split_denom[0].into(),
         });
     }
-    if split_denom[1] != remote_endpoint.channel_id {
-        return Err(ContractError::FromOtherChannel {
-            channel: split_denom[1].into(),
-        });
-    }
-
-    Ok((split_denom[2], false))
+    Err(ContractError::FromOtherChannel {
+        channel: split_denom[1].into(),
+    })
 }
 
 // Returns local denom if the denom is an encoded voucher from the expected endpoint
@@ -299,25 +367,129 @@ fn do_ibc_packet_receive(
     packet: &IbcPacket,
     relayer: &str,
 ) -> Result<IbcReceiveResponse, ContractError> {
-    let msg: Ics20Packet = from_json(&packet.data)?;
+    apply_scheduled_fee_changes(storage, env.block.time)?;
+    let msgs = parse_packet_data(storage, &packet.dest.channel_id, &packet.data)?;
+    let (res, fees) =
+        plan_ibc_packet_receive(storage, api, querier, env.clone(), packet, msgs, relayer)?;
+
+    // state is not reverted when the packet is acked with an error, so the fees are only recorded once every token succeeded
+    for (ibc_denom, fee_data) in fees {
+        update_channel_stats(storage, &packet.dest.channel_id, &ibc_denom, |stats| {
+            stats.total_token_fee = stats
+                .total_token_fee
+                .checked_add(fee_data.token_fee.amount())?;
+            stats.total_relayer_fee = stats
+                .total_relayer_fee
+                .checked_add(fee_data.relayer_fee.amount())?;
+            Ok(())
+        })?;
+        record_fee_revenue(storage, env.block.time, &packet.dest.channel_id, &fee_data)?;
+    }
 
-    // If the token originated on the remote chain, it looks like "ucosm".
-    // If it originated on our chain, it looks like "port/channel/ucosm".
-    let denom = parse_voucher_denom(&msg.denom, &packet.src)?;
+    Ok(res)
+}
 
-    // if denom is native, we handle it the native way
-    if denom.1 {
-        return handle_ibc_packet_receive_native_remote_chain(
-            storage, api, querier, env, denom.0, packet, &msg, relayer,
-        );
+// builds the response to the tokens of a packet without writing state, so that the receive can be simulated.
+// Returns the fees charged per ibc denom
+fn plan_ibc_packet_receive(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    querier: &QuerierWrapper,
+    env: Env,
+    packet: &IbcPacket,
+    msgs: Vec<Ics20Packet>,
+    relayer: &str,
+) -> Result<(IbcReceiveResponse, Vec<(String, FeeData)>), ContractError> {
+    // an ics20-2 packet may carry many tokens. If any of them fails, the whole packet is acked with an error
+    let mut res = IbcReceiveResponse::new().set_ack(ack_success());
+    let mut fees: Vec<(String, FeeData)> = vec![];
+    for (index, msg) in msgs.into_iter().enumerate() {
+        // If the token originated on the remote chain, it looks like "ucosm".
+        // If it originated on our chain, it looks like "port/channel/ucosm".
+        let denom = parse_voucher_denom(&msg.denom, &packet.src)?;
+
+        // only native denoms of the remote chain are supported
+        if !denom.1 {
+            return Err(ContractError::Std(StdError::generic_err("Not supported")));
+        }
+        let (token_res, fee_data) = handle_ibc_packet_receive_native_remote_chain(
+            storage,
+            api,
+            querier,
+            env.clone(),
+            denom.0,
+            packet,
+            &msg,
+            relayer,
+            // the relayer relays the packet once, so its fee is only charged on the first token
+            if index == 0 {
+                RelayerFeeMode::Deducted
+            } else {
+                RelayerFeeMode::Waived
+            },
+        )?;
+        res = res
+            .add_submessages(token_res.messages)
+            .add_attributes(token_res.attributes)
+            .add_events(token_res.events);
+        fees.push((
+            get_key_ics20_ibc_denom(&packet.dest.port_id, &packet.dest.channel_id, denom.0),
+            fee_data,
+        ));
     }
+    Ok((res, fees))
+}
 
-    Err(ContractError::Std(StdError::generic_err("Not supported")))
+/// runs the receive of the packet on the local channel without applying it. Returns the acknowledgement the
+/// packet would get, with the fees and the messages planned on success. Scheduled fee changes that became
+/// effective are read on top of the stored fees without being applied
+pub fn simulate_receive(
+    deps: Deps,
+    env: Env,
+    packet: Ics20Packet,
+    dest_channel: String,
+) -> StdResult<SimulateReceiveResponse> {
+    let channel_info = CHANNEL_INFO.load(deps.storage, &dest_channel)?;
+    let ibc_packet = IbcPacket::new(
+        to_json_binary(&packet)?,
+        channel_info.counterparty_endpoint,
+        IbcEndpoint {
+            port_id: parse_ibc_wasm_port_id(env.contract.address.as_str()),
+            channel_id: dest_channel,
+        },
+        0,
+        env.block.time.into(),
+    );
+    let plan = plan_ibc_packet_receive(
+        deps.storage,
+        deps.api,
+        &deps.querier,
+        env,
+        &ibc_packet,
+        vec![packet],
+        SIMULATED_RELAYER,
+    );
+    Ok(match plan {
+        Ok((res, fees)) => SimulateReceiveResponse {
+            ack: ack_success(),
+            error: None,
+            fees: fees.into_iter().map(|(_, fee_data)| fee_data).collect(),
+            messages: res.messages,
+            attributes: res.attributes,
+        },
+        Err(err) => SimulateReceiveResponse {
+            ack: ack_fail(err.to_string()),
+            error: Some(err.to_string()),
+            fees: vec![],
+            messages: vec![],
+            attributes: vec![],
+        },
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
 fn handle_ibc_packet_receive_native_remote_chain(
-    storage: &mut dyn Storage,
+    storage: &dyn Storage,
     api: &dyn Api,
     querier: &QuerierWrapper,
     env: Env,
@@ -325,7 +497,8 @@ fn handle_ibc_packet_receive_native_remote_chain(
     packet: &IbcPacket,
     msg: &Ics20Packet,
     relayer: &str,
-) -> Result<IbcReceiveResponse, ContractError> {
+    relayer_fee_mode: RelayerFeeMode,
+) -> Result<(IbcReceiveResponse, FeeData), ContractError> {
     let config = CONFIG.load(storage)?;
     let mut cosmos_msgs: Vec<CosmosMsg> = vec![];
     let ibc_packet_amount = msg.amount.to_string();
@@ -344,6 +517,17 @@ fn handle_ibc_packet_receive_native_remote_chain(
     let pair_mapping = ics20_denoms()
         .load(storage, &ibc_denom)
         .map_err(|_| ContractError::NotOnMappingList {})?;
+    assert_mapping_not_halted(storage, &ibc_denom)?;
+    assert_mapping_status(&ibc_denom, &pair_mapping, FeeDirection::Inbound)?;
+    assert_transfer_amount(&ibc_denom, &pair_mapping, msg.amount)?;
+    // checked here as well so that the packet is acked with an error instead of failing the increase balance msg
+    assert_outstanding_cap(
+        storage,
+        &packet.dest.channel_id,
+        &ibc_denom,
+        &pair_mapping,
+        msg.amount,
+    )?;
     let initial_receive_asset_info = pair_mapping.asset_info;
     let to_send = Amount::from_parts(
         parse_asset_info_denom(&initial_receive_asset_info),
@@ -373,34 +557,49 @@ fn handle_ibc_packet_receive_native_remote_chain(
         storage,
         querier,
         api,
+        FeeDirection::Inbound,
+        &packet.dest.channel_id,
+        &msg.sender,
         &msg.sender,
         &msg.denom,
         to_send.clone(),
+        relayer_fee_mode,
         &config.swap_router_contract,
+        env.block.time,
     )?;
 
+    let referral = msg.memo.as_deref().and_then(ReferralMemo::parse);
     // if the fees have consumed all user funds, we send all the fees to our token fee receiver
     if fee_data.deducted_amount.is_zero() {
-        return Ok(IbcReceiveResponse::new()
+        let res = IbcReceiveResponse::new()
             .set_ack(ack_success())
             .add_messages(cosmos_msgs)
-            .add_message(to_send.send_amount(config.token_fee_receiver.into_string(), None))
+            .add_messages(build_fee_msgs(
+                api,
+                &to_send,
+                &config.token_fee_shares,
+                config.token_fee_receiver.as_str(),
+                referral.as_deref(),
+            ))
             .add_attributes(attributes)
-            .add_attributes(vec![
-                ("token_fee", &fee_data.token_fee.amount().to_string()),
-                ("relayer_fee", &fee_data.relayer_fee.amount().to_string()),
-            ]));
-    }
-    if !fee_data.token_fee.is_empty() {
-        cosmos_msgs.push(
-            fee_data
-                .token_fee
-                .send_amount(config.token_fee_receiver.into_string(), None),
-        )
-    }
-    if !fee_data.relayer_fee.is_empty() {
-        cosmos_msgs.push(fee_data.relayer_fee.send_amount(relayer.to_string(), None))
+            .add_attributes(fee_data.attributes());
+        return Ok((res, fee_data));
     }
+    cosmos_msgs.extend(build_fee_msgs(
+        api,
+        &fee_data.token_fee,
+        &config.token_fee_shares,
+        config.token_fee_receiver.as_str(),
+        referral.as_deref(),
+    ));
+    // the relayer of the packet is the default receiver of the inbound relayer fees
+    cosmos_msgs.extend(build_fee_msgs(
+        api,
+        &fee_data.relayer_fee,
+        &config.relayer_fee_shares,
+        relayer,
+        referral.as_deref(),
+    ));
     let new_deducted_to_send = Amount::from_parts(to_send.denom(), fee_data.deducted_amount);
     let sub_msgs = get_follow_up_msgs(
         storage,
@@ -415,16 +614,53 @@ fn handle_ibc_packet_receive_native_remote_chain(
         .add_messages(cosmos_msgs)
         .add_submessages(sub_msgs)
         .add_attributes(attributes)
-        .add_attributes(vec![
-            ("token_fee", &fee_data.token_fee.amount().to_string()),
-            ("relayer_fee", &fee_data.relayer_fee.amount().to_string()),
-        ]);
+        .add_attributes(fee_data.attributes());
 
-    Ok(res)
+    Ok((res, fee_data))
+}
+
+// splits a fee among the weighted shares of its type. Referral shares without a valid referral and the rounding dust go to the default receiver
+pub fn build_fee_msgs(
+    api: &dyn Api,
+    fee: &Amount,
+    shares: &[FeeShare],
+    default_receiver: &str,
+    referral: Option<&str>,
+) -> Vec<CosmosMsg> {
+    if fee.is_empty() {
+        return vec![];
+    }
+    let total_weight: u64 = shares.iter().map(|share| share.weight).sum();
+    if total_weight == 0 {
+        return vec![fee.send_amount(default_receiver.to_string(), None)];
+    }
+    let referral = referral.and_then(|referral| api.addr_validate(referral).ok());
+    let mut remaining = fee.amount();
+    let mut msgs = vec![];
+    for share in shares {
+        let receiver = match (&share.recipient, &referral) {
+            (FeeRecipient::Address(address), _) => address.clone(),
+            (FeeRecipient::Referral, Some(referral)) => referral.to_string(),
+            (FeeRecipient::Referral, None) => continue,
+        };
+        let amount = fee.amount().multiply_ratio(share.weight, total_weight);
+        if amount.is_zero() {
+            continue;
+        }
+        remaining -= amount;
+        msgs.push(Amount::from_parts(fee.denom(), amount).send_amount(receiver, None));
+    }
+    if !remaining.is_zero() {
+        msgs.push(
+            Amount::from_parts(fee.denom(), remaining)
+                .send_amount(default_receiver.to_string(), None),
+        );
+    }
+    msgs
 }
 
 pub fn get_follow_up_msgs(
-    storage: &mut dyn Storage,
+    storage: &dyn Storage,
     api: &dyn Api,
     orai_receiver: String,
     to_send: Amount,
@@ -435,8 +671,11 @@ pub fn get_follow_up_msgs(
     let send_only_sub_msg =
         SubMsg::reply_on_error(to_send.send_amount(orai_receiver, None), NATIVE_RECEIVE_ID);
     if let Some(memo) = memo {
-        // Do not call universal swap if the memo is empty or is an address.
-        if memo.is_empty() || api.addr_validate(&memo).is_ok() {
+        // Do not call universal swap if the memo is empty, is an address or only names a referral.
+        if memo.is_empty()
+            || api.addr_validate(&memo).is_ok()
+            || ReferralMemo::parse(&memo).is_some()
+        {
             sub_msgs.push(send_only_sub_msg);
         } else {
             let swap_then_post_action_msg = to_send.send_amount(
@@ -473,23 +712,57 @@ pub fn check_gas_limit(deps: Deps, amount: &Amount) -> Result<Option<u64>, Contr
     }
 }
 
+/// how the relayer fee of a transfer is charged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayerFeeMode {
+    /// deducted from the transferred tokens
+    Deducted,
+    /// paid with the ORAI attached by the sender, nothing is deducted from the transferred tokens
+    PaidInOrai,
+    /// not charged, eg: already charged on another token of the same packet
+    Waived,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn process_deduct_fee(
-    storage: &mut dyn Storage,
+    storage: &dyn Storage,
     querier: &QuerierWrapper,
     api: &dyn Api,
+    direction: FeeDirection,
+    local_channel_id: &str,
+    fee_payer: &str,
     remote_sender: &str,
     remote_token_denom: &str,
     local_amount: Amount, // local amount
+    relayer_fee_mode: RelayerFeeMode,
     swap_router_contract: &RouterController,
+    now: Timestamp,
 ) -> StdResult<FeeData> {
     let local_denom = local_amount.denom();
-    let (deducted_amount, token_fee) =
-        deduct_token_fee(storage, remote_token_denom, local_amount.amount())?;
+    // the due scheduled fee changes are not applied yet when simulating a receive
+    let due_fee_changes = due_fee_changes(storage, now)?;
+    let exemption = FEE_EXEMPTIONS
+        .may_load(storage, (direction.key(), fee_payer))?
+        .unwrap_or_default();
+    let (_, token_fee) = deduct_token_fee(
+        storage,
+        &due_fee_changes,
+        direction,
+        local_channel_id,
+        remote_token_denom,
+        local_amount.amount(),
+    )?;
+    let (token_fee, token_fee_discount) =
+        apply_fee_discount(token_fee, exemption.token_fee_discount_bps);
+    let deducted_amount = local_amount.amount().checked_sub(token_fee)?;
 
     let mut fee_data = FeeData {
         deducted_amount,
         token_fee: Amount::from_parts(local_denom.clone(), token_fee),
         relayer_fee: Amount::from_parts(local_denom.clone(), Uint128::zero()),
+        token_fee_discount,
+        relayer_fee_discount: Uint128::zero(),
+        relayer_fee_price_source: None,
     };
     // if after token fee, the deducted amount is 0 then we deduct all to token fee
     if deducted_amount.is_zero() {
@@ -497,18 +770,45 @@ pub fn process_deduct_fee(
         return Ok(fee_data);
     }
 
+    if relayer_fee_mode == RelayerFeeMode::Waived {
+        return Ok(fee_data);
+    }
+    if relayer_fee_mode == RelayerFeeMode::PaidInOrai {
+        // the sender attached ORAI to pay the relayer fee, nothing is deducted from the transferred tokens
+        let relayer_fee = get_relayer_fee(
+            storage,
+            &due_fee_changes,
+            local_channel_id,
+            remote_sender,
+            remote_token_denom,
+        )?
+        .unwrap_or_default();
+        let (relayer_fee, relayer_fee_discount) =
+            apply_fee_discount(relayer_fee, exemption.relayer_fee_discount_bps);
+        fee_data.relayer_fee = Amount::from_parts("orai".to_string(), relayer_fee);
+        fee_data.relayer_fee_discount = relayer_fee_discount;
+        fee_data.relayer_fee_price_source = Some("orai_payment".to_string());
+        return Ok(fee_data);
+    }
+
     // simulate for relayer fee
     let ask_asset_info = denom_to_asset_info(api, &local_amount.raw_denom());
 
-    let relayer_fee = deduct_relayer_fee(
+    let (relayer_fee, relayer_fee_price_source) = deduct_relayer_fee(
         storage,
+        &due_fee_changes,
         api,
         querier,
+        local_channel_id,
         remote_sender,
         remote_token_denom,
         ask_asset_info,
         swap_router_contract,
     )?;
+    let (relayer_fee, relayer_fee_discount) =
+        apply_fee_discount(relayer_fee, exemption.relayer_fee_discount_bps);
+    fee_data.relayer_fee_discount = relayer_fee_discount;
+    fee_data.relayer_fee_price_source = relayer_fee_price_source;
 
     fee_data.deducted_amount = deducted_amount.checked_sub(relayer_fee).unwrap_or_default();
     fee_data.relayer_fee = Amount::from_parts(local_denom.clone(), relayer_fee);
@@ -519,12 +819,27 @@ pub fn process_deduct_fee(
     Ok(fee_data)
 }
 
+// returns the discounted fee and the discount
+pub fn apply_fee_discount(fee: Uint128, discount_bps: u16) -> (Uint128, Uint128) {
+    let discount = fee.multiply_ratio(discount_bps.min(MAX_BPS), MAX_BPS);
+    (fee - discount, discount)
+}
+
 pub fn deduct_token_fee(
-    storage: &mut dyn Storage,
+    storage: &dyn Storage,
+    due_fee_changes: &[FeeChange],
+    direction: FeeDirection,
+    local_channel_id: &str,
     remote_token_denom: &str,
     amount: Uint128,
 ) -> StdResult<(Uint128, Uint128)> {
-    let token_fee = TOKEN_FEE.may_load(storage, remote_token_denom)?;
+    let token_fee = get_token_fee(
+        storage,
+        due_fee_changes,
+        Some(direction),
+        Some(local_channel_id),
+        remote_token_denom,
+    )?;
     if let Some(token_fee) = token_fee {
         let fee = deduct_fee(token_fee, amount);
         let new_deducted_amount = amount.checked_sub(fee)?;
@@ -533,20 +848,105 @@ pub fn deduct_token_fee(
     Ok((amount, Uint128::zero()))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn deduct_relayer_fee(
-    storage: &mut dyn Storage,
+    storage: &dyn Storage,
+    due_fee_changes: &[FeeChange],
     _api: &dyn Api,
     querier: &QuerierWrapper,
+    local_channel_id: &str,
     remote_address: &str,
     remote_token_denom: &str,
     ask_asset_info: AssetInfo,
     swap_router_contract: &RouterController,
-) -> StdResult<Uint128> {
+) -> StdResult<(Uint128, Option<String>)> {
+    // no need to deduct fee if no fee is found in the mapping
+    let Some(offer_amount) = get_relayer_fee(
+        storage,
+        due_fee_changes,
+        local_channel_id,
+        remote_address,
+        remote_token_denom,
+    )?
+    else {
+        return Ok((Uint128::zero(), None));
+    };
+    let (fee, source) = get_relayer_fee_in_asset(
+        storage,
+        querier,
+        offer_amount,
+        swap_router_contract,
+        ask_asset_info,
+    )?;
+    Ok((fee, Some(source.to_string())))
+}
+
+// resolves the relayer fee in ORAI, from the channel metadata first and then from the legacy prefix fees. The due fee
+// changes are read on top of the stored fees
+pub fn get_relayer_fee(
+    storage: &dyn Storage,
+    due_fee_changes: &[FeeChange],
+    local_channel_id: &str,
+    remote_address: &str,
+    remote_token_denom: &str,
+) -> StdResult<Option<Uint128>> {
+    if let Some(relayer_fee) = get_channel_relayer_fee(
+        storage,
+        due_fee_changes,
+        local_channel_id,
+        remote_token_denom,
+    )? {
+        return Ok(Some(relayer_fee));
+    }
+    let prefix = get_legacy_relayer_fee_prefix(remote_address, remote_token_denom);
+    Ok(effective_fee(
+        RELAYER_FEE.may_load(storage, &prefix)?,
+        due_fee_changes,
+        |update| match update {
+            FeeUpdate::SetRelayerFee(fee) if fee.prefix == prefix => Some(Some(fee.fee)),
+            FeeUpdate::RemoveRelayerFee { prefix: removed } if *removed == prefix => Some(None),
+            _ => None,
+        },
+    ))
+}
+
+// resolves the relayer fee from the metadata of the channel. None if the channel has no metadata or no fee for the destination
+pub fn get_channel_relayer_fee(
+    storage: &dyn Storage,
+    due_fee_changes: &[FeeChange],
+    local_channel_id: &str,
+    remote_token_denom: &str,
+) -> StdResult<Option<Uint128>> {
+    let Some(metadata) = CHANNEL_INFO
+        .may_load(storage, local_channel_id)?
+        .and_then(|info| info.metadata)
+    else {
+        return Ok(None);
+    };
+    let destination = metadata.destination(remote_token_denom);
+    Ok(effective_fee(
+        CHANNEL_RELAYER_FEE.may_load(storage, (local_channel_id, &destination))?,
+        due_fee_changes,
+        |update| match update {
+            FeeUpdate::SetChannelRelayerFee(fee)
+                if fee.channel_id == local_channel_id && fee.destination == destination =>
+            {
+                Some(Some(fee.fee))
+            }
+            FeeUpdate::RemoveChannelRelayerFee {
+                channel_id,
+                destination: removed,
+            } if channel_id == local_channel_id && *removed == destination => Some(None),
+            _ => None,
+        },
+    ))
+}
+
+// legacy fallback for channels without metadata: guess the fee key from the remote address
+pub fn get_legacy_relayer_fee_prefix(remote_address: &str, remote_token_denom: &str) -> String {
     // this is bech32 prefix of sender from other chains. Should not error because we are in the cosmos ecosystem. Every address should have prefix
     // evm case, need to filter remote token denom since prefix is always oraib
-    let prefix_result = get_prefix_decode_bech32(remote_address);
-
-    let prefix: String = match prefix_result {
+    match get_prefix_decode_bech32(remote_address) {
         Err(_) => convert_remote_denom_to_evm_prefix(remote_token_denom),
         Ok(prefix) => {
             if prefix.eq(ORAIBRIDGE_PREFIX) {
@@ -555,57 +955,69 @@ pub fn deduct_relayer_fee(
                 prefix
             }
         }
-    };
-    let relayer_fee = RELAYER_FEE.may_load(storage, &prefix)?;
-    // no need to deduct fee if no fee is found in the mapping
-    Ok(relayer_fee
-        .map(|offer_amount| {
-            get_swap_token_amount_out_from_orai(
-                querier,
-                offer_amount,
-                swap_router_contract,
-                ask_asset_info,
-            )
-        })
-        .unwrap_or_default())
+    }
 }
 
-pub fn deduct_fee(token_fee: Ratio, amount: Uint128) -> Uint128 {
-    // ignore case where denominator is zero since we cannot divide with 0
-    if token_fee.denominator == 0 {
-        return Uint128::zero();
+pub fn deduct_fee(token_fee: FeeSchedule, amount: Uint128) -> Uint128 {
+    let mut fee = amount
+        .multiply_ratio(token_fee.bps, MAX_BPS)
+        .saturating_add(token_fee.flat)
+        .max(token_fee.min);
+    if let Some(max) = token_fee.max {
+        fee = fee.min(max);
     }
-    amount.mul(Decimal::from_ratio(
-        token_fee.nominator,
-        token_fee.denominator,
-    ))
+    // the fee can never exceed the transferred amount
+    fee.min(amount)
 }
 
-pub fn get_swap_token_amount_out_from_orai(
+// prices the relayer fee, set in ORAI, in the ask asset. Returns the fee and the name of the source used
+pub fn get_relayer_fee_in_asset(
+    storage: &dyn Storage,
     querier: &QuerierWrapper,
     offer_amount: Uint128,
     swap_router_contract: &RouterController,
     ask_asset_info: AssetInfo,
-) -> Uint128 {
+) -> StdResult<(Uint128, &'static str)> {
     let orai_asset_info = AssetInfo::NativeToken {
         denom: "orai".to_string(),
     };
     if ask_asset_info.eq(&orai_asset_info) {
-        return offer_amount;
+        return Ok((offer_amount, "orai"));
+    }
+    let pricing = get_relayer_fee_pricing(storage, &ask_asset_info)?;
+    let price = match &pricing.source {
+        RelayerFeePriceSource::SwapRoute { operations } => swap_router_contract
+            .simulate_swap(querier, offer_amount, operations.clone())
+            .map(|data| data.amount),
+        RelayerFeePriceSource::StaticPrice { price } => mul_price(offer_amount, *price),
+        RelayerFeePriceSource::Oracle { contract } => querier
+            .query_wasm_smart::<PriceResponse>(
+                contract,
+                &PriceOracleQueryMsg::Price {
+                    base: orai_asset_info.to_string(),
+                    quote: ask_asset_info.to_string(),
+                },
+            )
+            .and_then(|res| mul_price(offer_amount, res.price)),
+    };
+    match (price, pricing.on_failure) {
+        (Ok(fee), _) => Ok((fee, pricing.source.name())),
+        (Err(_), PricingFailure::Waive) => Ok((Uint128::zero(), "waived")),
+        (Err(_), PricingFailure::FallbackFee { amount }) => Ok((amount, "fallback")),
+        (Err(err), PricingFailure::Reject) => Err(StdError::generic_err(
+            ContractError::RelayerFeePricingFailed {
+                asset: ask_asset_info.to_string(),
+                error: err.to_string(),
+            }
+            .to_string(),
+        )),
     }
-    // TODO: switch this to mixed router?
-    swap_router_contract
-        .simulate_swap(
-            querier,
-            offer_amount,
-            vec![SwapOperation::OraiSwap {
-                offer_asset_info: orai_asset_info,
-                // always swap with orai. If it does not share a pool with ORAI => ignore, no fee
-                ask_asset_info,
-            }],
-        )
-        .map(|data| data.amount)
-        .unwrap_or_default()
+}
+
+fn mul_price(amount: Uint128, price: Decimal) -> StdResult<Uint128> {
+    amount
+        .checked_mul_floor(price)
+        .map_err(|err| StdError::generic_err(err.to_string()))
 }
 
 pub fn convert_remote_denom_to_evm_prefix(remote_denom: &str) -> String {
@@ -643,7 +1055,7 @@ pub fn collect_fee_msgs(
 /// This entrypoint is called when we receive an acknowledgement packet from a remote chain
 pub fn ibc_packet_ack(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketAckMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     // Design decision: should we trap error like in receive?
@@ -651,7 +1063,7 @@ pub fn ibc_packet_ack(
     let ics20msg: Ics20Ack = from_json(&msg.acknowledgement.data)?;
     match ics20msg {
         Ics20Ack::Result(_) => on_packet_success(deps, msg.original_packet),
-        Ics20Ack::Error(err) => on_packet_failure(deps, msg.original_packet, err),
+        Ics20Ack::Error(err) => on_packet_failure(deps, env, msg.original_packet, err, false),
     }
 }
 
@@ -659,32 +1071,36 @@ pub fn ibc_packet_ack(
 /// return fund to original sender (same as failure in ibc_packet_ack)
 pub fn ibc_packet_timeout(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketTimeoutMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     let packet = msg.packet;
-    on_packet_failure(deps, packet, "timeout".to_string())
+    on_packet_failure(deps, env, packet, "timeout".to_string(), true)
 }
 
 // update the balance stored on this (channel, denom) index
-fn on_packet_success(_deps: DepsMut, packet: IbcPacket) -> Result<IbcBasicResponse, ContractError> {
-    let msg: Ics20Packet = from_json(packet.data)?;
-
-    // similar event messages like ibctransfer module
-    let attributes = vec![
-        attr("action", "acknowledge"),
-        attr("sender", &msg.sender),
-        attr("receiver", &msg.receiver),
-        attr("denom", &msg.denom),
-        attr("amount", msg.amount),
-        attr("success", "true"),
-    ];
-
-    // if let Some(memo) = msg.memo {
-    //     attributes.push(attr("memo", memo));
-    // }
+fn on_packet_success(deps: DepsMut, packet: IbcPacket) -> Result<IbcBasicResponse, ContractError> {
+    let msgs = parse_packet_data(deps.storage, &packet.src.channel_id, &packet.data)?;
+
+    let mut res = IbcBasicResponse::new();
+    for msg in msgs {
+        // similar event messages like ibctransfer module
+        let attributes = vec![
+            attr("action", "acknowledge"),
+            attr("sender", &msg.sender),
+            attr("receiver", &msg.receiver),
+            attr("denom", &msg.denom),
+            attr("amount", msg.amount),
+            attr("success", "true"),
+        ];
+
+        // if let Some(memo) = msg.memo {
+        //     attributes.push(attr("memo", memo));
+        // }
+        res = res.add_attributes(attributes);
+    }
 
-    Ok(IbcBasicResponse::new().add_attributes(attributes))
+    Ok(res)
 }
 
 // return the tokens to sender
@@ -694,30 +1110,53 @@ fn on_packet_success(_deps: DepsMut, packet: IbcPacket) -> Result<IbcBasicRespon
 // FOLLOW_UP_IBC_SEND_FAILURE_ID failed to send ibc packet. This one has successfully sent
 fn on_packet_failure(
     deps: DepsMut,
+    env: Env,
     packet: IbcPacket,
     err: String,
+    timed_out: bool,
 ) -> Result<IbcBasicResponse, ContractError> {
-    let msg: Ics20Packet = from_json(&packet.data)?;
+    let msgs = parse_packet_data(deps.storage, &packet.src.channel_id, &packet.data)?;
+
+    // every token of the packet is refunded in the same transaction
+    let mut res = IbcBasicResponse::new();
+    for msg in msgs {
+        // in case that the denom is not in the mapping list, meaning that it is not transferred back, but transfer originally from this local chain
+        if ics20_denoms().may_load(deps.storage, &msg.denom)?.is_none() {
+            continue;
+        }
 
-    // in case that the denom is not in the mapping list, meaning that it is not transferred back, but transfer originally from this local chain
-    if ics20_denoms().may_load(deps.storage, &msg.denom)?.is_none() {
-        return Ok(IbcBasicResponse::new());
+        let sub_msg =
+            handle_packet_refund(deps.storage, &msg.sender, &msg.denom, msg.amount, true)?;
+        // since we reduce the channel's balance optimistically when transferring back, we undo reduce it again when receiving failed ack
+        undo_reduce_channel_balance(
+            deps.storage,
+            &packet.src.channel_id,
+            &msg.denom,
+            msg.amount,
+            env.block.height,
+        )?;
+        update_channel_stats(deps.storage, &packet.src.channel_id, &msg.denom, |stats| {
+            if timed_out {
+                stats.total_timed_out = stats.total_timed_out.checked_add(msg.amount)?;
+                stats.timed_out_packets += 1;
+            } else {
+                stats.total_refunded = stats.total_refunded.checked_add(msg.amount)?;
+                stats.refunded_packets += 1;
+            }
+            Ok(())
+        })?;
+
+        res = res
+            .add_submessage(sub_msg)
+            .add_attribute("action", "acknowledge")
+            .add_attribute("sender", msg.sender)
+            .add_attribute("receiver", msg.receiver)
+            .add_attribute("denom", msg.denom)
+            .add_attribute("amount", msg.amount.to_string())
+            .add_attribute("success", "false")
+            .add_attribute("error", err.clone());
     }
 
-    let sub_msg = handle_packet_refund(deps.storage, &msg.sender, &msg.denom, msg.amount, true)?;
-    // since we reduce the channel's balance optimistically when transferring back, we undo reduce it again when receiving failed ack
-    undo_reduce_channel_balance(deps.storage, &packet.src.channel_id, &msg.denom, msg.amount)?;
-
-    let res = IbcBasicResponse::new()
-        .add_submessage(sub_msg)
-        .add_attribute("action", "acknowledge")
-        .add_attribute("sender", msg.sender)
-        .add_attribute("receiver", msg.receiver)
-        .add_attribute("denom", msg.denom)
-        .add_attribute("amount", msg.amount.to_string())
-        .add_attribute("success", "false")
-        .add_attribute("error", err);
-
     Ok(res)
 
     // send ack fail to custom contract for refund
@@ -768,6 +1207,7 @@ pub fn handle_packet_refund(
     Ok(SubMsg::reply_on_error(cosmos_msg, REFUND_FAILURE_ID))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_ibc_send_packet(
     amount: Uint128,
     denom: &str,
@@ -776,18 +1216,44 @@ pub fn build_ibc_send_packet(
     memo: Option<String>,
     src_channel: &str,
     timeout: IbcTimeout,
+    version: &str,
 ) -> StdResult<IbcMsg> {
-    // build ics20 packet
-    let packet = Ics20Packet::new(
-        amount,
-        denom, // we use ibc denom in form <transfer>/<channel>/<denom> so that when it is sent back to remote chain, it gets parsed correctly and burned
-        sender, receiver, memo,
-    );
+    let data = if version == ICS20_VERSION_V2 {
+        // the ibc denom <transfer>/<channel>/<denom> becomes a hop on top of the trace of the remote denom
+        let (port_id, channel_id, remote_denom) = parse_ibc_info_without_sanity_checks(denom)?;
+        let remote_trace = DenomTrace::parse(remote_denom);
+        let mut trace = vec![Hop {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        }];
+        trace.extend(remote_trace.hops());
+        Ics20PacketV2 {
+            tokens: vec![Token {
+                denom: Denom {
+                    base: remote_trace.base_denom,
+                    trace,
+                },
+                amount,
+            }],
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            memo: memo.unwrap_or_default(),
+            forwarding: None,
+        }
+        .encode()
+    } else {
+        // build ics20 packet
+        to_json_binary(&Ics20Packet::new(
+            amount,
+            denom, // we use ibc denom in form <transfer>/<channel>/<denom> so that when it is sent back to remote chain, it gets parsed correctly and burned
+            sender, receiver, memo,
+        ))?
+    };
 
     // prepare ibc message
     Ok(IbcMsg::SendPacket {
         channel_id: src_channel.to_string(),
-        data: to_json_binary(&packet)?,
+        data,
         timeout,
     })
 }
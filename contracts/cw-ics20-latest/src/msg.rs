@@ -1,11 +1,22 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Binary, Uint128};
+use cosmwasm_std::{Addr, Attribute, Binary, Decimal, SubMsg, Timestamp, Uint128};
 use cw20::Cw20ReceiveMsg;
-use cw20_ics20_msg::msg::{AllowedInfo, DeletePairMsg, TransferBackMsg, UpdatePairMsg};
+use cw20_ics20_msg::msg::{
+    AllowedInfo, DeletePairMsg, DenomTrace, FeeData, TransferBackMsg, UpdatePairMsg,
+};
 use oraiswap::asset::AssetInfo;
 
-use cw20_ics20_msg::state::{ChannelInfo, MappingMetadata, Ratio, RelayerFee, TokenFee};
+use cw20_ics20_msg::state::{
+    ChannelInfo, ChannelMetadata, ChannelRelayerFee, FeeDirection, FeeExemption, FeeSchedule,
+    FeeShare, MappingMetadata, MappingStatus, RelayerFee, TokenFee,
+};
 use cw20_ics20_msg::{amount::Amount, ibc_hooks::HookMethods};
+
+use crate::ibc::Ics20Packet;
+use crate::state::{
+    ChannelBalanceOverride, ChannelState, ChannelStats, FeeChange, FeeType, FeeUpdate,
+    MappingMigration, PendingChannelBalanceOverride, RelayerFeePricing,
+};
 use token_bindings::Metadata;
 
 #[cw_serde]
@@ -64,6 +75,17 @@ pub enum ExecuteMsg {
     TransferToRemote(TransferBackMsg),
     UpdateMappingPair(UpdatePairMsg),
     DeleteMappingPair(DeletePairMsg),
+    /// Saves the mapping pairs and token fees all at once, eg: the pages of ExportMappingPairs (must be called by
+    /// the admin). Fails if any pair is invalid
+    BatchUpdateMappingPairs {
+        pairs: Vec<UpdatePairMsg>,
+        #[serde(default)]
+        token_fees: Vec<TokenFee>,
+    },
+    /// Deletes the mapping pairs all at once (must be called by the admin). Fails if any pair cannot be deleted
+    BatchDeleteMappingPairs {
+        pairs: Vec<DeletePairMsg>,
+    },
     /// This must be called by gov_contract, will allow a new cw20 token to be sent
     // Allow(AllowMsg),
     /// Change the admin (must be called by current admin)
@@ -74,11 +96,16 @@ pub enum ExecuteMsg {
         swap_router_contract: Option<String>,
         token_fee: Option<Vec<TokenFee>>,
         relayer_fee: Option<Vec<RelayerFee>>,
+        channel_relayer_fee: Option<Vec<ChannelRelayerFee>>,
         fee_receiver: Option<String>,
         relayer_fee_receiver: Option<String>,
         converter_contract: Option<String>,
         osor_entrypoint_contract: Option<String>,
         token_factory_addr: Option<String>,
+        /// weighted split of the token fees. An empty list sends them all to the fee receiver
+        token_fee_shares: Option<Vec<FeeShare>>,
+        /// weighted split of the relayer fees. An empty list sends them all to the relayer fee receiver
+        relayer_fee_shares: Option<Vec<FeeShare>>,
     },
     // self-call msgs to deal with on_ibc_receive reentrancy error
     IncreaseChannelBalanceIbcReceive {
@@ -93,11 +120,20 @@ pub enum ExecuteMsg {
         amount: Uint128,
         local_receiver: String,
     },
+    /// Overrides exceeding the max delta are kept pending until confirmed in a later block
     OverrideChannelBalance {
         channel_id: String,
         ibc_denom: String,
         outstanding: Uint128,
         total_sent: Option<Uint128>,
+        reason: String,
+    },
+    ConfirmOverrideChannelBalance {
+        id: u64,
+    },
+    /// None lets the admin apply overrides of any delta right away
+    UpdateOverrideMaxDelta {
+        max_delta: Option<Uint128>,
     },
     IbcHooksReceive {
         func: HookMethods,
@@ -105,6 +141,101 @@ pub enum ExecuteMsg {
         args: Binary,
     },
     RegisterDenom(RegisterDenomMsg),
+    /// Record token factory denoms the bridge created before they were tracked, so that they can be administered
+    /// (must be called by the admin). Other accounts create denoms under the same prefix, so only list the bridge ones
+    RegisterTokenFactoryDenoms {
+        denoms: Vec<String>,
+    },
+    /// Administration of the token factory denoms created with RegisterDenom (must be called by the admin)
+    ChangeDenomAdmin {
+        denom: String,
+        new_admin_address: String,
+    },
+    SetDenomMetadata {
+        denom: String,
+        metadata: Metadata,
+    },
+    /// Recovers the tokens of an address, eg: sent to a wrong address
+    ForceTransfer {
+        denom: String,
+        amount: Uint128,
+        from_address: String,
+        to_address: String,
+    },
+    BurnFrom {
+        denom: String,
+        amount: Uint128,
+        burn_from_address: String,
+    },
+    UpdateChannelMetadata {
+        channel_id: String,
+        metadata: ChannelMetadata,
+    },
+    /// Halts a mint-burn mapping pair if its minted supply exceeds its channel balances (must be called by the admin)
+    CheckSolvency {
+        mapping_key: String,
+    },
+    /// Lift the halt of a mapping pair (must be called by the admin)
+    ResumeMapping {
+        mapping_key: String,
+    },
+    /// Restrict the directions a mapping pair accepts new transfers in (must be called by the admin)
+    UpdateMappingStatus {
+        mapping_key: String,
+        status: MappingStatus,
+    },
+    /// Map the mapping pair to a new local asset (must be called by the admin). For grace_period seconds, the old
+    /// asset is still accepted on outbound transfers and converted at par into the new one
+    MigrateMappingAsset {
+        mapping_key: String,
+        new_asset_info: AssetInfo,
+        new_asset_info_decimals: u8,
+        is_mint_burn: Option<bool>,
+        grace_period: u64,
+    },
+    /// Discount the fees of a partner: a local sender for outbound transfers, a remote sender for inbound ones.
+    /// None removes the exemption
+    UpdateFeeExemption {
+        direction: FeeDirection,
+        address: String,
+        exemption: Option<FeeExemption>,
+    },
+    /// Set how the relayer fee is priced in the local asset. None restores the default ORAI pool swap
+    UpdateRelayerFeePricing {
+        asset_info: AssetInfo,
+        pricing: Option<RelayerFeePricing>,
+    },
+    /// Set or remove token and relayer fees. Scheduled if effective_at is in the future
+    UpdateFees {
+        updates: Vec<FeeUpdate>,
+        effective_at: Option<Timestamp>,
+    },
+    /// Replace every token and relayer fee. Scheduled if effective_at is in the future
+    ReplaceFees {
+        token_fees: Vec<TokenFee>,
+        relayer_fees: Vec<RelayerFee>,
+        channel_relayer_fees: Vec<ChannelRelayerFee>,
+        effective_at: Option<Timestamp>,
+    },
+    CancelScheduledFeeChange {
+        id: u64,
+    },
+    /// Record the fee revenue per epoch of the given length in seconds as well. None stops it
+    UpdateFeeEpochDuration {
+        duration: Option<u64>,
+    },
+    /// Deposit the attached ORAI to pay the relayer fees of the cw20 transfers of the sender
+    DepositRelayerFee {},
+    /// Withdraw ORAI from the relayer fee deposit of the sender. None withdraws all of it
+    WithdrawRelayerFee {
+        amount: Option<Uint128>,
+    },
+    /// Re-save a page of mapping pairs created before the channel index and the denom traces were recorded (must be
+    /// called by the admin). Continue from the last_key attribute until it is empty
+    ReindexMappings {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -134,12 +265,38 @@ pub enum QueryMsg {
     /// Return the port ID bound by this contract.
     #[returns(PortResponse)]
     Port {},
-    /// Show all channels we have connected to.
+    /// Show the channels we have connected to. Optionally filtered by connection, counterparty chain id
+    /// or local asset denom (channels having a mapping pair of the asset)
     #[returns(ListChannelsResponse)]
-    ListChannels {},
-    /// Returns the details of the name channel, error if not created.
+    ListChannels {
+        start_after: Option<String>,
+        limit: Option<u32>,
+        connection_id: Option<String>,
+        counterparty_chain_id: Option<String>,
+        denom: Option<String>,
+    },
+    /// Returns the details of the name channel, error if not created. Balances are paginated by ibc denom
     #[returns(ChannelResponse)]
-    Channel { id: String },
+    Channel {
+        id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the balance of a channel denom at the end of the given block height, after all its transactions
+    #[returns(ChannelBalanceAtResponse)]
+    ChannelBalanceAt {
+        channel_id: String,
+        denom: String,
+        height: u64,
+    },
+    /// Returns the balances of a local asset denom (native denom or cw20 address) across all channels, paginated by
+    /// mapping key
+    #[returns(ListChannelBalancesResponse)]
+    ListChannelBalances {
+        denom: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     /// Returns the details of the name channel, error if not created.
     #[returns(ChannelWithKeyResponse)]
     ChannelWithKey { channel_id: String, denom: String },
@@ -169,8 +326,101 @@ pub enum QueryMsg {
     PairMapping { key: String },
     #[returns(Vec<PairQuery>)]
     PairMappingsFromAssetInfo { asset_info: AssetInfo },
-    #[returns(Ratio)]
-    GetTransferTokenFee { remote_token_denom: String },
+    /// Mapping pairs of a local channel with their channel balance, paginated by mapping key
+    #[returns(ListChannelMappingsResponse)]
+    PairMappingsByChannel {
+        channel_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// token fee applied to the transfers of the given direction and local channel, falling back to coarser fees
+    #[returns(FeeSchedule)]
+    GetTransferTokenFee {
+        remote_token_denom: String,
+        direction: Option<FeeDirection>,
+        channel_id: Option<String>,
+    },
+    /// List the applied channel balance overrides
+    #[returns(ListChannelBalanceOverridesResponse)]
+    ChannelBalanceOverrides {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order: Option<u8>,
+    },
+    /// List the channel balance overrides waiting for confirmation
+    #[returns(ListPendingChannelBalanceOverridesResponse)]
+    PendingChannelBalanceOverrides {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the outstanding balances of the mappings of the channel against their caps
+    #[returns(ChannelUtilizationResponse)]
+    ChannelUtilization { channel_id: String },
+    /// Compares the outstanding channel balances of the mapping's local asset with the contract holdings, or the
+    /// supply for mint-burn mappings
+    #[returns(SolvencyResponse)]
+    CheckSolvency { mapping_key: String },
+    /// Returns the pricing of the relayer fee in the local asset
+    #[returns(RelayerFeePricing)]
+    RelayerFeePricing { asset_info: AssetInfo },
+    #[returns(ListFeeExemptionsResponse)]
+    FeeExemptions {
+        direction: FeeDirection,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the current token and relayer fees, and the fee changes scheduled
+    #[returns(FeesResponse)]
+    Fees {},
+    /// Cumulative fee revenue of a fee type per local denom and channel, of all time or of the given epoch.
+    /// Paginated by (denom, channel_id)
+    #[returns(ListFeeStatsResponse)]
+    FeeStats {
+        fee_type: FeeType,
+        epoch: Option<u64>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(FeeEpochResponse)]
+    FeeEpoch {},
+    /// Dry-runs the receive of the packet on the local channel: what it would be acked with, the fees and the
+    /// messages planned
+    #[returns(SimulateReceiveResponse)]
+    SimulateReceive {
+        packet: Ics20Packet,
+        dest_channel: String,
+    },
+    /// Mapping pairs with their token fees and balance, paginated by mapping key. The pairs, with their status and
+    /// halt, and the token fees of each page can be passed as is to BatchUpdateMappingPairs
+    #[returns(ExportMappingPairsResponse)]
+    ExportMappingPairs {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(MappingMigrationResponse)]
+    MappingMigration { mapping_key: String },
+    #[returns(ListMappingMigrationsResponse)]
+    MappingMigrations {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Resolves the ibc-go hash of a mapping key, with or without the ibc/ prefix, to its denom trace
+    #[returns(DenomTraceResponse)]
+    DenomTrace { hash: String },
+    #[returns(ListDenomTracesResponse)]
+    DenomTraces {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Token factory denoms created by the bridge with their current supply, paginated by denom
+    #[returns(ListTokenFactoryDenomsResponse)]
+    TokenFactoryDenoms {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// ORAI deposited by the address to pay the relayer fees of its cw20 transfers
+    #[returns(Uint128)]
+    RelayerFeeDeposit { address: String },
 }
 
 #[cw_serde]
@@ -189,6 +439,26 @@ pub struct ChannelResponse {
     pub total_sent: Vec<Amount>,
 }
 
+#[cw_serde]
+pub struct ListChannelBalancesResponse {
+    pub denom: String,
+    pub balances: Vec<ChannelBalance>,
+    /// sum of the outstanding balances of the page, in local asset decimals
+    pub total_outstanding: Uint128,
+    /// sum of the total sent of the page, in local asset decimals
+    pub total_sent: Uint128,
+}
+
+#[cw_serde]
+pub struct ChannelBalance {
+    pub channel_id: String,
+    pub ibc_denom: String,
+    /// outstanding balance, in remote decimals
+    pub outstanding: Uint128,
+    /// total sent, in remote decimals
+    pub total_sent: Uint128,
+}
+
 #[cw_serde]
 pub struct ChannelWithKeyResponse {
     /// Information on the channel's connection
@@ -198,6 +468,17 @@ pub struct ChannelWithKeyResponse {
     /// The total number of tokens that have been sent over this channel
     /// (even if many have been returned, so balance is low)
     pub total_sent: Amount,
+    /// received, returned, refunded and fee totals of the channel denom
+    pub stats: ChannelStats,
+}
+
+#[cw_serde]
+pub struct ChannelBalanceAtResponse {
+    pub height: u64,
+    /// How many tokens were pending over this channel at the given height
+    pub balance: Amount,
+    /// The total number of tokens that had been sent over this channel at the given height
+    pub total_sent: Amount,
 }
 
 #[cw_serde]
@@ -216,8 +497,60 @@ pub struct ConfigResponse {
     pub relayer_fee_receiver: Addr,
     pub token_fees: Vec<TokenFee>,
     pub relayer_fees: Vec<RelayerFeeResponse>,
+    pub channel_relayer_fees: Vec<ChannelRelayerFee>,
     pub converter_contract: String,
     pub osor_entrypoint_contract: String,
+    pub token_fee_shares: Vec<FeeShare>,
+    pub relayer_fee_shares: Vec<FeeShare>,
+}
+
+#[cw_serde]
+pub struct FeesResponse {
+    pub token_fees: Vec<TokenFee>,
+    pub relayer_fees: Vec<RelayerFeeResponse>,
+    pub channel_relayer_fees: Vec<ChannelRelayerFee>,
+    /// changes applied by the first transfer after their effective time, the earliest first
+    pub scheduled: Vec<ScheduledFeeChangeResponse>,
+}
+
+#[cw_serde]
+pub struct SimulateReceiveResponse {
+    pub ack: Binary,
+    /// the error the packet would be acked with, None on success
+    pub error: Option<String>,
+    /// fees of every token of the packet
+    pub fees: Vec<FeeData>,
+    pub messages: Vec<SubMsg>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[cw_serde]
+pub struct ListFeeStatsResponse {
+    pub fee_type: FeeType,
+    pub epoch: Option<u64>,
+    pub stats: Vec<FeeRevenue>,
+}
+
+#[cw_serde]
+pub struct FeeRevenue {
+    /// local denom of the fee, prefixed with cw20: for cw20 tokens
+    pub denom: String,
+    pub channel_id: String,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct FeeEpochResponse {
+    /// None if the revenue is not recorded per epoch
+    pub duration: Option<u64>,
+    pub current_epoch: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ScheduledFeeChangeResponse {
+    pub id: u64,
+    pub effective_at: Timestamp,
+    pub change: FeeChange,
 }
 
 #[cw_serde]
@@ -242,8 +575,155 @@ pub struct ListMappingResponse {
     pub pairs: Vec<PairQuery>,
 }
 
+#[cw_serde]
+pub struct ListChannelMappingsResponse {
+    pub channel_id: String,
+    pub pairs: Vec<ChannelMappingResponse>,
+}
+
+#[cw_serde]
+pub struct ChannelMappingResponse {
+    pub key: String,
+    pub pair_mapping: MappingMetadata,
+    /// channel balance of the pair, in remote decimals
+    pub balance: ChannelState,
+}
+
+#[cw_serde]
+pub struct ListTokenFactoryDenomsResponse {
+    pub denoms: Vec<TokenFactoryDenomResponse>,
+}
+
+#[cw_serde]
+pub struct TokenFactoryDenomResponse {
+    pub denom: String,
+    pub subdenom: String,
+    pub supply: Uint128,
+}
+
+#[cw_serde]
+pub struct ExportMappingPairsResponse {
+    pub pairs: Vec<UpdatePairMsg>,
+    /// token fees of the remote denoms of the pairs, for any channel or the channel of a pair
+    pub token_fees: Vec<TokenFee>,
+    pub states: Vec<MappingPairState>,
+}
+
+#[cw_serde]
+pub struct MappingPairState {
+    pub key: String,
+    /// channel balance of the pair, in remote decimals
+    pub balance: ChannelState,
+}
+
+#[cw_serde]
+pub struct MappingMigrationResponse {
+    pub mapping_key: String,
+    pub new_asset_info: AssetInfo,
+    pub migration: MappingMigration,
+    /// true while the old asset is accepted on outbound transfers
+    pub in_grace_period: bool,
+}
+
+#[cw_serde]
+pub struct ListMappingMigrationsResponse {
+    pub migrations: Vec<MappingMigrationResponse>,
+}
+
+#[cw_serde]
+pub struct DenomTraceResponse {
+    pub hash: String,
+    pub denom_trace: DenomTrace,
+    /// key of the mapping pair of the trace
+    pub mapping_key: String,
+}
+
+#[cw_serde]
+pub struct ListDenomTracesResponse {
+    pub traces: Vec<DenomTraceResponse>,
+}
+
 #[cw_serde]
 pub struct PairQuery {
     pub key: String,
     pub pair_mapping: MappingMetadata,
 }
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    pub mapping_key: String,
+    pub asset_info: AssetInfo,
+    pub is_mint_burn: bool,
+    /// sum of the outstanding channel balances of every mapping of the local asset, in local decimals
+    pub outstanding: Uint128,
+    /// escrowed balance of the contract, or the total supply for mint-burn mappings
+    pub holdings: Uint128,
+    /// outstanding balances minus the supply of a mint-burn mapping, zero for pool mappings
+    pub surplus: Uint128,
+    /// supply of a mint-burn mapping minus its outstanding balances, zero for pool mappings
+    pub deficit: Uint128,
+    pub is_halted: bool,
+}
+
+#[cw_serde]
+pub struct ChannelUtilizationResponse {
+    pub channel_id: String,
+    pub mappings: Vec<MappingUtilization>,
+}
+
+#[cw_serde]
+pub struct MappingUtilization {
+    pub ibc_denom: String,
+    /// outstanding balance of the mapping, in remote decimals
+    pub outstanding: Uint128,
+    pub max_outstanding: Option<Uint128>,
+    /// outstanding / max_outstanding. None if the mapping has no cap
+    pub utilization: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct ListChannelBalanceOverridesResponse {
+    pub max_delta: Option<Uint128>,
+    pub overrides: Vec<ChannelBalanceOverrideResponse>,
+}
+
+#[cw_serde]
+pub struct ChannelBalanceOverrideResponse {
+    pub id: u64,
+    pub channel_balance_override: ChannelBalanceOverride,
+}
+
+#[cw_serde]
+pub struct ListPendingChannelBalanceOverridesResponse {
+    pub overrides: Vec<PendingChannelBalanceOverrideResponse>,
+}
+
+#[cw_serde]
+pub struct PendingChannelBalanceOverrideResponse {
+    pub id: u64,
+    pub pending_override: PendingChannelBalanceOverride,
+}
+
+/// Query interface of the price oracles used to price the relayer fee
+#[cw_serde]
+pub enum PriceOracleQueryMsg {
+    /// price of the base asset in the quote asset
+    Price { base: String, quote: String },
+}
+
+#[cw_serde]
+pub struct PriceResponse {
+    pub price: Decimal,
+}
+
+#[cw_serde]
+pub struct ListFeeExemptionsResponse {
+    pub direction: FeeDirection,
+    pub exemptions: Vec<FeeExemptionResponse>,
+}
+
+#[cw_serde]
+pub struct FeeExemptionResponse {
+    pub address: String,
+    pub exemption: FeeExemption,
+}
@@ -1,12 +1,15 @@
-use cosmwasm_std::{Api, Env, Order, StdResult, Storage};
+use cosmwasm_std::{Api, Decimal, Deps, Env, Order, StdResult, Storage, Uint128};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw20_ics20_msg::amount::convert_remote_to_local;
 use cw20_ics20_msg::helper::{denom_to_asset_info, parse_ibc_wasm_port_id};
 use cw20_ics20_msg::state::MappingMetadata;
 use oraiswap::asset::AssetInfo;
 use sha256::digest;
 
 use crate::{
-    msg::PairQuery,
-    state::{get_key_ics20_ibc_denom, ics20_denoms},
+    ibc::parse_ibc_channel_without_sanity_checks,
+    msg::{ChannelUtilizationResponse, MappingUtilization, PairQuery, SolvencyResponse},
+    state::{get_key_ics20_ibc_denom, ics20_denoms, CHANNEL_REVERSE_STATE, HALTED_MAPPINGS},
 };
 
 pub fn get_mappings_from_asset_info(
@@ -71,3 +74,104 @@ pub fn get_destination_info_on_orai(
 
     (AssetInfo::NativeToken { denom: ibc_denom }, None)
 }
+
+/// compares the outstanding channel balances of a local asset with its holdings: the escrowed balance of this contract,
+/// or the total supply for mint-burn mappings. Every mapping of the same local asset shares the holdings, so their
+/// outstanding balances are summed in local decimals. Only mint-burn mappings can report a deficit
+pub fn get_solvency(deps: Deps, env: &Env, mapping_key: String) -> StdResult<SolvencyResponse> {
+    let mapping = ics20_denoms().load(deps.storage, &mapping_key)?;
+
+    let mut outstanding = Uint128::zero();
+    for pair in get_mappings_from_asset_info(deps.storage, mapping.asset_info.clone())? {
+        let channel_id = parse_ibc_channel_without_sanity_checks(&pair.key)?;
+        if let Some(state) =
+            CHANNEL_REVERSE_STATE.may_load(deps.storage, (channel_id, &pair.key))?
+        {
+            outstanding += convert_remote_to_local(
+                state.outstanding,
+                pair.pair_mapping.remote_decimals,
+                pair.pair_mapping.asset_info_decimals,
+            )?;
+        }
+    }
+
+    let holdings = match (&mapping.asset_info, mapping.is_mint_burn) {
+        (AssetInfo::NativeToken { denom }, false) => {
+            deps.querier
+                .query_balance(&env.contract.address, denom)?
+                .amount
+        }
+        (AssetInfo::NativeToken { denom }, true) => deps.querier.query_supply(denom)?.amount,
+        (AssetInfo::Token { contract_addr }, false) => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(
+                contract_addr,
+                &Cw20QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            res.balance
+        }
+        (AssetInfo::Token { contract_addr }, true) => {
+            let res: TokenInfoResponse = deps
+                .querier
+                .query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
+            res.total_supply
+        }
+    };
+
+    // minted supply must not exceed the outstanding balances. The escrow of a pool mapping does not back them: inbound
+    // transfers are paid out of the pool, so its holdings go down as the outstanding balances go up
+    let (surplus, deficit) = if mapping.is_mint_burn {
+        (
+            outstanding.saturating_sub(holdings),
+            holdings.saturating_sub(outstanding),
+        )
+    } else {
+        (Uint128::zero(), Uint128::zero())
+    };
+
+    Ok(SolvencyResponse {
+        mapping_key: mapping_key.clone(),
+        asset_info: mapping.asset_info,
+        is_mint_burn: mapping.is_mint_burn,
+        outstanding,
+        holdings,
+        surplus,
+        deficit,
+        is_halted: HALTED_MAPPINGS.has(deps.storage, &mapping_key),
+    })
+}
+
+pub fn get_channel_utilization(
+    storage: &dyn Storage,
+    channel_id: String,
+) -> StdResult<ChannelUtilizationResponse> {
+    let utilization = |outstanding: Uint128, max_outstanding: Option<Uint128>| {
+        max_outstanding
+            .filter(|max_outstanding| !max_outstanding.is_zero())
+            .map(|max_outstanding| Decimal::from_ratio(outstanding, max_outstanding))
+    };
+
+    let mut mappings = vec![];
+    for item in
+        CHANNEL_REVERSE_STATE
+            .prefix(&channel_id)
+            .range(storage, None, None, Order::Ascending)
+    {
+        let (ibc_denom, state) = item?;
+        let max_outstanding = ics20_denoms()
+            .may_load(storage, &ibc_denom)?
+            .and_then(|mapping| mapping.max_outstanding);
+        mappings.push(MappingUtilization {
+            ibc_denom,
+            outstanding: state.outstanding,
+            max_outstanding,
+            utilization: utilization(state.outstanding, max_outstanding),
+        });
+    }
+
+    Ok(ChannelUtilizationResponse {
+        channel_id,
+        mappings,
+    })
+}
@@ -1,12 +1,17 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, StdResult, Storage, Uint128};
+use cosmwasm_std::{Addr, Decimal, Order, StdResult, Storage, Timestamp, Uint128};
 use cw20_ics20_msg::converter::ConverterController;
+use cw20_ics20_msg::msg::{DenomTrace, FeeData};
 use cw20_ics20_msg::state::{
-    AllowInfo, ChannelInfo, ConvertReplyArgs, MappingMetadata, Ratio, ReplyArgs,
+    AllowInfo, ChannelInfo, ChannelRelayerFee, ConvertReplyArgs, FeeDirection, FeeExemption,
+    FeeSchedule, FeeShare, MappingMetadata, Ratio, RelayerFee, ReplyArgs, TokenFee, MAX_BPS,
 };
 use cw_controllers::Admin;
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
-use oraiswap::router::RouterController;
+use cw_storage_plus::{
+    Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy,
+};
+use oraiswap::asset::AssetInfo;
+use oraiswap::router::{RouterController, SwapOperation};
 
 use crate::ContractError;
 
@@ -28,9 +33,17 @@ pub const CHANNEL_INFO: Map<&str, ChannelInfo> = Map::new("channel_info");
 // pub const CHANNEL_FORWARD_STATE: Map<(&str, &str), ChannelState> =
 //     Map::new("channel_forward_state");
 
-/// Reverse channel state is used when REMOTE chain initiates ibc transfer to local chain
-pub const CHANNEL_REVERSE_STATE: Map<(&str, &str), ChannelState> =
-    Map::new("channel_reverse_state");
+/// Reverse channel state is used when REMOTE chain initiates ibc transfer to local chain.
+/// Snapshotted every block so that the balances at a past height can be queried
+pub const CHANNEL_REVERSE_STATE: SnapshotMap<(&str, &str), ChannelState> = SnapshotMap::new(
+    "channel_reverse_state",
+    "channel_reverse_state__checkpoints",
+    "channel_reverse_state__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Statistics of the transfers of a (channel, ibc denom)
+pub const CHANNEL_STATS: Map<(&str, &str), ChannelStats> = Map::new("channel_stats");
 
 /// Reverse channel state is used when LOCAL chain initiates ibc transfer to remote chain
 // pub const CHANNEL_FORWARD_STATE: Map<(&str, &str), ChannelState> =
@@ -39,12 +52,72 @@ pub const CHANNEL_REVERSE_STATE: Map<(&str, &str), ChannelState> =
 /// Every cw20 contract we allow to be sent is stored here, possibly with a gas_limit
 pub const ALLOW_LIST: Map<&Addr, AllowInfo> = Map::new("allow_list");
 
+/// token fee ratios saved before fee schedules were introduced. Loaded as fee schedules until replaced in TOKEN_FEE_SCHEDULE
 pub const TOKEN_FEE: Map<&str, Ratio> = Map::new("token_fee");
 
+/// token fee schedules keyed by (direction, local channel, remote denom). An empty direction or channel matches any
+pub const TOKEN_FEE_SCHEDULE: Map<(&str, &str, &str), FeeSchedule> = Map::new("token_fee_schedule");
+
 // relayer fee. This fee depends on the network type, not token type
 // decimals of relayer fee should always be 10^6 because we use ORAI as relayer fee
 pub const RELAYER_FEE: Map<&str, Uint128> = Map::new("relayer_fee");
 
+// ORAI deposited by an address to pay the relayer fees of its cw20 transfers, which cannot carry native coins
+pub const RELAYER_FEE_DEPOSITS: Map<&Addr, Uint128> = Map::new("relayer_fee_deposits");
+
+// relayer fee keyed by (channel, destination). Takes precedence over RELAYER_FEE, which is guessed from the address prefix
+pub const CHANNEL_RELAYER_FEE: Map<(&str, &str), Uint128> = Map::new("channel_relayer_fee");
+
+/// how the ORAI relayer fee is priced in a local asset, keyed by the asset info string
+pub const RELAYER_FEE_PRICING: Map<&str, RelayerFeePricing> = Map::new("relayer_fee_pricing");
+
+/// fee exemptions keyed by (direction, address): the local sender of outbound transfers, the remote sender of inbound ones
+pub const FEE_EXEMPTIONS: Map<(&str, &str), FeeExemption> = Map::new("fee_exemptions");
+
+/// overrides of a channel balance moving outstanding or total_sent by more than this delta must be confirmed
+/// by the admin in a later block
+pub const OVERRIDE_MAX_DELTA: Item<Uint128> = Item::new("override_max_delta");
+
+/// append-only log of the applied channel balance overrides, keyed by a sequence number
+pub const CHANNEL_BALANCE_OVERRIDES: Map<u64, ChannelBalanceOverride> =
+    Map::new("channel_balance_overrides");
+pub const CHANNEL_BALANCE_OVERRIDE_COUNT: Item<u64> = Item::new("channel_balance_override_count");
+
+/// overrides exceeding the max delta, waiting for the confirmation of the admin
+pub const PENDING_CHANNEL_BALANCE_OVERRIDES: Map<u64, PendingChannelBalanceOverride> =
+    Map::new("pending_channel_balance_overrides");
+pub const PENDING_CHANNEL_BALANCE_OVERRIDE_COUNT: Item<u64> =
+    Item::new("pending_channel_balance_override_count");
+
+/// fee changes waiting for their effective time, keyed by (effective time in nanoseconds, sequence number) so that only
+/// the due ones are loaded. Applied by the next transfer after it
+pub const SCHEDULED_FEE_CHANGES: Map<(u64, u64), ScheduledFeeChange> =
+    Map::new("scheduled_fee_changes");
+/// effective time of the scheduled fee changes keyed by sequence number, to find them when cancelled
+pub const SCHEDULED_FEE_CHANGE_TIMES: Map<u64, Timestamp> = Map::new("scheduled_fee_change_times");
+pub const SCHEDULED_FEE_CHANGE_COUNT: Item<u64> = Item::new("scheduled_fee_change_count");
+
+/// cumulative fee revenue keyed by (fee type, local denom, local channel)
+pub const FEE_REVENUE: Map<(&str, &str, &str), Uint128> = Map::new("fee_revenue");
+/// fee revenue of each epoch, keyed by ((epoch, fee type), local denom, local channel)
+pub const EPOCH_FEE_REVENUE: Map<((u64, &str), &str, &str), Uint128> =
+    Map::new("epoch_fee_revenue");
+/// length of the fee revenue epochs in seconds. The revenue is not recorded per epoch if unset
+pub const FEE_EPOCH_DURATION: Item<u64> = Item::new("fee_epoch_duration");
+
+/// mappings halted at the given block height because their solvency check failed. Receiving and sending
+/// through them is rejected until the admin resumes them
+pub const HALTED_MAPPINGS: Map<&str, u64> = Map::new("halted_mappings");
+
+/// migrations of mapping pairs to a new local asset, keyed by mapping key
+pub const MAPPING_MIGRATIONS: Map<&str, MappingMigration> = Map::new("mapping_migrations");
+
+/// denom traces of the mapping keys, keyed by their ibc-go hash
+pub const DENOM_TRACES: Map<&str, DenomTrace> = Map::new("denom_traces");
+
+/// token factory denoms created by the bridge with RegisterDenom, keyed by denom, with their subdenom
+pub const TOKEN_FACTORY_DENOMS: Map<&str, String> = Map::new("token_factory_denoms");
+
 // // accumulated token fee
 // pub const TOKEN_FEE_ACCUMULATOR: Map<&str, Uint128> = Map::new("token_fee_accumulator");
 
@@ -55,12 +128,14 @@ pub const RELAYER_FEE: Map<&str, Uint128> = Map::new("relayer_fee");
 pub struct MappingMetadataIndexex<'a> {
     // token.identifier
     pub asset_info: MultiIndex<'a, String, MappingMetadata, String>,
+    // local channel of the mapping key
+    pub channel: MultiIndex<'a, String, MappingMetadata, String>,
 }
 
 // IndexList is just boilerplate code for fetching a struct's indexes
 impl<'a> IndexList<MappingMetadata> for MappingMetadataIndexex<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<MappingMetadata>> + '_> {
-        let v: Vec<&dyn Index<MappingMetadata>> = vec![&self.asset_info];
+        let v: Vec<&dyn Index<MappingMetadata>> = vec![&self.asset_info, &self.channel];
         Box::new(v.into_iter())
     }
 }
@@ -75,6 +150,17 @@ pub fn ics20_denoms<'a>() -> IndexedMap<'a, &'a str, MappingMetadata, MappingMet
             "ics20_mapping_namespace",
             "asset__info",
         ),
+        channel: MultiIndex::new(
+            |k, _d| {
+                String::from_utf8_lossy(k)
+                    .splitn(3, '/')
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string()
+            },
+            "ics20_mapping_namespace",
+            "channel__id",
+        ),
     };
     IndexedMap::new("ics20_mapping_namespace", indexes)
 }
@@ -86,6 +172,194 @@ pub struct ChannelState {
     pub total_sent: Uint128,
 }
 
+/// cumulative statistics of a channel denom. Transferred amounts are in remote decimals, fees in local decimals
+#[cw_serde]
+#[derive(Default)]
+pub struct ChannelStats {
+    /// total amount received from the remote chain
+    pub total_received: Uint128,
+    pub received_packets: u64,
+    /// total amount transferred back to the remote chain
+    pub total_returned: Uint128,
+    pub returned_packets: u64,
+    /// total amount refunded to local senders after an error acknowledgement
+    pub total_refunded: Uint128,
+    pub refunded_packets: u64,
+    /// total amount refunded to local senders after a timeout
+    pub total_timed_out: Uint128,
+    pub timed_out_packets: u64,
+    pub total_token_fee: Uint128,
+    pub total_relayer_fee: Uint128,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum FeeType {
+    Token,
+    Relayer,
+}
+
+impl FeeType {
+    pub fn key(&self) -> &'static str {
+        match self {
+            FeeType::Token => "token",
+            FeeType::Relayer => "relayer",
+        }
+    }
+}
+
+/// migration of a mapping pair to a new local asset. Until the end of the grace period, the old asset is still
+/// accepted on outbound transfers and converted at par into the new asset
+#[cw_serde]
+pub struct MappingMigration {
+    pub old_asset_info: AssetInfo,
+    pub old_asset_info_decimals: u8,
+    pub old_is_mint_burn: bool,
+    pub started_at: Timestamp,
+    pub grace_period_end: Timestamp,
+    /// old asset converted so far, in the old asset decimals
+    pub converted_amount: Uint128,
+    pub converted_transfers: u64,
+}
+
+#[cw_serde]
+pub struct ChannelBalanceOverride {
+    pub channel_id: String,
+    pub ibc_denom: String,
+    pub old: ChannelState,
+    pub new: ChannelState,
+    pub reason: String,
+    /// admin who requested the override
+    pub admin: Addr,
+    /// admin who confirmed the override, if it exceeded the max delta
+    pub confirmed_by: Option<Addr>,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct PendingChannelBalanceOverride {
+    pub channel_id: String,
+    pub ibc_denom: String,
+    pub outstanding: Uint128,
+    pub total_sent: Option<Uint128>,
+    pub reason: String,
+    pub admin: Addr,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct RelayerFeePricing {
+    pub source: RelayerFeePriceSource,
+    pub on_failure: PricingFailure,
+}
+
+#[cw_serde]
+pub enum RelayerFeePriceSource {
+    /// simulate swapping the ORAI fee on the swap router through the operations, which may go through several hops
+    SwapRoute { operations: Vec<SwapOperation> },
+    /// admin-set amount of the asset per ORAI, in the smallest units of both
+    StaticPrice { price: Decimal },
+    /// query the price of ORAI in the asset from an oracle contract
+    Oracle { contract: String },
+}
+
+impl RelayerFeePriceSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RelayerFeePriceSource::SwapRoute { .. } => "swap_route",
+            RelayerFeePriceSource::StaticPrice { .. } => "static_price",
+            RelayerFeePriceSource::Oracle { .. } => "oracle",
+        }
+    }
+}
+
+/// what to charge when the relayer fee cannot be priced
+#[cw_serde]
+pub enum PricingFailure {
+    /// charge no relayer fee
+    Waive,
+    /// charge a fixed fee, in the asset decimals
+    FallbackFee { amount: Uint128 },
+    /// reject the transfer
+    Reject,
+}
+
+#[cw_serde]
+pub enum FeeUpdate {
+    SetTokenFee(TokenFee),
+    RemoveTokenFee {
+        token_denom: String,
+        direction: Option<FeeDirection>,
+        channel_id: Option<String>,
+    },
+    SetRelayerFee(RelayerFee),
+    RemoveRelayerFee {
+        prefix: String,
+    },
+    SetChannelRelayerFee(ChannelRelayerFee),
+    RemoveChannelRelayerFee {
+        channel_id: String,
+        destination: String,
+    },
+}
+
+#[cw_serde]
+pub enum FeeChange {
+    /// applies the updates on top of the current fees
+    Update { updates: Vec<FeeUpdate> },
+    /// replaces every token fee, relayer fee and channel relayer fee
+    Replace {
+        token_fees: Vec<TokenFee>,
+        relayer_fees: Vec<RelayerFee>,
+        channel_relayer_fees: Vec<ChannelRelayerFee>,
+    },
+}
+
+impl FeeChange {
+    /// the token fees set by the change
+    pub fn token_fees(&self) -> Vec<&TokenFee> {
+        match self {
+            FeeChange::Update { updates } => updates
+                .iter()
+                .filter_map(|update| match update {
+                    FeeUpdate::SetTokenFee(token_fee) => Some(token_fee),
+                    _ => None,
+                })
+                .collect(),
+            FeeChange::Replace { token_fees, .. } => token_fees.iter().collect(),
+        }
+    }
+
+    /// the updates of the change. A replacement clears every fee before applying them
+    pub fn updates(&self) -> Vec<FeeUpdate> {
+        match self {
+            FeeChange::Update { updates } => updates.clone(),
+            FeeChange::Replace {
+                token_fees,
+                relayer_fees,
+                channel_relayer_fees,
+            } => token_fees
+                .iter()
+                .cloned()
+                .map(FeeUpdate::SetTokenFee)
+                .chain(relayer_fees.iter().cloned().map(FeeUpdate::SetRelayerFee))
+                .chain(
+                    channel_relayer_fees
+                        .iter()
+                        .cloned()
+                        .map(FeeUpdate::SetChannelRelayerFee),
+                )
+                .collect(),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct ScheduledFeeChange {
+    pub effective_at: Timestamp,
+    pub change: FeeChange,
+}
+
 #[cw_serde]
 pub struct Config {
     pub default_timeout: u64,
@@ -97,6 +371,12 @@ pub struct Config {
     pub converter_contract: ConverterController,
     pub osor_entrypoint_contract: String,
     pub token_factory_addr: Addr,
+    /// split of the token fees. Empty sends them all to token_fee_receiver
+    #[serde(default)]
+    pub token_fee_shares: Vec<FeeShare>,
+    /// split of the relayer fees. Empty sends them all to the relayer fee receiver
+    #[serde(default)]
+    pub relayer_fee_shares: Vec<FeeShare>,
 }
 
 pub fn increase_channel_balance(
@@ -104,13 +384,17 @@ pub fn increase_channel_balance(
     channel: &str,
     denom: &str, // should be ibc denom
     amount: Uint128,
+    height: u64,
 ) -> Result<(), ContractError> {
-    let store = CHANNEL_REVERSE_STATE.key((channel, denom));
     // whatever error or not found, return default
-    let mut state = store.load(storage).unwrap_or_default();
+    let mut state = CHANNEL_REVERSE_STATE
+        .load(storage, (channel, denom))
+        .unwrap_or_default();
     state.outstanding += amount;
     state.total_sent += amount;
-    store.save(storage, &state).map_err(ContractError::Std)
+    CHANNEL_REVERSE_STATE
+        .save(storage, (channel, denom), &state, height)
+        .map_err(ContractError::Std)
 }
 
 pub fn reduce_channel_balance(
@@ -118,9 +402,9 @@ pub fn reduce_channel_balance(
     channel: &str,
     denom: &str, // should be ibc denom
     amount: Uint128,
+    height: u64,
 ) -> Result<(), ContractError> {
-    let store = CHANNEL_REVERSE_STATE.key((channel, denom));
-    let Ok(mut state) = store.load(storage) else {
+    let Ok(mut state) = CHANNEL_REVERSE_STATE.load(storage, (channel, denom)) else {
         return Err(ContractError::NoSuchChannelState {
             id: channel.to_string(),
             denom: denom.to_string(),
@@ -136,7 +420,9 @@ pub fn reduce_channel_balance(
                 denom: denom.to_string(),
             })?;
 
-    store.save(storage, &state).map_err(ContractError::Std)
+    CHANNEL_REVERSE_STATE
+        .save(storage, (channel, denom), &state, height)
+        .map_err(ContractError::Std)
 }
 
 // only used for admin of the contract
@@ -146,8 +432,9 @@ pub fn override_channel_balance(
     denom: &str, // should be ibc denom
     outstanding: Uint128,
     total_sent: Option<Uint128>,
+    height: u64,
 ) -> Result<(), ContractError> {
-    CHANNEL_REVERSE_STATE.update(storage, (channel, denom), |orig| -> StdResult<_> {
+    CHANNEL_REVERSE_STATE.update(storage, (channel, denom), height, |orig| -> StdResult<_> {
         let mut state = orig.unwrap_or_default();
         state.outstanding = outstanding;
         if let Some(total_sent) = total_sent {
@@ -165,8 +452,9 @@ pub fn undo_reduce_channel_balance(
     channel: &str,
     denom: &str,
     amount: Uint128,
+    height: u64,
 ) -> Result<(), ContractError> {
-    CHANNEL_REVERSE_STATE.update(storage, (channel, denom), |orig| -> StdResult<_> {
+    CHANNEL_REVERSE_STATE.update(storage, (channel, denom), height, |orig| -> StdResult<_> {
         let mut state = orig.unwrap_or_default();
         state.outstanding += amount;
         Ok(state)
@@ -174,6 +462,414 @@ pub fn undo_reduce_channel_balance(
     Ok(())
 }
 
+/// pricing of the relayer fee in the asset. Defaults to a swap in the ORAI pool of the asset, charging no fee on failure
+pub fn get_relayer_fee_pricing(
+    storage: &dyn Storage,
+    asset_info: &AssetInfo,
+) -> StdResult<RelayerFeePricing> {
+    Ok(RELAYER_FEE_PRICING
+        .may_load(storage, &asset_info.to_string())?
+        .unwrap_or_else(|| RelayerFeePricing {
+            source: RelayerFeePriceSource::SwapRoute {
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: AssetInfo::NativeToken {
+                        denom: "orai".to_string(),
+                    },
+                    ask_asset_info: asset_info.clone(),
+                }],
+            },
+            on_failure: PricingFailure::Waive,
+        }))
+}
+
+pub fn update_channel_stats<A>(
+    storage: &mut dyn Storage,
+    channel: &str,
+    denom: &str, // should be ibc denom
+    action: A,
+) -> StdResult<ChannelStats>
+where
+    A: FnOnce(&mut ChannelStats) -> StdResult<()>,
+{
+    CHANNEL_STATS.update(storage, (channel, denom), |orig| -> StdResult<_> {
+        let mut stats = orig.unwrap_or_default();
+        action(&mut stats)?;
+        Ok(stats)
+    })
+}
+
+pub fn save_token_fee(storage: &mut dyn Storage, token_fee: &TokenFee) -> StdResult<()> {
+    let direction = token_fee.direction.map(|d| d.key()).unwrap_or_default();
+    let channel = token_fee.channel_id.as_deref().unwrap_or_default();
+    TOKEN_FEE_SCHEDULE.save(
+        storage,
+        (direction, channel, &token_fee.token_denom),
+        &token_fee.fee,
+    )?;
+    // the fee of every direction and channel replaces the legacy ratio
+    if direction.is_empty() && channel.is_empty() {
+        TOKEN_FEE.remove(storage, &token_fee.token_denom);
+    }
+    Ok(())
+}
+
+/// token fee of a remote denom, from the most specific key to the coarsest: (direction, channel), direction only,
+/// channel only, any direction and channel, then the ratio saved before fee schedules were introduced. The due fee
+/// changes are read on top of the stored fees
+pub fn get_token_fee(
+    storage: &dyn Storage,
+    due_fee_changes: &[FeeChange],
+    direction: Option<FeeDirection>,
+    channel: Option<&str>,
+    remote_token_denom: &str,
+) -> StdResult<Option<FeeSchedule>> {
+    let direction = direction.map(|d| d.key()).unwrap_or_default();
+    let channel = channel.unwrap_or_default();
+    // the update of the token fee saved under the key, see effective_fee
+    let update_token_fee = |key: (&str, &str), update: &FeeUpdate| match update {
+        FeeUpdate::SetTokenFee(token_fee)
+            if token_fee_key(token_fee.direction, token_fee.channel_id.as_deref()) == key
+                && token_fee.token_denom == remote_token_denom =>
+        {
+            Some(Some(token_fee.fee.clone()))
+        }
+        FeeUpdate::RemoveTokenFee {
+            token_denom,
+            direction,
+            channel_id,
+        } if token_fee_key(*direction, channel_id.as_deref()) == key
+            && token_denom == remote_token_denom =>
+        {
+            Some(None)
+        }
+        _ => None,
+    };
+    for key in [
+        (direction, channel),
+        (direction, ""),
+        ("", channel),
+        ("", ""),
+    ] {
+        let stored = TOKEN_FEE_SCHEDULE.may_load(storage, (key.0, key.1, remote_token_denom))?;
+        if let Some(fee) = effective_fee(stored, due_fee_changes, |update| {
+            update_token_fee(key, update)
+        }) {
+            return Ok(Some(fee));
+        }
+    }
+    // the fee of every direction and channel replaces the legacy ratio, and so does its removal
+    let legacy_ratio = effective_fee(
+        TOKEN_FEE.may_load(storage, remote_token_denom)?,
+        due_fee_changes,
+        |update| update_token_fee(("", ""), update).map(|_| None),
+    );
+    legacy_ratio.map(FeeSchedule::try_from).transpose()
+}
+
+// key of a token fee in TOKEN_FEE_SCHEDULE, without the denom
+fn token_fee_key(direction: Option<FeeDirection>, channel_id: Option<&str>) -> (&str, &str) {
+    (
+        direction.map(|d| d.key()).unwrap_or_default(),
+        channel_id.unwrap_or_default(),
+    )
+}
+
+/// every token fee, with the legacy ratios that have not been replaced yet
+pub fn list_token_fees(storage: &dyn Storage) -> StdResult<Vec<TokenFee>> {
+    let mut fees = TOKEN_FEE_SCHEDULE
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let ((direction, channel, token_denom), fee) = item?;
+            Ok(TokenFee {
+                token_denom,
+                fee,
+                direction: FeeDirection::from_key(&direction),
+                channel_id: Some(channel).filter(|channel| !channel.is_empty()),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for item in TOKEN_FEE.range(storage, None, None, Order::Ascending) {
+        let (token_denom, ratio) = item?;
+        if !TOKEN_FEE_SCHEDULE.has(storage, ("", "", &token_denom)) {
+            fees.push(TokenFee {
+                token_denom,
+                fee: ratio.try_into()?,
+                direction: None,
+                channel_id: None,
+            });
+        }
+    }
+    fees.sort_by(|a, b| a.token_denom.cmp(&b.token_denom));
+    Ok(fees)
+}
+
+/// converts the token fee ratios saved before fee schedules were introduced. Fails on a ratio that is not a whole
+/// number of basis points rather than changing the fee
+pub fn migrate_legacy_token_fees(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let mut token_fees = vec![];
+    for item in TOKEN_FEE.range(storage, None, None, Order::Ascending) {
+        let (token_denom, ratio) = item?;
+        let fee = FeeSchedule::try_from(ratio.clone()).map_err(|_| {
+            ContractError::LossyTokenFeeRatio {
+                denom: token_denom.clone(),
+                nominator: ratio.nominator,
+                denominator: ratio.denominator,
+            }
+        })?;
+        token_fees.push(TokenFee {
+            token_denom,
+            fee,
+            direction: None,
+            channel_id: None,
+        });
+    }
+    for token_fee in token_fees {
+        // a schedule saved since replaces the legacy ratio
+        if TOKEN_FEE_SCHEDULE.has(storage, ("", "", &token_fee.token_denom)) {
+            TOKEN_FEE.remove(storage, &token_fee.token_denom);
+        } else {
+            save_token_fee(storage, &token_fee)?;
+        }
+    }
+    Ok(())
+}
+
+/// checks that the fee schedule charges at most 100% and its min is not above its max
+pub fn assert_fee_schedule(fee: &FeeSchedule) -> Result<(), ContractError> {
+    if fee.bps > MAX_BPS {
+        return Err(ContractError::InvalidFeeBps { bps: fee.bps });
+    }
+    if let Some(max) = fee.max.filter(|max| *max < fee.min) {
+        return Err(ContractError::InvalidFeeBounds { min: fee.min, max });
+    }
+    Ok(())
+}
+
+pub fn remove_token_fee(
+    storage: &mut dyn Storage,
+    direction: Option<FeeDirection>,
+    channel_id: Option<&str>,
+    token_denom: &str,
+) {
+    let direction = direction.map(|d| d.key()).unwrap_or_default();
+    let channel = channel_id.unwrap_or_default();
+    TOKEN_FEE_SCHEDULE.remove(storage, (direction, channel, token_denom));
+    if direction.is_empty() && channel.is_empty() {
+        TOKEN_FEE.remove(storage, token_denom);
+    }
+}
+
+pub fn apply_fee_change(storage: &mut dyn Storage, change: &FeeChange) -> StdResult<()> {
+    if let FeeChange::Replace { .. } = change {
+        TOKEN_FEE.clear(storage);
+        TOKEN_FEE_SCHEDULE.clear(storage);
+        RELAYER_FEE.clear(storage);
+        CHANNEL_RELAYER_FEE.clear(storage);
+    }
+    for update in change.updates() {
+        match update {
+            FeeUpdate::SetTokenFee(token_fee) => save_token_fee(storage, &token_fee)?,
+            FeeUpdate::RemoveTokenFee {
+                token_denom,
+                direction,
+                channel_id,
+            } => remove_token_fee(storage, direction, channel_id.as_deref(), &token_denom),
+            FeeUpdate::SetRelayerFee(fee) => RELAYER_FEE.save(storage, &fee.prefix, &fee.fee)?,
+            FeeUpdate::RemoveRelayerFee { prefix } => RELAYER_FEE.remove(storage, &prefix),
+            FeeUpdate::SetChannelRelayerFee(fee) => {
+                CHANNEL_RELAYER_FEE.save(storage, (&fee.channel_id, &fee.destination), &fee.fee)?
+            }
+            FeeUpdate::RemoveChannelRelayerFee {
+                channel_id,
+                destination,
+            } => CHANNEL_RELAYER_FEE.remove(storage, (&channel_id, &destination)),
+        }
+    }
+    Ok(())
+}
+
+/// the fee stored under a key once the due fee changes are applied on top of it, without writing them. `update_fee`
+/// returns Some(fee) if the update saves the fee under the key, Some(None) if it removes it, None if it does not touch it
+pub fn effective_fee<T>(
+    stored: Option<T>,
+    due_fee_changes: &[FeeChange],
+    update_fee: impl Fn(&FeeUpdate) -> Option<Option<T>>,
+) -> Option<T> {
+    let mut fee = stored;
+    for change in due_fee_changes {
+        if let FeeChange::Replace { .. } = change {
+            fee = None;
+        }
+        for update in change.updates() {
+            if let Some(updated_fee) = update_fee(&update) {
+                fee = updated_fee;
+            }
+        }
+    }
+    fee
+}
+
+/// the changes of the scheduled fee changes that became effective, the earliest first
+pub fn due_fee_changes(storage: &dyn Storage, now: Timestamp) -> StdResult<Vec<FeeChange>> {
+    Ok(due_scheduled_fee_changes(storage, now)?
+        .into_iter()
+        .map(|(_, scheduled)| scheduled.change)
+        .collect())
+}
+
+/// scheduled fee changes that became effective, the earliest first
+pub fn due_scheduled_fee_changes(
+    storage: &dyn Storage,
+    now: Timestamp,
+) -> StdResult<Vec<((u64, u64), ScheduledFeeChange)>> {
+    SCHEDULED_FEE_CHANGES
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive((now.nanos(), u64::MAX))),
+            Order::Ascending,
+        )
+        .collect()
+}
+
+/// applies the scheduled fee changes that became effective, the earliest first
+pub fn apply_scheduled_fee_changes(storage: &mut dyn Storage, now: Timestamp) -> StdResult<()> {
+    for ((effective_at, id), scheduled) in due_scheduled_fee_changes(storage, now)? {
+        apply_fee_change(storage, &scheduled.change)?;
+        SCHEDULED_FEE_CHANGES.remove(storage, (effective_at, id));
+        SCHEDULED_FEE_CHANGE_TIMES.remove(storage, id);
+    }
+    Ok(())
+}
+
+/// adds the fees of a transfer to the revenue of its channel, and to the revenue of the current epoch if enabled
+pub fn record_fee_revenue(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    channel: &str,
+    fee_data: &FeeData,
+) -> StdResult<()> {
+    let epoch = FEE_EPOCH_DURATION
+        .may_load(storage)?
+        .map(|duration| now.seconds() / duration);
+    for (fee_type, fee) in [
+        (FeeType::Token, &fee_data.token_fee),
+        (FeeType::Relayer, &fee_data.relayer_fee),
+    ] {
+        if fee.is_empty() {
+            continue;
+        }
+        let denom = fee.denom();
+        let add_fee = |total: Option<Uint128>| -> StdResult<Uint128> {
+            Ok(total.unwrap_or_default().checked_add(fee.amount())?)
+        };
+        FEE_REVENUE.update(storage, (fee_type.key(), &denom, channel), add_fee)?;
+        if let Some(epoch) = epoch {
+            EPOCH_FEE_REVENUE.update(
+                storage,
+                ((epoch, fee_type.key()), &denom, channel),
+                add_fee,
+            )?;
+        }
+    }
+    Ok(())
+}
+
 pub fn get_key_ics20_ibc_denom(port_id: &str, channel_id: &str, denom: &str) -> String {
     format!("{}/{}/{}", port_id, channel_id, denom)
 }
+
+/// saves the trace of the mapping key so that its hash can be resolved, returns the hash
+pub fn save_denom_trace(storage: &mut dyn Storage, mapping_key: &str) -> StdResult<String> {
+    let trace = DenomTrace::parse(mapping_key);
+    let hash = trace.hash();
+    DENOM_TRACES.save(storage, &hash, &trace)?;
+    Ok(hash)
+}
+
+pub fn assert_mapping_not_halted(
+    storage: &dyn Storage,
+    mapping_key: &str,
+) -> Result<(), ContractError> {
+    if HALTED_MAPPINGS.has(storage, mapping_key) {
+        return Err(ContractError::MappingHalted {
+            key: mapping_key.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// rejects the transfers of the direction the status of the mapping does not allow
+pub fn assert_mapping_status(
+    mapping_key: &str,
+    mapping: &MappingMetadata,
+    direction: FeeDirection,
+) -> Result<(), ContractError> {
+    if !mapping.status.allows(direction) {
+        return Err(ContractError::MappingDirectionDisabled {
+            key: mapping_key.to_string(),
+            direction: direction.key().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// checks the amount of a transfer, in remote decimals, against the bounds of the mapping
+pub fn assert_transfer_amount(
+    mapping_key: &str,
+    mapping: &MappingMetadata,
+    remote_amount: Uint128,
+) -> Result<(), ContractError> {
+    if let Some(min_amount) = mapping.min_amount {
+        if remote_amount < min_amount {
+            return Err(ContractError::TransferAmountTooSmall {
+                key: mapping_key.to_string(),
+                amount: remote_amount,
+                min_amount,
+            });
+        }
+    }
+    if let Some(max_amount) = mapping.max_amount {
+        if remote_amount > max_amount {
+            return Err(ContractError::TransferAmountTooLarge {
+                key: mapping_key.to_string(),
+                amount: remote_amount,
+                max_amount,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// balances of a channel denom at the end of the given block height, after all its transactions were executed
+pub fn channel_balance_at_height(
+    storage: &dyn Storage,
+    channel: &str,
+    denom: &str,
+    height: u64,
+) -> StdResult<Option<ChannelState>> {
+    // snapshots hold the state at the start of a height, which is the end of the previous one
+    CHANNEL_REVERSE_STATE.may_load_at_height(storage, (channel, denom), height.saturating_add(1))
+}
+
+/// checks that receiving the remote amount keeps the outstanding balance of the mapping under its cap
+pub fn assert_outstanding_cap(
+    storage: &dyn Storage,
+    channel: &str,
+    ibc_denom: &str,
+    mapping: &MappingMetadata,
+    remote_amount: Uint128,
+) -> Result<(), ContractError> {
+    if let Some(max_outstanding) = mapping.max_outstanding {
+        let state = CHANNEL_REVERSE_STATE
+            .may_load(storage, (channel, ibc_denom))?
+            .unwrap_or_default();
+        if state.outstanding.checked_add(remote_amount)? > max_outstanding {
+            return Err(ContractError::OutstandingCapExceeded {
+                id: channel.to_string(),
+                denom: ibc_denom.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
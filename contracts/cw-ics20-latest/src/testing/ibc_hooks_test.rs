@@ -84,6 +84,12 @@ fn test_ibc_hooks_receive() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     contract_instance
@@ -1,8 +1,9 @@
 use std::ops::Sub;
 
 use cosmwasm_std::{
-    wasm_execute, Addr, BankMsg, Coin, CosmosMsg, Decimal, IbcChannelConnectMsg, IbcChannelOpenMsg,
-    StdError,
+    wasm_execute, Addr, Attribute, BankMsg, Coin, ContractResult, CosmosMsg, Decimal,
+    DenomMetadata, DenomUnit, Deps, DepsMut, Env, IbcChannelConnectMsg, IbcChannelOpenMsg, Order,
+    StdError, StdResult, Storage, SystemError, SystemResult, WasmQuery,
 };
 use cosmwasm_testing_util::mock::MockContract;
 use cosmwasm_vm::testing::MockInstanceOptions;
@@ -12,40 +13,57 @@ use oraiswap::asset::AssetInfo;
 use oraiswap::router::RouterController;
 
 use crate::ibc::{
-    convert_remote_denom_to_evm_prefix, deduct_fee, deduct_relayer_fee, deduct_token_fee,
-    get_follow_up_msgs, get_swap_token_amount_out_from_orai, handle_packet_refund,
-    ibc_packet_receive, parse_ibc_channel_without_sanity_checks,
-    parse_ibc_denom_without_sanity_checks, parse_ibc_info_without_sanity_checks,
-    parse_voucher_denom, Ics20Ack, Ics20Packet, ICS20_VERSION, NATIVE_RECEIVE_ID,
-    REFUND_FAILURE_ID,
+    build_fee_msgs, convert_remote_denom_to_evm_prefix, deduct_fee, deduct_relayer_fee,
+    deduct_token_fee, get_follow_up_msgs, get_relayer_fee_in_asset, handle_packet_refund,
+    ibc_channel_open, ibc_packet_ack, ibc_packet_receive, ibc_packet_timeout,
+    parse_ibc_channel_without_sanity_checks, parse_ibc_denom_without_sanity_checks,
+    parse_ibc_info_without_sanity_checks, parse_voucher_denom, Ics20Ack, Ics20Packet,
+    ICS20_VERSION, ICS20_VERSION_V2, NATIVE_RECEIVE_ID, REFUND_FAILURE_ID,
 };
 use crate::query_helper::get_destination_info_on_orai;
 use crate::testing::test_helpers::*;
 use cosmwasm_std::{
-    from_json, to_json_binary, IbcEndpoint, IbcMsg, IbcPacket, IbcPacketReceiveMsg, SubMsg,
-    Timestamp, Uint128, WasmMsg,
+    from_json, to_json_binary, IbcAcknowledgement, IbcEndpoint, IbcMsg, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, Response, SubMsg, Timestamp, Uint128, WasmMsg,
 };
 
 use crate::error::ContractError;
 use crate::state::{
-    get_key_ics20_ibc_denom, increase_channel_balance, reduce_channel_balance, Config,
-    CHANNEL_REVERSE_STATE, CONFIG, RELAYER_FEE, REPLY_ARGS, TOKEN_FEE,
+    apply_scheduled_fee_changes, get_key_ics20_ibc_denom, increase_channel_balance,
+    override_channel_balance, reduce_channel_balance, save_token_fee, ChannelBalanceOverride,
+    ChannelState, ChannelStats, Config, FeeType, FeeUpdate, PricingFailure, RelayerFeePriceSource,
+    RelayerFeePricing, CHANNEL_INFO, CHANNEL_REVERSE_STATE, CHANNEL_STATS, CONFIG, FEE_EXEMPTIONS,
+    HALTED_MAPPINGS, RELAYER_FEE, REPLY_ARGS, TOKEN_FEE, TOKEN_FEE_SCHEDULE,
 };
-use cw20::{Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg, TokenInfoResponse};
 use cw20_ics20_msg::amount::{convert_remote_to_local, Amount};
-use cw20_ics20_msg::state::{MappingMetadata, Ratio, RelayerFee, TokenFee};
+use cw20_ics20_msg::state::{
+    ChannelMetadata, ChannelRelayerFee, FeeDirection, FeeExemption, FeeRecipient, FeeSchedule,
+    FeeShare, MappingMetadata, MappingStatus, Ratio, RelayerFee, TokenFee, MAX_BPS,
+};
+use cw_storage_plus::Map;
+use cw_utils::PaymentError;
 
 use crate::contract::{
     build_burn_mapping_msg, build_mint_mapping_msg, execute, handle_override_channel_balance,
-    query, query_channel, query_channel_with_key,
+    migrate, query, query_channel, query_channel_with_key,
 };
 use crate::msg::{
-    AllowMsg, ChannelResponse, ConfigResponse, ExecuteMsg, InitMsg, ListChannelsResponse,
-    ListMappingResponse, PairQuery, QueryMsg,
+    AllowMsg, ChannelBalance, ChannelBalanceAtResponse, ChannelResponse,
+    ChannelUtilizationResponse, ConfigResponse, DenomTraceResponse, ExecuteMsg,
+    ExportMappingPairsResponse, FeeEpochResponse, FeeExemptionResponse, FeeRevenue, FeesResponse,
+    InitMsg, ListChannelBalancesResponse, ListChannelMappingsResponse, ListChannelsResponse,
+    ListDenomTracesResponse, ListFeeExemptionsResponse, ListFeeStatsResponse, ListMappingResponse,
+    ListTokenFactoryDenomsResponse, MappingMigrationResponse, MappingUtilization, MigrateMsg,
+    PairQuery, QueryMsg, RegisterDenomMsg, SimulateReceiveResponse, SolvencyResponse,
+    TokenFactoryDenomResponse,
 };
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{coins, to_json_vec};
-use cw20_ics20_msg::msg::{DeletePairMsg, TransferBackMsg, UpdatePairMsg};
+use cw20_ics20_msg::msg::{
+    DeletePairMsg, Denom, DenomTrace, ForwardingPacketData, Hop, Ics20PacketV2, Token,
+    TransferBackMsg, UpdatePairMsg,
+};
 
 const SENDER: &str = "orai1gkr56hlnx9vc7vncln2dkd896zfsqjn300kfq0";
 const CONTRACT: &str = "orai19p43y0tqnr5qlhfwnxft2u5unph5yn60y7tuvu";
@@ -175,6 +193,11 @@ fn test_parse_voucher_denom_invalid_length() {
         parse_voucher_denom(voucher_denom, &ibc_endpoint).unwrap_err(),
         ContractError::NoForeignTokens {}
     );
+    // the endpoint hop without a denom
+    assert_eq!(
+        parse_voucher_denom("hello/world/", &ibc_endpoint).unwrap_err(),
+        ContractError::NoForeignTokens {}
+    );
 }
 
 #[test]
@@ -191,6 +214,13 @@ fn test_parse_voucher_denom_invalid_port() {
             port: "foobar".to_string()
         }
     );
+    // denoms containing '/' without a channel hop, eg: token factory denoms
+    assert_eq!(
+        parse_voucher_denom("factory/cosmos1creator/xyz", &ibc_endpoint).unwrap_err(),
+        ContractError::FromOtherPort {
+            port: "factory".to_string()
+        }
+    );
 }
 
 #[test]
@@ -209,6 +239,41 @@ fn test_parse_voucher_denom_invalid_channel() {
     );
 }
 
+#[test]
+fn test_parse_voucher_denom_voucher_of_endpoint() {
+    let ibc_endpoint = IbcEndpoint {
+        port_id: "hello".to_string(),
+        channel_id: "world".to_string(),
+    };
+    assert_eq!(
+        parse_voucher_denom("hello/world/xyz", &ibc_endpoint).unwrap(),
+        ("xyz", false)
+    );
+    // the rest of the path is kept
+    assert_eq!(
+        parse_voucher_denom("hello/world/transfer/channel-5/uatom", &ibc_endpoint).unwrap(),
+        ("transfer/channel-5/uatom", false)
+    );
+}
+
+#[test]
+fn test_parse_voucher_denom_multi_hop() {
+    let ibc_endpoint = IbcEndpoint {
+        port_id: "hello".to_string(),
+        channel_id: "world".to_string(),
+    };
+    // vouchers the remote chain received through other hops are native to it
+    assert_eq!(
+        parse_voucher_denom("transfer/channel-5/uatom", &ibc_endpoint).unwrap(),
+        ("transfer/channel-5/uatom", true)
+    );
+    // a hop through another channel of the endpoint port
+    assert_eq!(
+        parse_voucher_denom("hello/channel-7/transfer/channel-5/uatom", &ibc_endpoint).unwrap(),
+        ("hello/channel-7/transfer/channel-5/uatom", true)
+    );
+}
+
 #[test]
 fn test_parse_voucher_denom_native_denom_valid() {
     let voucher_denom = "foobar";
@@ -222,6 +287,164 @@ fn test_parse_voucher_denom_native_denom_valid() {
     );
 }
 
+#[test]
+fn test_multi_hop_denom_trace() {
+    let local_channel = "channel-0";
+    let remote_denom = "transfer/channel-5/uatom";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, remote_denom);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: remote_denom.to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    let hash = DenomTrace::parse(&ibc_denom).hash();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("denom_hash", hash.clone())));
+
+    // the trace resolves with or without the ibc/ prefix, in any case
+    let trace: DenomTraceResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::DenomTrace {
+                hash: format!("ibc/{}", hash.to_lowercase()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        trace,
+        DenomTraceResponse {
+            hash: hash.clone(),
+            denom_trace: DenomTrace {
+                path: format!("{}/{}/transfer/channel-5", CONTRACT_PORT, local_channel),
+                base_denom: "uatom".to_string(),
+            },
+            mapping_key: ibc_denom.clone(),
+        }
+    );
+    let traces: ListDenomTracesResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::DenomTraces {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(traces.traces, vec![trace]);
+
+    // the multi-hop denom is received through the mapping of its full trace
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet(
+                "channel-1",
+                local_channel,
+                Uint128::from(100u128),
+                remote_denom.to_string(),
+                "orai1receiver".to_string(),
+            ),
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    let ack: Ics20Ack = from_json(&res.acknowledgement).unwrap();
+    assert!(matches!(ack, Ics20Ack::Result(_)));
+    assert_eq!(
+        res.messages[0].msg,
+        wasm_execute(
+            contract_addr.to_string(),
+            &ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+                dest_channel_id: local_channel.to_string(),
+                ibc_denom: ibc_denom.clone(),
+                amount: Uint128::from(100u128),
+                local_receiver: "orai1receiver".to_string(),
+            },
+            vec![]
+        )
+        .unwrap()
+        .into()
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: local_channel.to_string(),
+            ibc_denom: ibc_denom.clone(),
+            amount: Uint128::from(100u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    // and sent back with its full trace
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &coins(100, "orai")),
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: remote_denom.to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        }),
+    )
+    .unwrap();
+    let packet = res
+        .messages
+        .iter()
+        .find_map(|msg| match &msg.msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => {
+                Some(from_json::<Ics20Packet>(data).unwrap())
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(packet.denom, ibc_denom);
+
+    // deleting the mapping removes its trace
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::DeleteMappingPair(DeletePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: remote_denom.to_string(),
+        }),
+    )
+    .unwrap();
+    query(deps.as_ref(), mock_env(), QueryMsg::DenomTrace { hash }).unwrap_err();
+}
+
 /////////////////////////////// Test cases for native denom transfer from remote chain to local chain
 
 #[test]
@@ -289,6 +512,12 @@ fn proper_checks_on_execute_native_transfer_back_to_remote() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     let _ = execute(
@@ -306,6 +535,7 @@ fn proper_checks_on_execute_native_transfer_back_to_remote() {
         remote_denom: denom.to_string(),
         timeout: Some(DEFAULT_TIMEOUT),
         memo: None,
+        referral: None,
     };
 
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
@@ -410,7 +640,7 @@ fn proper_checks_on_execute_native_transfer_back_to_remote() {
     }
 
     // check new channel state after reducing balance
-    let chan = query_channel(deps.as_ref(), local_channel.into()).unwrap();
+    let chan = query_channel(deps.as_ref(), local_channel.into(), None, None).unwrap();
     assert_eq!(
         chan.balances,
         vec![Amount::native(
@@ -436,6 +666,12 @@ fn proper_checks_on_execute_native_transfer_back_to_remote() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     execute(
@@ -538,6 +774,12 @@ fn send_from_remote_to_local_receive_happy_path() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     contract_instance
@@ -624,20 +866,14 @@ fn test_follow_up_msgs() {}
 #[test]
 fn test_deduct_fee() {
     assert_eq!(
-        deduct_fee(
-            Ratio {
-                nominator: 1,
-                denominator: 0,
-            },
-            Uint128::from(1000u64)
-        ),
+        deduct_fee(FeeSchedule::default(), Uint128::from(1000u64)),
         Uint128::zero()
     );
     assert_eq!(
         deduct_fee(
-            Ratio {
-                nominator: 1,
-                denominator: 1,
+            FeeSchedule {
+                bps: 10000,
+                ..FeeSchedule::default()
             },
             Uint128::from(1000u64)
         ),
@@ -645,14 +881,41 @@ fn test_deduct_fee() {
     );
     assert_eq!(
         deduct_fee(
-            Ratio {
-                nominator: 1,
-                denominator: 100,
+            FeeSchedule {
+                bps: 100,
+                ..FeeSchedule::default()
             },
             Uint128::from(1000u64)
         ),
         Uint128::from(10u64)
     );
+
+    let fee_schedule = FeeSchedule {
+        bps: 100,
+        flat: Uint128::from(5u64),
+        min: Uint128::from(20u64),
+        max: Some(Uint128::from(500u64)),
+    };
+    // 1% + 5 = 15, raised to the min
+    assert_eq!(
+        deduct_fee(fee_schedule.clone(), Uint128::from(1000u64)),
+        Uint128::from(20u64)
+    );
+    // 1% + 5
+    assert_eq!(
+        deduct_fee(fee_schedule.clone(), Uint128::from(10000u64)),
+        Uint128::from(105u64)
+    );
+    // capped by the max
+    assert_eq!(
+        deduct_fee(fee_schedule.clone(), Uint128::from(1000000u64)),
+        Uint128::from(500u64)
+    );
+    // never more than the amount
+    assert_eq!(
+        deduct_fee(fee_schedule, Uint128::from(10u64)),
+        Uint128::from(10u64)
+    );
 }
 
 #[test]
@@ -725,7 +988,19 @@ fn test_deduct_token_fee() {
     let storage = deps.as_mut().storage;
     let token_fee_denom = "foo0x";
     // should return amount because we have not set relayer fee yet
-    assert_eq!(deduct_token_fee(storage, "foo", amount).unwrap().0, amount);
+    assert_eq!(
+        deduct_token_fee(
+            storage,
+            &[],
+            FeeDirection::Inbound,
+            "channel-0",
+            "foo",
+            amount
+        )
+        .unwrap()
+        .0,
+        amount
+    );
     TOKEN_FEE
         .save(
             storage,
@@ -737,11 +1012,83 @@ fn test_deduct_token_fee() {
         )
         .unwrap();
     assert_eq!(
-        deduct_token_fee(storage, token_fee_denom, amount)
-            .unwrap()
-            .0,
+        deduct_token_fee(
+            storage,
+            &[],
+            FeeDirection::Inbound,
+            "channel-0",
+            token_fee_denom,
+            amount
+        )
+        .unwrap()
+        .0,
         Uint128::from(990u64)
     );
+
+    // free deposits, withdrawals charged more on channel-1
+    let fee = |bps: u16| FeeSchedule {
+        bps,
+        ..FeeSchedule::default()
+    };
+    for (direction, channel_id, bps) in [
+        (Some(FeeDirection::Inbound), None, 0),
+        (Some(FeeDirection::Outbound), None, 200),
+        (Some(FeeDirection::Outbound), Some("channel-1"), 500),
+    ] {
+        save_token_fee(
+            storage,
+            &TokenFee {
+                token_denom: token_fee_denom.to_string(),
+                fee: fee(bps),
+                direction,
+                channel_id: channel_id.map(|c| c.to_string()),
+            },
+        )
+        .unwrap();
+    }
+    let deduct = |storage: &mut dyn Storage, direction: FeeDirection, channel: &str| {
+        deduct_token_fee(storage, &[], direction, channel, token_fee_denom, amount)
+            .unwrap()
+            .1
+    };
+    assert_eq!(
+        deduct(storage, FeeDirection::Inbound, "channel-1"),
+        Uint128::zero()
+    );
+    assert_eq!(
+        deduct(storage, FeeDirection::Outbound, "channel-0"),
+        Uint128::from(20u64)
+    );
+    assert_eq!(
+        deduct(storage, FeeDirection::Outbound, "channel-1"),
+        Uint128::from(50u64)
+    );
+
+    // a channel fee of any direction is coarser than a direction fee
+    save_token_fee(
+        storage,
+        &TokenFee {
+            token_denom: token_fee_denom.to_string(),
+            fee: fee(300),
+            direction: None,
+            channel_id: Some("channel-2".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        deduct(storage, FeeDirection::Outbound, "channel-2"),
+        Uint128::from(20u64)
+    );
+    TOKEN_FEE_SCHEDULE.remove(storage, ("outbound", "", token_fee_denom));
+    assert_eq!(
+        deduct(storage, FeeDirection::Outbound, "channel-2"),
+        Uint128::from(30u64)
+    );
+    // the legacy ratio is still the fallback of the other channels
+    assert_eq!(
+        deduct(storage, FeeDirection::Outbound, "channel-0"),
+        Uint128::from(10u64)
+    );
 }
 
 #[test]
@@ -757,44 +1104,50 @@ fn test_deduct_relayer_fee() {
     // token price empty case. Should return zero fee
     let result = deduct_relayer_fee(
         deps_mut.storage,
+        &[],
         deps_mut.api,
         &deps_mut.querier,
+        "channel-0",
         remote_address,
         token_fee_denom,
         destination_asset_on_orai.clone(),
         &swap_router_contract,
     )
     .unwrap();
-    assert_eq!(result, Uint128::zero());
+    assert_eq!(result, (Uint128::zero(), None));
 
     // remote address is wrong (dont follow bech32 form)
     assert_eq!(
         deduct_relayer_fee(
             deps_mut.storage,
+            &[],
             deps_mut.api,
             &deps_mut.querier,
+            "channel-0",
             "foobar",
             token_fee_denom,
             destination_asset_on_orai.clone(),
             &swap_router_contract,
         )
         .unwrap(),
-        Uint128::from(0u128)
+        (Uint128::from(0u128), None)
     );
 
     // no relayer fee case
     assert_eq!(
         deduct_relayer_fee(
             deps_mut.storage,
+            &[],
             deps_mut.api,
             &deps_mut.querier,
+            "channel-0",
             remote_address,
             token_fee_denom,
             destination_asset_on_orai.clone(),
             &swap_router_contract,
         )
         .unwrap(),
-        Uint128::zero()
+        (Uint128::zero(), None)
     );
 
     // oraib prefix case.
@@ -809,46 +1162,168 @@ fn test_deduct_relayer_fee() {
     assert_eq!(
         deduct_relayer_fee(
             deps_mut.storage,
+            &[],
             deps_mut.api,
             &deps_mut.querier,
+            "channel-0",
             "oraib1603j3e4juddh7cuhfquxspl0p0nsun047wz3rl",
             "foo0x",
             destination_asset_on_orai.clone(),
             &swap_router_contract,
         )
         .unwrap(),
-        Uint128::from(1000u64)
+        (Uint128::from(1000u64), Some("orai".to_string()))
     );
 
     // normal case with remote address
     assert_eq!(
         deduct_relayer_fee(
             deps_mut.storage,
+            &[],
             deps_mut.api,
             &deps_mut.querier,
+            "channel-0",
             remote_address,
             token_fee_denom,
             destination_asset_on_orai,
             &swap_router_contract,
         )
         .unwrap(),
-        Uint128::from(100u64)
+        (Uint128::from(100u64), Some("orai".to_string()))
     );
 }
 
 #[test]
-fn test_get_swap_token_amount_out_from_orai() {
-    let deps = mock_dependencies();
+fn test_get_relayer_fee_in_asset() {
+    let mut deps = setup(&["channel-0"], &[]);
     let simulate_amount = Uint128::from(10u128);
-    let result = get_swap_token_amount_out_from_orai(
+    let router = RouterController("foo".to_string());
+    let atom = AssetInfo::NativeToken {
+        denom: "uatom".to_string(),
+    };
+    let result = get_relayer_fee_in_asset(
+        deps.as_ref().storage,
         &deps.as_ref().querier,
         simulate_amount,
-        &RouterController("foo".to_string()),
+        &router,
         AssetInfo::NativeToken {
             denom: "orai".to_string(),
         },
+    )
+    .unwrap();
+    assert_eq!(result, (simulate_amount, "orai"));
+
+    // the default swap in the ORAI pool fails, no fee is charged
+    assert_eq!(
+        get_relayer_fee_in_asset(
+            deps.as_ref().storage,
+            &deps.as_ref().querier,
+            simulate_amount,
+            &router,
+            atom.clone(),
+        )
+        .unwrap(),
+        (Uint128::zero(), "waived")
+    );
+
+    let update_pricing = |deps: DepsMut, sender: &str, pricing: Option<RelayerFeePricing>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::UpdateRelayerFeePricing {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "uatom".to_string(),
+                },
+                pricing,
+            },
+        )
+    };
+    let pricing = |source: RelayerFeePriceSource, on_failure: PricingFailure| {
+        Some(RelayerFeePricing { source, on_failure })
+    };
+    let static_price = pricing(
+        RelayerFeePriceSource::StaticPrice {
+            price: Decimal::percent(250),
+        },
+        PricingFailure::Reject,
+    );
+    // only the admin can set the pricing
+    let err = update_pricing(deps.as_mut(), "foo", static_price.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    assert_eq!(
+        update_pricing(
+            deps.as_mut(),
+            "gov",
+            pricing(
+                RelayerFeePriceSource::SwapRoute { operations: vec![] },
+                PricingFailure::Reject,
+            )
+        )
+        .unwrap_err(),
+        ContractError::EmptySwapRoute {}
+    );
+    update_pricing(deps.as_mut(), "gov", static_price).unwrap();
+    let price = |deps: Deps| {
+        get_relayer_fee_in_asset(
+            deps.storage,
+            &deps.querier,
+            simulate_amount,
+            &router,
+            atom.clone(),
+        )
+    };
+    assert_eq!(
+        price(deps.as_ref()).unwrap(),
+        (Uint128::from(25u128), "static_price")
+    );
+
+    // the oracle cannot be queried on the mock querier
+    update_pricing(
+        deps.as_mut(),
+        "gov",
+        pricing(
+            RelayerFeePriceSource::Oracle {
+                contract: "oracle".to_string(),
+            },
+            PricingFailure::FallbackFee {
+                amount: Uint128::from(7u128),
+            },
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        price(deps.as_ref()).unwrap(),
+        (Uint128::from(7u128), "fallback")
     );
-    assert_eq!(result, simulate_amount)
+    update_pricing(
+        deps.as_mut(),
+        "gov",
+        pricing(
+            RelayerFeePriceSource::Oracle {
+                contract: "oracle".to_string(),
+            },
+            PricingFailure::Reject,
+        ),
+    )
+    .unwrap();
+    assert!(price(deps.as_ref())
+        .unwrap_err()
+        .to_string()
+        .contains("Cannot price the relayer fee in uatom"));
+
+    // removing the pricing restores the default
+    update_pricing(deps.as_mut(), "gov", None).unwrap();
+    let pricing: RelayerFeePricing = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RelayerFeePricing { asset_info: atom },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(pricing.on_failure, PricingFailure::Waive);
 }
 
 #[test]
@@ -864,7 +1339,18 @@ fn test_split_denom() {
 fn setup_and_query() {
     let deps = setup(&["channel-3", "channel-7"], &[]);
 
-    let raw_list = query(deps.as_ref(), mock_env(), QueryMsg::ListChannels {}).unwrap();
+    let raw_list = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ListChannels {
+            start_after: None,
+            limit: None,
+            connection_id: None,
+            counterparty_chain_id: None,
+            denom: None,
+        },
+    )
+    .unwrap();
     let list_res: ListChannelsResponse = from_json(&raw_list).unwrap();
     assert_eq!(2, list_res.channels.len());
     assert_eq!(mock_channel_info("channel-3"), list_res.channels[0]);
@@ -875,6 +1361,8 @@ fn setup_and_query() {
         mock_env(),
         QueryMsg::Channel {
             id: "channel-3".to_string(),
+            start_after: None,
+            limit: None,
         },
     )
     .unwrap();
@@ -888,6 +1376,8 @@ fn setup_and_query() {
         mock_env(),
         QueryMsg::Channel {
             id: "channel-10".to_string(),
+            start_after: None,
+            limit: None,
         },
     )
     .unwrap_err();
@@ -898,42 +1388,246 @@ fn setup_and_query() {
 }
 
 #[test]
-fn test_query_pair_mapping_by_asset_info() {
-    let mut deps = setup(&["channel-3", "channel-7"], &[]);
-    let asset_info = AssetInfo::Token {
-        contract_addr: Addr::unchecked("cw20:foobar".to_string()),
-    };
-    let mut update = UpdatePairMsg {
-        local_channel_id: "mars-channel".to_string(),
-        denom: "earth".to_string(),
-        local_asset_info: asset_info.clone(),
-        remote_decimals: 18,
-        local_asset_info_decimals: 18,
-        is_mint_burn: None,
-    };
-
-    // works with proper funds
-    let mut msg = ExecuteMsg::UpdateMappingPair(update.clone());
-
-    let info = mock_info("gov", &coins(1234567, "ucosm"));
-    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-
-    // add another pair with the same asset info to filter
-    update.denom = "jupiter".to_string();
-    msg = ExecuteMsg::UpdateMappingPair(update.clone());
-    let info = mock_info("gov", &coins(1234567, "ucosm"));
-    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+fn test_query_channels_paginated_and_filtered() {
+    let mut deps = setup(&["channel-3", "channel-7", "channel-9"], &[]);
+    // channel-9 lives on another connection and has metadata
+    let mut info = mock_channel_info("channel-9");
+    info.connection_id = "connection-9".to_string();
+    info.metadata = Some(ChannelMetadata {
+        chain_id: "cosmoshub-4".to_string(),
+        bech32_prefix: "cosmos".to_string(),
+        is_evm_through_oraibridge: false,
+        evm_prefixes: vec![],
+    });
+    CHANNEL_INFO
+        .save(deps.as_mut().storage, "channel-9", &info)
+        .unwrap();
 
-    // add another pair with a different asset info
-    update.denom = "moon".to_string();
-    update.local_asset_info = AssetInfo::NativeToken {
-        denom: "orai".to_string(),
+    let list_channels = |deps: Deps,
+                         start_after: Option<&str>,
+                         limit: Option<u32>,
+                         connection_id: Option<&str>,
+                         counterparty_chain_id: Option<&str>,
+                         denom: Option<&str>|
+     -> Vec<String> {
+        let res: ListChannelsResponse = from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ListChannels {
+                    start_after: start_after.map(|s| s.to_string()),
+                    limit,
+                    connection_id: connection_id.map(|s| s.to_string()),
+                    counterparty_chain_id: counterparty_chain_id.map(|s| s.to_string()),
+                    denom: denom.map(|s| s.to_string()),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.channels.into_iter().map(|c| c.id).collect()
     };
-    msg = ExecuteMsg::UpdateMappingPair(update.clone());
-    let info = mock_info("gov", &coins(1234567, "ucosm"));
-    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-
-    // query based on asset info
+
+    // pagination
+    assert_eq!(
+        list_channels(deps.as_ref(), None, Some(2), None, None, None),
+        vec!["channel-3", "channel-7"]
+    );
+    assert_eq!(
+        list_channels(deps.as_ref(), Some("channel-7"), Some(2), None, None, None),
+        vec!["channel-9"]
+    );
+    // filters
+    assert_eq!(
+        list_channels(deps.as_ref(), None, None, Some("connection-9"), None, None),
+        vec!["channel-9"]
+    );
+    assert_eq!(
+        list_channels(deps.as_ref(), None, None, None, Some("cosmoshub-4"), None),
+        vec!["channel-9"]
+    );
+
+    // map orai on channel-3 and channel-7 with different remote decimals
+    for (channel, remote_decimals) in [("channel-3", 6u8), ("channel-7", 18u8)] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: channel.to_string(),
+                denom: "uatom".to_string(),
+                local_asset_info: AssetInfo::NativeToken {
+                    denom: "orai".to_string(),
+                },
+                remote_decimals,
+                local_asset_info_decimals: 6,
+                is_mint_burn: None,
+                max_outstanding: None,
+                override_decimals: None,
+                min_amount: None,
+                max_amount: None,
+                status: None,
+                halted: None,
+            }),
+        )
+        .unwrap();
+    }
+    assert_eq!(
+        list_channels(deps.as_ref(), None, None, None, None, Some("orai")),
+        vec!["channel-3", "channel-7"]
+    );
+    assert_eq!(
+        list_channels(deps.as_ref(), None, None, None, None, Some("foobar")),
+        Vec::<String>::new()
+    );
+
+    let ibc_denom_3 = "wasm.cosmos2contract/channel-3/uatom";
+    let ibc_denom_7 = "wasm.cosmos2contract/channel-7/uatom";
+    increase_channel_balance(
+        deps.as_mut().storage,
+        "channel-3",
+        ibc_denom_3,
+        Uint128::from(1000000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        "channel-7",
+        ibc_denom_7,
+        Uint128::from(2000000000000000000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        "channel-7",
+        "wasm.cosmos2contract/channel-7/uosmo",
+        Uint128::from(1u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+
+    // channel balances are paginated by ibc denom
+    let chan = query_channel(deps.as_ref(), "channel-7".to_string(), None, Some(1)).unwrap();
+    assert_eq!(
+        chan.balances,
+        vec![Amount::from_parts(
+            ibc_denom_7.to_string(),
+            Uint128::from(2000000000000000000u128)
+        )]
+    );
+    let chan = query_channel(
+        deps.as_ref(),
+        "channel-7".to_string(),
+        Some(ibc_denom_7.to_string()),
+        Some(1),
+    )
+    .unwrap();
+    assert_eq!(
+        chan.balances,
+        vec![Amount::from_parts(
+            "wasm.cosmos2contract/channel-7/uosmo".to_string(),
+            Uint128::from(1u128)
+        )]
+    );
+
+    // balances of orai across all channels, totals in local decimals
+    let list_channel_balances = |start_after: Option<&str>, limit: Option<u32>| {
+        from_json::<ListChannelBalancesResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ListChannelBalances {
+                    denom: "orai".to_string(),
+                    start_after: start_after.map(|s| s.to_string()),
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let res = list_channel_balances(None, None);
+    assert_eq!(
+        res,
+        ListChannelBalancesResponse {
+            denom: "orai".to_string(),
+            balances: vec![
+                ChannelBalance {
+                    channel_id: "channel-3".to_string(),
+                    ibc_denom: ibc_denom_3.to_string(),
+                    outstanding: Uint128::from(1000000u128),
+                    total_sent: Uint128::from(1000000u128),
+                },
+                ChannelBalance {
+                    channel_id: "channel-7".to_string(),
+                    ibc_denom: ibc_denom_7.to_string(),
+                    outstanding: Uint128::from(2000000000000000000u128),
+                    total_sent: Uint128::from(2000000000000000000u128),
+                },
+            ],
+            total_outstanding: Uint128::from(3000000u128),
+            total_sent: Uint128::from(3000000u128),
+        }
+    );
+
+    // paginated by mapping key, with the totals of the page
+    let first_page = list_channel_balances(None, Some(1));
+    assert_eq!(first_page.balances, res.balances[..1]);
+    assert_eq!(first_page.total_outstanding, Uint128::from(1000000u128));
+    let second_page = list_channel_balances(Some(ibc_denom_3), Some(1));
+    assert_eq!(second_page.balances, res.balances[1..]);
+    assert_eq!(second_page.total_outstanding, Uint128::from(2000000u128));
+    assert_eq!(second_page.total_sent, Uint128::from(2000000u128));
+    assert!(list_channel_balances(Some(ibc_denom_7), None)
+        .balances
+        .is_empty());
+}
+
+#[test]
+fn test_query_pair_mapping_by_asset_info() {
+    let mut deps = setup(&["channel-3", "channel-7"], &[]);
+    let asset_info = AssetInfo::Token {
+        contract_addr: Addr::unchecked("cw20:foobar".to_string()),
+    };
+    let mut update = UpdatePairMsg {
+        local_channel_id: "mars-channel".to_string(),
+        denom: "earth".to_string(),
+        local_asset_info: asset_info.clone(),
+        remote_decimals: 18,
+        local_asset_info_decimals: 18,
+        is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
+    };
+
+    // works with proper funds
+    let mut msg = ExecuteMsg::UpdateMappingPair(update.clone());
+
+    let info = mock_info("gov", &coins(1234567, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+    // add another pair with the same asset info to filter
+    update.denom = "jupiter".to_string();
+    msg = ExecuteMsg::UpdateMappingPair(update.clone());
+    let info = mock_info("gov", &coins(1234567, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+    // add another pair with a different asset info
+    update.denom = "moon".to_string();
+    update.local_asset_info = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    msg = ExecuteMsg::UpdateMappingPair(update.clone());
+    let info = mock_info("gov", &coins(1234567, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+    // query based on asset info
 
     let mappings = query(
         deps.as_ref(),
@@ -994,6 +1688,12 @@ fn test_update_cw20_mapping() {
         remote_decimals: 18,
         local_asset_info_decimals: 18,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     // works with proper funds
@@ -1083,6 +1783,12 @@ fn test_delete_cw20_mapping() {
         remote_decimals: 18,
         local_asset_info_decimals: 18,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     // works with proper funds
@@ -1357,6 +2063,12 @@ fn proper_checks_on_execute_cw20_transfer_back_to_remote() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     let _ = execute(
@@ -1374,6 +2086,7 @@ fn proper_checks_on_execute_cw20_transfer_back_to_remote() {
         remote_denom: denom.to_string(),
         timeout: Some(DEFAULT_TIMEOUT),
         memo: None,
+        referral: None,
     };
 
     let msg = ExecuteMsg::TransferToRemote(transfer.clone());
@@ -1465,7 +2178,7 @@ fn proper_checks_on_execute_cw20_transfer_back_to_remote() {
     }
 
     // check new channel state after reducing balance
-    let chan = query_channel(deps.as_ref(), local_channel.into()).unwrap();
+    let chan = query_channel(deps.as_ref(), local_channel.into(), None, None).unwrap();
     assert_eq!(
         chan.balances,
         vec![Amount::native(
@@ -1491,6 +2204,12 @@ fn proper_checks_on_execute_cw20_transfer_back_to_remote() {
         remote_decimals: 18u8,
         local_asset_info_decimals: 18u8,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     execute(
@@ -1519,28 +2238,50 @@ fn test_update_config() {
         token_fee: Some(vec![
             TokenFee {
                 token_denom: "orai".to_string(),
-                ratio: Ratio {
-                    nominator: 1,
-                    denominator: 10,
+                fee: FeeSchedule {
+                    bps: 1000,
+                    ..FeeSchedule::default()
                 },
+                direction: None,
+                channel_id: None,
             },
             TokenFee {
                 token_denom: "atom".to_string(),
-                ratio: Ratio {
-                    nominator: 1,
-                    denominator: 5,
+                fee: FeeSchedule {
+                    bps: 2000,
+                    flat: Uint128::from(10u64),
+                    min: Uint128::from(20u64),
+                    max: Some(Uint128::from(1000u64)),
                 },
+                direction: None,
+                channel_id: None,
             },
         ]),
         relayer_fee: Some(vec![RelayerFee {
             prefix: "foo".to_string(),
             fee: Uint128::from(1000000u64),
         }]),
+        channel_relayer_fee: Some(vec![ChannelRelayerFee {
+            channel_id: "channel-0".to_string(),
+            destination: "cosmos".to_string(),
+            fee: Uint128::from(500u64),
+        }]),
         fee_receiver: Some("token_fee_receiver".to_string()),
         relayer_fee_receiver: Some("relayer_fee_receiver".to_string()),
         converter_contract: Some("new_converter".to_string()),
         osor_entrypoint_contract: Some("new_osor_contract".to_string()),
         token_factory_addr: Some("new_token_factory_addr".to_string()),
+        token_fee_shares: Some(vec![
+            FeeShare {
+                recipient: FeeRecipient::Address("treasury".to_string()),
+                weight: 9,
+            },
+            FeeShare {
+                recipient: FeeRecipient::Referral,
+                weight: 1,
+            },
+        ]),
+        relayer_fee_shares: None,
     };
     // unauthorized case
     let unauthorized_info = mock_info(&String::from("somebody"), &[]);
@@ -1573,13 +2314,166 @@ fn test_update_config() {
         Addr::unchecked("new_osor_contract")
     );
     assert_eq!(config.token_fees.len(), 2usize);
-    assert_eq!(config.token_fees[0].ratio.denominator, 5);
+    assert_eq!(config.token_fees[0].fee.bps, 2000);
+    assert_eq!(config.token_fees[0].fee.max, Some(Uint128::from(1000u64)));
     assert_eq!(config.token_fees[0].token_denom, "atom".to_string());
-    assert_eq!(config.token_fees[1].ratio.denominator, 10);
+    assert_eq!(config.token_fees[1].fee.bps, 1000);
     assert_eq!(config.token_fees[1].token_denom, "orai".to_string());
     assert_eq!(config.relayer_fees.len(), 1);
     assert_eq!(config.relayer_fees[0].prefix, "foo".to_string());
     assert_eq!(config.relayer_fees[0].amount, Uint128::from(1000000u64));
+    assert_eq!(
+        config.channel_relayer_fees,
+        vec![ChannelRelayerFee {
+            channel_id: "channel-0".to_string(),
+            destination: "cosmos".to_string(),
+            fee: Uint128::from(500u64),
+        }]
+    );
+    assert_eq!(config.token_fee_shares.len(), 2);
+    assert_eq!(config.token_fee_shares[1].recipient, FeeRecipient::Referral);
+    assert_eq!(config.relayer_fee_shares, vec![]);
+}
+
+#[test]
+fn test_deduct_relayer_fee_from_channel_metadata() {
+    let local_channel_id = "channel-0";
+    let mut deps = setup(&[local_channel_id], &[]);
+    let destination_asset_on_orai = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let swap_router_contract = RouterController("foo".to_string());
+    let metadata = ChannelMetadata {
+        chain_id: "oraibridge-subnet-2".to_string(),
+        bech32_prefix: "oraib".to_string(),
+        is_evm_through_oraibridge: true,
+        evm_prefixes: vec!["eth-mainnet".to_string()],
+    };
+
+    // only admin can update the channel metadata
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("somebody", &[]),
+        ExecuteMsg::UpdateChannelMetadata {
+            channel_id: local_channel_id.to_string(),
+            metadata: metadata.clone(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    // unknown channel
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateChannelMetadata {
+            channel_id: "channel-1".to_string(),
+            metadata: metadata.clone(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoSuchChannel {
+            id: "channel-1".to_string()
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateChannelMetadata {
+            channel_id: local_channel_id.to_string(),
+            metadata: metadata.clone(),
+        },
+    )
+    .unwrap();
+    let channel: ChannelResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Channel {
+                id: local_channel_id.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(channel.info.metadata, Some(metadata));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateConfig {
+            admin: None,
+            default_timeout: None,
+            default_gas_limit: None,
+            swap_router_contract: None,
+            token_fee: None,
+            relayer_fee: None,
+            channel_relayer_fee: Some(vec![
+                ChannelRelayerFee {
+                    channel_id: local_channel_id.to_string(),
+                    destination: "eth-mainnet".to_string(),
+                    fee: Uint128::from(1000u64),
+                },
+                ChannelRelayerFee {
+                    channel_id: local_channel_id.to_string(),
+                    destination: "oraib".to_string(),
+                    fee: Uint128::from(100u64),
+                },
+            ]),
+            fee_receiver: None,
+            relayer_fee_receiver: None,
+            converter_contract: None,
+            osor_entrypoint_contract: None,
+            token_factory_addr: None,
+            token_fee_shares: None,
+            relayer_fee_shares: None,
+        },
+    )
+    .unwrap();
+
+    let deps_mut = deps.as_mut();
+    // evm destination is resolved from the remote denom, the remote address does not matter
+    assert_eq!(
+        deduct_relayer_fee(
+            deps_mut.storage,
+            &[],
+            deps_mut.api,
+            &deps_mut.querier,
+            local_channel_id,
+            "foobar",
+            "eth-mainnet0xdAC17F958D2ee523a2206206994597C13D831ec7",
+            destination_asset_on_orai.clone(),
+            &swap_router_contract,
+        )
+        .unwrap(),
+        Uint128::from(1000u64)
+    );
+
+    // non-evm denom falls back to the bech32 prefix of the channel
+    assert_eq!(
+        deduct_relayer_fee(
+            deps_mut.storage,
+            &[],
+            deps_mut.api,
+            &deps_mut.querier,
+            local_channel_id,
+            "foobar",
+            "uatom",
+            destination_asset_on_orai,
+            &swap_router_contract,
+        )
+        .unwrap(),
+        Uint128::from(100u64)
+    );
 }
 
 #[test]
@@ -1623,6 +2517,12 @@ fn test_handle_packet_refund() {
         remote_decimals: 6,
         local_asset_info_decimals: 6,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
 
     let msg = ExecuteMsg::UpdateMappingPair(update.clone());
@@ -1698,6 +2598,12 @@ fn test_increase_channel_balance_ibc_receive() {
         remote_decimals: 6,
         local_asset_info_decimals: 6,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     execute(
         deps.as_mut(),
@@ -1773,6 +2679,12 @@ fn test_reduce_channel_balance_ibc_receive() {
         remote_decimals: 6,
         local_asset_info_decimals: 6,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     execute(
         deps.as_mut(),
@@ -1843,12 +2755,20 @@ fn test_query_channel_balance_with_key() {
     let amount = Uint128::from(10u128);
     let reduce_amount = Uint128::from(1u128);
     let mut deps = setup(&[channel], &[]);
-    increase_channel_balance(deps.as_mut().storage, channel, ibc_denom, amount).unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        amount,
+        mock_env().block.height,
+    )
+    .unwrap();
     reduce_channel_balance(
         deps.as_mut().storage,
         channel,
         ibc_denom,
         Uint128::from(1u128),
+        mock_env().block.height,
     )
     .unwrap();
 
@@ -1876,16 +2796,25 @@ fn test_handle_override_channel_balance() {
     let override_amount = Uint128::from(100u128);
     let total_sent_override = Uint128::from(1000u128);
     let mut deps = setup(&[channel], &[]);
-    increase_channel_balance(deps.as_mut().storage, channel, ibc_denom, amount).unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        amount,
+        mock_env().block.height,
+    )
+    .unwrap();
 
     // unauthorized case
     let unauthorized = handle_override_channel_balance(
         deps.as_mut(),
+        mock_env(),
         mock_info("attacker", &vec![]),
         channel.to_string(),
         ibc_denom.to_string(),
         amount,
         None,
+        "reason".to_string(),
     )
     .unwrap_err();
     assert_eq!(unauthorized, ContractError::Admin(AdminError::NotAdmin {}));
@@ -1893,11 +2822,13 @@ fn test_handle_override_channel_balance() {
     // execution, valid case
     handle_override_channel_balance(
         deps.as_mut(),
+        mock_env(),
         mock_info("gov", &vec![]),
         channel.to_string(),
         ibc_denom.to_string(),
         override_amount,
         Some(total_sent_override),
+        "reason".to_string(),
     )
     .unwrap();
 
@@ -1916,46 +2847,208 @@ fn test_handle_override_channel_balance() {
 }
 
 #[test]
-fn test_get_destination_info_on_orai() {
-    let mut deps = setup(&["channel-3", "channel-7"], &[]);
-    let asset_info = AssetInfo::Token {
-        contract_addr: Addr::unchecked("cw20:foobar".to_string()),
+fn test_override_channel_balance_log_and_max_delta() {
+    let channel = "channel-0";
+    let ibc_denom = "wasm.cosmos2contract/channel-0/uatom";
+    let mut deps = setup(&[channel], &[]);
+    increase_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        Uint128::from(100u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    let override_msg = |outstanding: u128, reason: &str| ExecuteMsg::OverrideChannelBalance {
+        channel_id: channel.to_string(),
+        ibc_denom: ibc_denom.to_string(),
+        outstanding: Uint128::from(outstanding),
+        total_sent: None,
+        reason: reason.to_string(),
     };
-    let update = UpdatePairMsg {
-        local_channel_id: "mars-channel".to_string(),
-        denom: "earth".to_string(),
-        local_asset_info: asset_info.clone(),
-        remote_decimals: 18,
-        local_asset_info_decimals: 18,
-        is_mint_burn: None,
+    let list_overrides = |deps: Deps| -> ListChannelBalanceOverridesResponse {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ChannelBalanceOverrides {
+                    start_after: None,
+                    limit: None,
+                    order: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
     };
 
-    // works with proper funds
-    let msg = ExecuteMsg::UpdateMappingPair(update.clone());
-
-    let info = mock_info("gov", &coins(1234567, "ucosm"));
-    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateOverrideMaxDelta {
+            max_delta: Some(Uint128::from(50u128)),
+        },
+    )
+    .unwrap();
 
-    //case 1: destination asset on Oraichain
-    let destination_info = get_destination_info_on_orai(
-        deps.as_ref().storage,
-        deps.as_ref().api,
-        &mock_env(),
-        "",
-        "orai1lus0f0rhx8s03gdllx2n6vhkmf0536dv57wfge",
-    );
+    // within the max delta, applied right away and logged
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        override_msg(120, "missed ack"),
+    )
+    .unwrap();
+    let overrides = list_overrides(deps.as_ref());
+    assert_eq!(overrides.max_delta, Some(Uint128::from(50u128)));
     assert_eq!(
-        destination_info.0,
-        AssetInfo::Token {
-            contract_addr: Addr::unchecked("orai1lus0f0rhx8s03gdllx2n6vhkmf0536dv57wfge")
-        }
+        overrides.overrides,
+        vec![ChannelBalanceOverrideResponse {
+            id: 1,
+            channel_balance_override: ChannelBalanceOverride {
+                channel_id: channel.to_string(),
+                ibc_denom: ibc_denom.to_string(),
+                old: ChannelState {
+                    outstanding: Uint128::from(100u128),
+                    total_sent: Uint128::from(100u128),
+                },
+                new: ChannelState {
+                    outstanding: Uint128::from(120u128),
+                    total_sent: Uint128::from(100u128),
+                },
+                reason: "missed ack".to_string(),
+                admin: Addr::unchecked("gov"),
+                confirmed_by: None,
+                height: mock_env().block.height,
+            },
+        }]
     );
-    assert_eq!(destination_info.1, None);
 
-    // case 2: Destination asset was registered in mapping pair
-    let destination_info = get_destination_info_on_orai(
-        deps.as_ref().storage,
-        deps.as_ref().api,
+    // exceeding the max delta, kept pending
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        override_msg(1000, "exploit"),
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&cosmwasm_std::attr(
+        "action",
+        "propose_override_channel_balance"
+    )));
+    let state = CHANNEL_REVERSE_STATE
+        .load(deps.as_ref().storage, (channel, ibc_denom))
+        .unwrap();
+    assert_eq!(state.outstanding, Uint128::from(120u128));
+    let pending: ListPendingChannelBalanceOverridesResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PendingChannelBalanceOverrides {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(pending.overrides.len(), 1);
+    assert_eq!(pending.overrides[0].id, 1);
+
+    // the confirmation must happen in a later block
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::ConfirmOverrideChannelBalance { id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OverrideConfirmationTooEarly { id: 1 });
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("attacker", &[]),
+        ExecuteMsg::ConfirmOverrideChannelBalance { id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("gov", &[]),
+        ExecuteMsg::ConfirmOverrideChannelBalance { id: 1 },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("gov", &[]),
+        ExecuteMsg::ConfirmOverrideChannelBalance { id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoSuchOverride { id: 1 });
+
+    let overrides = list_overrides(deps.as_ref());
+    assert_eq!(overrides.overrides.len(), 2);
+    let confirmed = &overrides.overrides[1].channel_balance_override;
+    assert_eq!(confirmed.old.outstanding, Uint128::from(120u128));
+    assert_eq!(confirmed.new.outstanding, Uint128::from(1000u128));
+    assert_eq!(confirmed.reason, "exploit".to_string());
+    assert_eq!(confirmed.confirmed_by, Some(Addr::unchecked("gov")));
+    assert_eq!(confirmed.height, env.block.height);
+}
+
+#[test]
+fn test_get_destination_info_on_orai() {
+    let mut deps = setup(&["channel-3", "channel-7"], &[]);
+    let asset_info = AssetInfo::Token {
+        contract_addr: Addr::unchecked("cw20:foobar".to_string()),
+    };
+    let update = UpdatePairMsg {
+        local_channel_id: "mars-channel".to_string(),
+        denom: "earth".to_string(),
+        local_asset_info: asset_info.clone(),
+        remote_decimals: 18,
+        local_asset_info_decimals: 18,
+        is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
+    };
+
+    // works with proper funds
+    let msg = ExecuteMsg::UpdateMappingPair(update.clone());
+
+    let info = mock_info("gov", &coins(1234567, "ucosm"));
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+    //case 1: destination asset on Oraichain
+    let destination_info = get_destination_info_on_orai(
+        deps.as_ref().storage,
+        deps.as_ref().api,
+        &mock_env(),
+        "",
+        "orai1lus0f0rhx8s03gdllx2n6vhkmf0536dv57wfge",
+    );
+    assert_eq!(
+        destination_info.0,
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("orai1lus0f0rhx8s03gdllx2n6vhkmf0536dv57wfge")
+        }
+    );
+    assert_eq!(destination_info.1, None);
+
+    // case 2: Destination asset was registered in mapping pair
+    let destination_info = get_destination_info_on_orai(
+        deps.as_ref().storage,
+        deps.as_ref().api,
         &mock_env(),
         "mars-channel",
         "earth",
@@ -1981,7 +3074,11 @@ fn test_get_destination_info_on_orai() {
                 },
                 remote_decimals: 18,
                 asset_info_decimals: 18,
-                is_mint_burn: false
+                is_mint_burn: false,
+                max_outstanding: None,
+                status: MappingStatus::Active,
+                min_amount: None,
+                max_amount: None,
             }
         })
     );
@@ -2025,6 +3122,12 @@ fn test_build_mint_mapping_msg() {
         remote_decimals: 18,
         local_asset_info_decimals: 18,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     let msg = ExecuteMsg::UpdateMappingPair(update.clone());
     let info = mock_info("gov", &coins(1234567, "ucosm"));
@@ -2113,6 +3216,12 @@ fn test_build_burn_mapping_msg() {
         remote_decimals: 18,
         local_asset_info_decimals: 18,
         is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     let msg = ExecuteMsg::UpdateMappingPair(update.clone());
     let info = mock_info("gov", &coins(1234567, "ucosm"));
@@ -2206,6 +3315,12 @@ fn test_increase_channel_balance_ibc_receive_with_mint_burn() {
         remote_decimals: 18,
         local_asset_info_decimals: 6,
         is_mint_burn: Some(true),
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     execute(
         deps.as_mut(),
@@ -2303,6 +3418,12 @@ fn test_reduce_channel_balance_ibc_receive_with_mint_burn() {
         remote_decimals: 18,
         local_asset_info_decimals: 6,
         is_mint_burn: Some(true),
+        max_outstanding: None,
+        override_decimals: Some(true),
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
     };
     execute(
         deps.as_mut(),
@@ -2401,6 +3522,8 @@ pub fn test_get_follow_up_msg() {
                 converter_contract: ConverterController("converter".to_string()),
                 osor_entrypoint_contract: "osor_entrypoint_contract".to_string(),
                 token_factory_addr: Addr::unchecked("token_factory_addr"),
+                token_fee_shares: vec![],
+                relayer_fee_shares: vec![],
             },
         )
         .unwrap();
@@ -2487,3 +3610,3736 @@ pub fn test_get_follow_up_msg() {
     );
     // case 4: call universal swap (todo)
 }
+
+#[test]
+fn test_ics20_v2_channel_negotiation() {
+    let mut deps = setup(&[], &[]);
+    add_channel_with_version(deps.as_mut(), "channel-v2", ICS20_VERSION_V2);
+    let chan = query_channel(deps.as_ref(), "channel-v2".to_string(), None, None).unwrap();
+    assert_eq!(chan.info.version, Some(ICS20_VERSION_V2.to_string()));
+
+    // counterparty must speak the same version
+    let channel = mock_channel_with_version("channel-10", ICS20_VERSION_V2);
+    let err = ibc_channel_open(
+        deps.as_mut(),
+        mock_env(),
+        IbcChannelOpenMsg::new_try(channel, ICS20_VERSION),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidIbcVersion {
+            version: ICS20_VERSION.to_string()
+        }
+    );
+
+    // unknown version
+    let channel = mock_channel_with_version("channel-11", "ics20-3");
+    let err = ibc_channel_open(
+        deps.as_mut(),
+        mock_env(),
+        IbcChannelOpenMsg::new_init(channel),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidIbcVersion {
+            version: "ics20-3".to_string()
+        }
+    );
+}
+
+fn mock_receive_packet_v2(local_channel: &str, data: &Ics20PacketV2) -> IbcPacket {
+    IbcPacket::new(
+        data.encode(),
+        IbcEndpoint {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: format!("{}5", local_channel),
+        },
+        IbcEndpoint {
+            port_id: CONTRACT_PORT.to_string(),
+            channel_id: local_channel.to_string(),
+        },
+        3,
+        Timestamp::from_seconds(1665321069).into(),
+    )
+}
+
+#[test]
+fn test_receive_ics20_v2_multi_tokens() {
+    let local_channel = "channel-v2";
+    let relayer = Addr::unchecked("relayer");
+    let mut deps = setup(&[], &[]);
+    add_channel_with_version(deps.as_mut(), local_channel, ICS20_VERSION_V2);
+    for denom in ["uatom", "uosmo"] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: local_channel.to_string(),
+                denom: denom.to_string(),
+                local_asset_info: AssetInfo::NativeToken {
+                    denom: format!("local{}", denom),
+                },
+                remote_decimals: 6,
+                local_asset_info_decimals: 6,
+                is_mint_burn: None,
+                max_outstanding: None,
+                override_decimals: None,
+                min_amount: None,
+                max_amount: None,
+                status: None,
+                halted: None,
+            }),
+        )
+        .unwrap();
+    }
+
+    let mut data = Ics20PacketV2 {
+        tokens: vec![
+            Token {
+                denom: Denom {
+                    base: "uatom".to_string(),
+                    trace: vec![],
+                },
+                amount: Uint128::from(100u128),
+            },
+            Token {
+                denom: Denom {
+                    base: "uosmo".to_string(),
+                    trace: vec![],
+                },
+                amount: Uint128::from(200u128),
+            },
+        ],
+        sender: "remote-sender".to_string(),
+        receiver: "receiver".to_string(),
+        memo: "".to_string(),
+        forwarding: None,
+    };
+
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet_v2(local_channel, &data),
+            relayer.clone(),
+        ),
+    )
+    .unwrap();
+    let ack: Ics20Ack = from_json(&res.acknowledgement).unwrap();
+    assert!(matches!(ack, Ics20Ack::Result(_)));
+    // every token gets its own increase channel balance msg & send msg
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0].msg,
+        wasm_execute(
+            mock_env().contract.address.to_string(),
+            &ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+                dest_channel_id: local_channel.to_string(),
+                ibc_denom: get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom"),
+                amount: Uint128::from(100u128),
+                local_receiver: "receiver".to_string(),
+            },
+            vec![]
+        )
+        .unwrap()
+        .into()
+    );
+    assert_eq!(
+        res.messages[3].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "receiver".to_string(),
+            amount: coins(200, "localuosmo")
+        })
+    );
+
+    // a single unknown token fails the whole packet
+    data.tokens[1].denom.base = "unknown".to_string();
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet_v2(local_channel, &data),
+            relayer.clone(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+    let ack: Ics20Ack = from_json(&res.acknowledgement).unwrap();
+    assert_eq!(
+        ack,
+        Ics20Ack::Error(ContractError::NotOnMappingList {}.to_string())
+    );
+
+    // forwarding through this chain is rejected
+    data.tokens.pop();
+    data.forwarding = Some(ForwardingPacketData {
+        destination_memo: "".to_string(),
+        hops: vec![Hop {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: "channel-1".to_string(),
+        }],
+    });
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(mock_receive_packet_v2(local_channel, &data), relayer),
+    )
+    .unwrap();
+    let ack: Ics20Ack = from_json(&res.acknowledgement).unwrap();
+    assert_eq!(
+        ack,
+        Ics20Ack::Error(ContractError::ForwardingNotSupported {}.to_string())
+    );
+}
+
+#[test]
+fn test_receive_ics20_v2_caps_and_relayer_fee() {
+    let local_channel = "channel-v2";
+    let relayer = Addr::unchecked("relayer");
+    let mut deps = setup(&[], &[]);
+    add_channel_with_version(deps.as_mut(), local_channel, ICS20_VERSION_V2);
+    let update_pair = |denom: &str, max_outstanding: Option<u128>| {
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: denom.to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: max_outstanding.map(Uint128::from),
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        })
+    };
+    for denom in ["uatom", "uosmo"] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            update_pair(denom, None),
+        )
+        .unwrap();
+    }
+    RELAYER_FEE
+        .save(deps.as_mut().storage, "cosmos", &Uint128::from(100u128))
+        .unwrap();
+
+    let token = |denom: &str, amount: u128| Token {
+        denom: Denom {
+            base: denom.to_string(),
+            trace: vec![],
+        },
+        amount: Uint128::from(amount),
+    };
+    let mut data = Ics20PacketV2 {
+        tokens: vec![token("uatom", 1000), token("uatom", 2000)],
+        sender: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+        receiver: "receiver".to_string(),
+        memo: "".to_string(),
+        forwarding: None,
+    };
+    let receive = |deps: DepsMut, data: &Ics20PacketV2| {
+        ibc_packet_receive(
+            deps,
+            mock_env(),
+            IbcPacketReceiveMsg::new(mock_receive_packet_v2(local_channel, data), relayer.clone()),
+        )
+        .unwrap()
+    };
+
+    // a denom repeated in the packet would be checked against the caps once per token
+    let res = receive(deps.as_mut(), &data);
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        from_json::<Ics20Ack>(&res.acknowledgement).unwrap(),
+        Ics20Ack::Error(
+            ContractError::DuplicatePacketDenom {
+                denom: "uatom".to_string()
+            }
+            .to_string()
+        )
+    );
+
+    // a token over the cap of its mapping fails the whole packet
+    data.tokens[1] = token("uosmo", 2000);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_pair("uosmo", Some(1500)),
+    )
+    .unwrap();
+    let res = receive(deps.as_mut(), &data);
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        from_json::<Ics20Ack>(&res.acknowledgement).unwrap(),
+        Ics20Ack::Error(
+            ContractError::OutstandingCapExceeded {
+                id: local_channel.to_string(),
+                denom: get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uosmo"),
+            }
+            .to_string()
+        )
+    );
+
+    // the packet is relayed once, so the relayer fee is only deducted from its first token
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_pair("uosmo", None),
+    )
+    .unwrap();
+    let res = receive(deps.as_mut(), &data);
+    assert!(matches!(
+        from_json::<Ics20Ack>(&res.acknowledgement).unwrap(),
+        Ics20Ack::Result(_)
+    ));
+    let received: Vec<CosmosMsg> = res
+        .messages
+        .into_iter()
+        .map(|sub_msg| sub_msg.msg)
+        .filter(|msg| {
+            matches!(msg, CosmosMsg::Bank(BankMsg::Send { to_address, .. }) if to_address == "receiver")
+        })
+        .collect();
+    assert_eq!(
+        received,
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "receiver".to_string(),
+                amount: coins(900, "orai")
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "receiver".to_string(),
+                amount: coins(2000, "orai")
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_transfer_back_and_timeout_ics20_v2() {
+    let local_channel = "channel-v2";
+    let mut deps = setup(&[], &[]);
+    add_channel_with_version(deps.as_mut(), local_channel, ICS20_VERSION_V2);
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    let amount = Uint128::from(100u128);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "localuatom".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        amount,
+        mock_env().block.height,
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &coins(amount.u128(), "localuatom")),
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let CosmosMsg::Ibc(IbcMsg::SendPacket { data, timeout, .. }) = res.messages[0].msg.clone()
+    else {
+        panic!("Unexpected return message: {:?}", res.messages[0]);
+    };
+    let packet_data = Ics20PacketV2::decode(&data).unwrap();
+    assert_eq!(
+        packet_data.tokens,
+        vec![Token {
+            denom: Denom {
+                base: "uatom".to_string(),
+                trace: vec![Hop {
+                    port_id: CONTRACT_PORT.to_string(),
+                    channel_id: local_channel.to_string(),
+                }],
+            },
+            amount,
+        }]
+    );
+    let chan = query_channel_with_key(deps.as_ref(), local_channel.to_string(), ibc_denom.clone())
+        .unwrap();
+    assert_eq!(chan.balance.amount(), Uint128::zero());
+
+    // timeout refunds the sender and restores the channel balance
+    let packet = IbcPacket::new(
+        data,
+        IbcEndpoint {
+            port_id: CONTRACT_PORT.to_string(),
+            channel_id: local_channel.to_string(),
+        },
+        IbcEndpoint {
+            port_id: REMOTE_PORT.to_string(),
+            channel_id: format!("{}5", local_channel),
+        },
+        3,
+        timeout,
+    );
+    let res = ibc_packet_timeout(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketTimeoutMsg::new(packet, Addr::unchecked("relayer")),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "sender".to_string(),
+                amount: coins(amount.u128(), "localuatom")
+            }),
+            REFUND_FAILURE_ID
+        )]
+    );
+    let chan = query_channel_with_key(deps.as_ref(), local_channel.to_string(), ibc_denom).unwrap();
+    assert_eq!(chan.balance.amount(), amount);
+}
+
+#[test]
+fn test_check_solvency() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    for (denom, local_denom, is_mint_burn) in [
+        ("uatom", "orai", false),
+        ("uosmo", "factory/cosmos2contract/osmo", true),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: local_channel.to_string(),
+                denom: denom.to_string(),
+                local_asset_info: AssetInfo::NativeToken {
+                    denom: local_denom.to_string(),
+                },
+                remote_decimals: 18,
+                local_asset_info_decimals: 6,
+                is_mint_burn: Some(is_mint_burn),
+                max_outstanding: None,
+                override_decimals: None,
+                min_amount: None,
+                max_amount: None,
+                status: None,
+                halted: None,
+            }),
+        )
+        .unwrap();
+    }
+    let atom_key = format!("{}/{}/uatom", CONTRACT_PORT, local_channel);
+    let osmo_key = format!("{}/{}/uosmo", CONTRACT_PORT, local_channel);
+    let receive = |deps: DepsMut, denom: &str| -> Ics20Ack {
+        let res = ibc_packet_receive(
+            deps,
+            mock_env(),
+            IbcPacketReceiveMsg::new(
+                mock_receive_packet(
+                    "channel-05",
+                    local_channel,
+                    Uint128::from(1000000000000000000u128),
+                    denom.to_string(),
+                    "receiver".to_string(),
+                ),
+                Addr::unchecked("relayer"),
+            ),
+        )
+        .unwrap();
+        from_json(&res.acknowledgement).unwrap()
+    };
+    let solvency = |deps: Deps, mapping_key: &str| -> SolvencyResponse {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::CheckSolvency {
+                    mapping_key: mapping_key.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let check_solvency = |mapping_key: &str| ExecuteMsg::CheckSolvency {
+        mapping_key: mapping_key.to_string(),
+    };
+
+    // a pool mapping pays the inbound transfers out of its escrow, which shrinks as the outstanding balance grows
+    deps.querier
+        .update_balance(contract_addr.clone(), coins(3000000, "orai"));
+    for _ in 0..2 {
+        assert!(matches!(
+            receive(deps.as_mut(), "uatom"),
+            Ics20Ack::Result(_)
+        ));
+        // the self-called msg of the receive raises the outstanding balance
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(contract_addr.as_str(), &[]),
+            ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+                dest_channel_id: local_channel.to_string(),
+                ibc_denom: atom_key.clone(),
+                amount: Uint128::from(1000000000000000000u128),
+                local_receiver: "receiver".to_string(),
+            },
+        )
+        .unwrap();
+    }
+    deps.querier
+        .update_balance(contract_addr, coins(1000000, "orai"));
+    let atom_solvency = solvency(deps.as_ref(), &atom_key);
+    assert_eq!(atom_solvency.outstanding, Uint128::from(2000000u128));
+    assert_eq!(atom_solvency.holdings, Uint128::from(1000000u128));
+    assert_eq!(atom_solvency.surplus, Uint128::zero());
+    assert_eq!(atom_solvency.deficit, Uint128::zero());
+
+    // only the admin can halt
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        check_solvency(&atom_key),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        check_solvency(&atom_key),
+    )
+    .unwrap();
+    assert!(!res.attributes.iter().any(|attr| attr.key == "halted"));
+    assert!(!solvency(deps.as_ref(), &atom_key).is_halted);
+    assert!(matches!(
+        receive(deps.as_mut(), "uatom"),
+        Ics20Ack::Result(_)
+    ));
+
+    // mint-burn: the supply must not exceed the outstanding balance
+    deps.querier
+        .update_balance("holder", coins(10, "factory/cosmos2contract/osmo"));
+    let osmo_solvency = solvency(deps.as_ref(), &osmo_key);
+    assert!(osmo_solvency.is_mint_burn);
+    assert_eq!(osmo_solvency.outstanding, Uint128::zero());
+    assert_eq!(osmo_solvency.holdings, Uint128::from(10u128));
+    assert_eq!(osmo_solvency.deficit, Uint128::from(10u128));
+
+    // deficit halts the mapping
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        check_solvency(&osmo_key),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&cosmwasm_std::attr("deficit", "10")));
+    assert!(res
+        .attributes
+        .contains(&cosmwasm_std::attr("halted", "true")));
+    assert_eq!(
+        receive(deps.as_mut(), "uosmo"),
+        Ics20Ack::Error(
+            ContractError::MappingHalted {
+                key: osmo_key.clone()
+            }
+            .to_string()
+        )
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &coins(1000, "factory/cosmos2contract/osmo")),
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1zedxv25ah8fksmg2lzrndrpkvsjqgk4zt5ff7n".to_string(),
+            remote_denom: "uosmo".to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::MappingHalted {
+            key: osmo_key.clone()
+        }
+    );
+
+    // only the admin can resume
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::ResumeMapping {
+            mapping_key: osmo_key.clone(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::ResumeMapping {
+            mapping_key: osmo_key.clone(),
+        },
+    )
+    .unwrap();
+    assert!(!solvency(deps.as_ref(), &osmo_key).is_halted);
+}
+
+#[test]
+fn test_query_channel_balance_at_height() {
+    let channel = "channel-0";
+    let ibc_denom = "wasm.cosmos2contract/channel-0/uatom";
+    let mut deps = setup(&[channel], &[]);
+    let balance_at = |deps: Deps, height: u64| -> (Uint128, Uint128) {
+        let res: ChannelBalanceAtResponse = from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ChannelBalanceAt {
+                    channel_id: channel.to_string(),
+                    denom: ibc_denom.to_string(),
+                    height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        (res.balance.amount(), res.total_sent.amount())
+    };
+
+    increase_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        Uint128::from(100u128),
+        10,
+    )
+    .unwrap();
+    // two transactions in the same block
+    increase_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        Uint128::from(50u128),
+        20,
+    )
+    .unwrap();
+    reduce_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        Uint128::from(80u128),
+        20,
+    )
+    .unwrap();
+    override_channel_balance(
+        deps.as_mut().storage,
+        channel,
+        ibc_denom,
+        Uint128::from(500u128),
+        None,
+        30,
+    )
+    .unwrap();
+
+    // a height reflects the balances after all the transactions of that block
+    assert_eq!(
+        balance_at(deps.as_ref(), 5),
+        (Uint128::zero(), Uint128::zero())
+    );
+    assert_eq!(
+        balance_at(deps.as_ref(), 9),
+        (Uint128::zero(), Uint128::zero())
+    );
+    assert_eq!(
+        balance_at(deps.as_ref(), 10),
+        (Uint128::from(100u128), Uint128::from(100u128))
+    );
+    assert_eq!(
+        balance_at(deps.as_ref(), 19),
+        (Uint128::from(100u128), Uint128::from(100u128))
+    );
+    assert_eq!(
+        balance_at(deps.as_ref(), 20),
+        (Uint128::from(70u128), Uint128::from(150u128))
+    );
+    assert_eq!(
+        balance_at(deps.as_ref(), 29),
+        (Uint128::from(70u128), Uint128::from(150u128))
+    );
+    // no change since the last update, current balances are returned
+    assert_eq!(
+        balance_at(deps.as_ref(), 100),
+        (Uint128::from(500u128), Uint128::from(150u128))
+    );
+}
+
+#[test]
+fn test_outstanding_caps() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = format!("{}/{}/uatom", CONTRACT_PORT, local_channel);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            remote_decimals: 18,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: Some(Uint128::from(2000000000000000000u128)),
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    let receive = |deps: DepsMut, amount: u128| {
+        let res = ibc_packet_receive(
+            deps,
+            mock_env(),
+            IbcPacketReceiveMsg::new(
+                mock_receive_packet(
+                    "channel-05",
+                    local_channel,
+                    Uint128::from(amount),
+                    "uatom".to_string(),
+                    "receiver".to_string(),
+                ),
+                Addr::unchecked("relayer"),
+            ),
+        )
+        .unwrap();
+        from_json::<Ics20Ack>(&res.acknowledgement).unwrap()
+    };
+    let increase_msg = |amount: u128| ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+        dest_channel_id: local_channel.to_string(),
+        ibc_denom: ibc_denom.clone(),
+        amount: Uint128::from(amount),
+        local_receiver: "receiver".to_string(),
+    };
+
+    assert!(matches!(
+        receive(deps.as_mut(), 1500000000000000000),
+        Ics20Ack::Result(_)
+    ));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        increase_msg(1500000000000000000),
+    )
+    .unwrap();
+
+    // mapping cap
+    assert_eq!(
+        receive(deps.as_mut(), 1000000000000000000),
+        Ics20Ack::Error(
+            ContractError::OutstandingCapExceeded {
+                id: local_channel.to_string(),
+                denom: ibc_denom.clone()
+            }
+            .to_string()
+        )
+    );
+    assert_eq!(
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(contract_addr.as_str(), &[]),
+            increase_msg(1000000000000000000),
+        )
+        .unwrap_err(),
+        ContractError::OutstandingCapExceeded {
+            id: local_channel.to_string(),
+            denom: ibc_denom.clone()
+        }
+    );
+
+    let utilization: ChannelUtilizationResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ChannelUtilization {
+                channel_id: local_channel.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        utilization,
+        ChannelUtilizationResponse {
+            channel_id: local_channel.to_string(),
+            mappings: vec![MappingUtilization {
+                ibc_denom: ibc_denom.clone(),
+                outstanding: Uint128::from(1500000000000000000u128),
+                max_outstanding: Some(Uint128::from(2000000000000000000u128)),
+                utilization: Some(Decimal::from_ratio(3u128, 4u128)),
+            }],
+        }
+    );
+
+    // an amount that fits under the cap still gets through
+    assert!(matches!(
+        receive(deps.as_mut(), 500000000000000000),
+        Ics20Ack::Result(_)
+    ));
+}
+
+#[test]
+fn test_mapping_status() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    let update_pair = ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+        local_channel_id: local_channel.to_string(),
+        denom: "uatom".to_string(),
+        local_asset_info: AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        },
+        remote_decimals: 6,
+        local_asset_info_decimals: 6,
+        is_mint_burn: None,
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_pair.clone(),
+    )
+    .unwrap();
+    let update_status = |deps: DepsMut, sender: &str, mapping_key: &str, status: MappingStatus| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::UpdateMappingStatus {
+                mapping_key: mapping_key.to_string(),
+                status,
+            },
+        )
+    };
+    let receive = |deps: DepsMut| -> Ics20Ack {
+        let res = ibc_packet_receive(
+            deps,
+            mock_env(),
+            IbcPacketReceiveMsg::new(
+                mock_receive_packet(
+                    "channel-1",
+                    local_channel,
+                    Uint128::from(100u128),
+                    "uatom".to_string(),
+                    "orai1receiver".to_string(),
+                ),
+                Addr::unchecked("relayer"),
+            ),
+        )
+        .unwrap();
+        from_json(&res.acknowledgement).unwrap()
+    };
+    let transfer_back = |deps: DepsMut| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("sender", &coins(10, "orai")),
+            ExecuteMsg::TransferToRemote(TransferBackMsg {
+                local_channel_id: local_channel.to_string(),
+                remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+                remote_denom: "uatom".to_string(),
+                timeout: None,
+                memo: None,
+                referral: None,
+            }),
+        )
+    };
+
+    assert_eq!(
+        update_status(
+            deps.as_mut(),
+            "foobar",
+            &ibc_denom,
+            MappingStatus::Deprecated
+        )
+        .unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    assert_eq!(
+        update_status(deps.as_mut(), "gov", "unknown", MappingStatus::Deprecated).unwrap_err(),
+        ContractError::MappingPairNotFound {}
+    );
+
+    // outbound only mappings reject inbound transfers
+    update_status(
+        deps.as_mut(),
+        "gov",
+        &ibc_denom,
+        MappingStatus::OutboundOnly,
+    )
+    .unwrap();
+    assert_eq!(
+        receive(deps.as_mut()),
+        Ics20Ack::Error(
+            ContractError::MappingDirectionDisabled {
+                key: ibc_denom.clone(),
+                direction: "inbound".to_string(),
+            }
+            .to_string()
+        )
+    );
+
+    update_status(deps.as_mut(), "gov", &ibc_denom, MappingStatus::Active).unwrap();
+    assert!(matches!(receive(deps.as_mut()), Ics20Ack::Result(_)));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: local_channel.to_string(),
+            ibc_denom: ibc_denom.clone(),
+            amount: Uint128::from(100u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    // inbound only mappings reject outbound transfers
+    update_status(deps.as_mut(), "gov", &ibc_denom, MappingStatus::InboundOnly).unwrap();
+    assert_eq!(
+        transfer_back(deps.as_mut()).unwrap_err(),
+        ContractError::MappingDirectionDisabled {
+            key: ibc_denom.clone(),
+            direction: "outbound".to_string(),
+        }
+    );
+    assert!(matches!(receive(deps.as_mut()), Ics20Ack::Result(_)));
+
+    // updating the pair keeps its status
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_pair,
+    )
+    .unwrap();
+    let pair: PairQuery = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PairMapping {
+                key: ibc_denom.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(pair.pair_mapping.status, MappingStatus::InboundOnly);
+
+    // deprecated mappings accept no transfer, but still process refunds
+    update_status(deps.as_mut(), "gov", &ibc_denom, MappingStatus::Deprecated).unwrap();
+    assert!(matches!(receive(deps.as_mut()), Ics20Ack::Error(_)));
+    transfer_back(deps.as_mut()).unwrap_err();
+    handle_packet_refund(
+        deps.as_mut().storage,
+        "sender",
+        &ibc_denom,
+        Uint128::from(10u128),
+        false,
+    )
+    .unwrap();
+
+    // the mapping cannot be deleted while it has an outstanding balance
+    let delete = ExecuteMsg::DeleteMappingPair(DeletePairMsg {
+        local_channel_id: local_channel.to_string(),
+        denom: "uatom".to_string(),
+    });
+    assert_eq!(
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            delete.clone()
+        )
+        .unwrap_err(),
+        ContractError::MappingHasOutstandingBalance {
+            key: ibc_denom.clone(),
+            outstanding: Uint128::from(100u128),
+        }
+    );
+    reduce_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        Uint128::from(100u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("gov", &[]), delete).unwrap();
+}
+
+#[test]
+fn test_migrate_mapping_asset() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    let old_asset_info = AssetInfo::Token {
+        contract_addr: Addr::unchecked("old-token"),
+    };
+    let new_asset_info = AssetInfo::NativeToken {
+        denom: "factory/token_factory_addr/uatom".to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: old_asset_info.clone(),
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: Some(true),
+            max_outstanding: None,
+            override_decimals: Some(true),
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: local_channel.to_string(),
+            ibc_denom: ibc_denom.clone(),
+            amount: Uint128::from(1000u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    let migrate = |deps: DepsMut, sender: &str, asset_info: AssetInfo| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::MigrateMappingAsset {
+                mapping_key: ibc_denom.clone(),
+                new_asset_info: asset_info,
+                new_asset_info_decimals: 8,
+                is_mint_burn: Some(true),
+                grace_period: 100,
+            },
+        )
+    };
+    assert_eq!(
+        migrate(deps.as_mut(), "foobar", new_asset_info.clone()).unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    assert_eq!(
+        migrate(deps.as_mut(), "gov", old_asset_info.clone()).unwrap_err(),
+        ContractError::MappingAssetUnchanged {
+            key: ibc_denom.clone()
+        }
+    );
+    migrate(deps.as_mut(), "gov", new_asset_info.clone()).unwrap();
+    assert_eq!(
+        migrate(deps.as_mut(), "gov", new_asset_info.clone()).unwrap_err(),
+        ContractError::MappingMigrationInProgress {
+            key: ibc_denom.clone()
+        }
+    );
+
+    // the old asset is still accepted on outbound transfers, converted at par into the new one
+    let transfer_old = |deps: DepsMut, env: Env| {
+        execute(
+            deps,
+            env,
+            mock_info("old-token", &[]),
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "sender".to_string(),
+                amount: Uint128::from(100u128),
+                msg: to_json_binary(&TransferBackMsg {
+                    local_channel_id: local_channel.to_string(),
+                    remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+                    remote_denom: "uatom".to_string(),
+                    timeout: None,
+                    memo: None,
+                    referral: None,
+                })
+                .unwrap(),
+            }),
+        )
+    };
+    let res = transfer_old(deps.as_mut(), mock_env()).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        wasm_execute(
+            "old-token",
+            &Cw20ExecuteMsg::Burn {
+                amount: Uint128::from(100u128)
+            },
+            vec![]
+        )
+        .unwrap()
+        .into()
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        wasm_execute(
+            "token_factory_addr",
+            &tokenfactory::msg::ExecuteMsg::MintTokens {
+                denom: "factory/token_factory_addr/uatom".to_string(),
+                amount: Uint128::from(10000u128),
+                mint_to_address: contract_addr.to_string(),
+            },
+            vec![]
+        )
+        .unwrap()
+        .into()
+    );
+    let packet = res
+        .messages
+        .iter()
+        .find_map(|msg| match &msg.msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => {
+                Some(from_json::<Ics20Packet>(data).unwrap())
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(packet.amount, Uint128::from(100u128));
+
+    let migration: MappingMigrationResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::MappingMigration {
+                mapping_key: ibc_denom.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(migration.new_asset_info, new_asset_info);
+    assert_eq!(migration.migration.old_asset_info, old_asset_info);
+    assert_eq!(migration.migration.converted_amount, Uint128::from(100u128));
+    assert_eq!(migration.migration.converted_transfers, 1);
+    assert!(migration.in_grace_period);
+
+    // refunds are paid in the new asset
+    let refund = handle_packet_refund(
+        deps.as_mut().storage,
+        "sender",
+        &ibc_denom,
+        Uint128::from(100u128),
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        refund.msg,
+        wasm_execute(
+            "token_factory_addr",
+            &tokenfactory::msg::ExecuteMsg::MintTokens {
+                denom: "factory/token_factory_addr/uatom".to_string(),
+                amount: Uint128::from(10000u128),
+                mint_to_address: "sender".to_string(),
+            },
+            vec![]
+        )
+        .unwrap()
+        .into()
+    );
+
+    // after the grace period, only the new asset is accepted
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    assert_eq!(
+        transfer_old(deps.as_mut(), env.clone()).unwrap_err(),
+        ContractError::MappingPairNotFound {}
+    );
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info("sender", &coins(10000, "factory/token_factory_addr/uatom")),
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        }),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_batch_mapping_pairs() {
+    let mut deps = setup(&["channel-0", "channel-1"], &[]);
+    let contract_addr = mock_env().contract.address;
+    let pair = |local_channel: &str, denom: &str| UpdatePairMsg {
+        local_channel_id: local_channel.to_string(),
+        denom: denom.to_string(),
+        local_asset_info: AssetInfo::NativeToken {
+            denom: format!("local{}", denom),
+        },
+        remote_decimals: 6,
+        local_asset_info_decimals: 6,
+        is_mint_burn: Some(false),
+        max_outstanding: None,
+        override_decimals: None,
+        min_amount: None,
+        max_amount: None,
+        status: None,
+        halted: None,
+    };
+    // the export comes with the status and halt of the pairs
+    let exported_pair = |local_channel: &str, denom: &str| UpdatePairMsg {
+        status: Some(MappingStatus::Active),
+        halted: Some(false),
+        ..pair(local_channel, denom)
+    };
+    let token_fee = TokenFee {
+        token_denom: "uatom".to_string(),
+        fee: FeeSchedule {
+            bps: 10,
+            ..FeeSchedule::default()
+        },
+        direction: None,
+        channel_id: None,
+    };
+    let batch_update = |deps: DepsMut, sender: &str, pairs: Vec<UpdatePairMsg>, token_fees| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::BatchUpdateMappingPairs { pairs, token_fees },
+        )
+    };
+    let export = |deps: Deps| -> ExportMappingPairsResponse {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ExportMappingPairs {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(
+        batch_update(
+            deps.as_mut(),
+            "foobar",
+            vec![pair("channel-0", "uatom")],
+            vec![]
+        )
+        .unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    assert_eq!(
+        batch_update(deps.as_mut(), "gov", vec![], vec![]).unwrap_err(),
+        ContractError::EmptyBatch {}
+    );
+    // the whole batch is rejected
+    assert_eq!(
+        batch_update(
+            deps.as_mut(),
+            "gov",
+            vec![pair("channel-0", "uatom"), pair("channel-0", "uatom")],
+            vec![]
+        )
+        .unwrap_err(),
+        ContractError::DuplicateMappingPair {
+            key: get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-0", "uatom")
+        }
+    );
+    assert_eq!(export(deps.as_ref()).pairs.len(), 0);
+
+    batch_update(
+        deps.as_mut(),
+        "gov",
+        vec![pair("channel-0", "uatom"), pair("channel-1", "uosmo")],
+        vec![token_fee.clone()],
+    )
+    .unwrap();
+    let uatom_key = get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-0", "uatom");
+    let uosmo_key = get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-1", "uosmo");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: "channel-0".to_string(),
+            ibc_denom: uatom_key.clone(),
+            amount: Uint128::from(100u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    // the export comes with the fees and balances of the pairs
+    let exported = export(deps.as_ref());
+    assert_eq!(
+        exported.pairs,
+        vec![
+            exported_pair("channel-0", "uatom"),
+            exported_pair("channel-1", "uosmo")
+        ]
+    );
+    assert_eq!(exported.token_fees, vec![token_fee]);
+    assert_eq!(exported.states[0].key, uatom_key);
+    assert_eq!(
+        exported.states[0].balance.outstanding,
+        Uint128::from(100u128)
+    );
+    assert_eq!(exported.states[1].key, uosmo_key);
+    assert_eq!(exported.states[1].balance, ChannelState::default());
+
+    // and can be fed back into the batch import
+    batch_update(
+        deps.as_mut(),
+        "gov",
+        exported.pairs.clone(),
+        exported.token_fees.clone(),
+    )
+    .unwrap();
+    assert_eq!(export(deps.as_ref()), exported);
+
+    // statuses and halts round trip into another contract
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingStatus {
+            mapping_key: uosmo_key.clone(),
+            status: MappingStatus::OutboundOnly,
+        },
+    )
+    .unwrap();
+    HALTED_MAPPINGS
+        .save(deps.as_mut().storage, &uatom_key, &mock_env().block.height)
+        .unwrap();
+    let exported = export(deps.as_ref());
+    assert_eq!(
+        exported.pairs,
+        vec![
+            UpdatePairMsg {
+                halted: Some(true),
+                ..exported_pair("channel-0", "uatom")
+            },
+            UpdatePairMsg {
+                status: Some(MappingStatus::OutboundOnly),
+                ..exported_pair("channel-1", "uosmo")
+            },
+        ]
+    );
+    let mut other_deps = setup(&["channel-0", "channel-1"], &[]);
+    batch_update(
+        other_deps.as_mut(),
+        "gov",
+        exported.pairs.clone(),
+        exported.token_fees.clone(),
+    )
+    .unwrap();
+    let imported = export(other_deps.as_ref());
+    assert_eq!(imported.pairs, exported.pairs);
+    assert_eq!(imported.token_fees, exported.token_fees);
+    let pair_mapping = |deps: Deps, key: &str| -> PairQuery {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::PairMapping {
+                    key: key.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        pair_mapping(other_deps.as_ref(), &uosmo_key)
+            .pair_mapping
+            .status,
+        MappingStatus::OutboundOnly
+    );
+    assert!(HALTED_MAPPINGS.has(other_deps.as_ref().storage, &uatom_key));
+
+    // pairs without status and halt keep their current ones
+    batch_update(
+        other_deps.as_mut(),
+        "gov",
+        vec![pair("channel-0", "uatom"), pair("channel-1", "uosmo")],
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(export(other_deps.as_ref()).pairs, exported.pairs);
+
+    let batch_delete = |deps: DepsMut, pairs: Vec<DeletePairMsg>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::BatchDeleteMappingPairs { pairs },
+        )
+    };
+    let delete_uosmo = DeletePairMsg {
+        local_channel_id: "channel-1".to_string(),
+        denom: "uosmo".to_string(),
+    };
+    // uatom still has an outstanding balance, so nothing is deleted
+    assert_eq!(
+        batch_delete(
+            deps.as_mut(),
+            vec![
+                delete_uosmo.clone(),
+                DeletePairMsg {
+                    local_channel_id: "channel-0".to_string(),
+                    denom: "uatom".to_string(),
+                }
+            ]
+        )
+        .unwrap_err(),
+        ContractError::MappingHasOutstandingBalance {
+            key: uatom_key,
+            outstanding: Uint128::from(100u128),
+        }
+    );
+    assert_eq!(export(deps.as_ref()).pairs.len(), 2);
+    batch_delete(deps.as_mut(), vec![delete_uosmo]).unwrap();
+    assert_eq!(
+        export(deps.as_ref()).pairs,
+        vec![UpdatePairMsg {
+            halted: Some(true),
+            ..exported_pair("channel-0", "uatom")
+        }]
+    );
+}
+
+#[test]
+fn test_mapping_decimals_validation() {
+    let mut deps = setup(&["channel-0"], &[]);
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "cw20:token" => {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&TokenInfoResponse {
+                    name: "token".to_string(),
+                    symbol: "TOKEN".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::zero(),
+                })
+                .unwrap(),
+            ))
+        }
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    deps.querier.bank.set_denom_metadata(&[DenomMetadata {
+        description: "usdt".to_string(),
+        denom_units: vec![
+            DenomUnit {
+                denom: "uusdt".to_string(),
+                exponent: 0,
+                aliases: vec![],
+            },
+            DenomUnit {
+                denom: "usdt".to_string(),
+                exponent: 6,
+                aliases: vec![],
+            },
+        ],
+        base: "uusdt".to_string(),
+        display: "usdt".to_string(),
+        name: "usdt".to_string(),
+        symbol: "USDT".to_string(),
+        uri: "".to_string(),
+        uri_hash: "".to_string(),
+    }]);
+
+    let update = |deps: DepsMut,
+                  local_asset_info: AssetInfo,
+                  remote_decimals: u8,
+                  local_asset_info_decimals: u8,
+                  override_decimals: Option<bool>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: "channel-0".to_string(),
+                denom: "uatom".to_string(),
+                local_asset_info,
+                remote_decimals,
+                local_asset_info_decimals,
+                is_mint_burn: None,
+                max_outstanding: None,
+                override_decimals,
+                min_amount: None,
+                max_amount: None,
+                status: None,
+                halted: None,
+            }),
+        )
+    };
+    let cw20 = AssetInfo::Token {
+        contract_addr: Addr::unchecked("cw20:token"),
+    };
+    let usdt = AssetInfo::NativeToken {
+        denom: "uusdt".to_string(),
+    };
+
+    // decimals that cannot be converted are rejected even when overridden
+    assert_eq!(
+        update(deps.as_mut(), cw20.clone(), 6, 39, Some(true)).unwrap_err(),
+        ContractError::InvalidMappingDecimals {
+            remote_decimals: 6,
+            local_decimals: 39
+        }
+    );
+    assert_eq!(
+        update(deps.as_mut(), usdt.clone(), 25, 6, None).unwrap_err(),
+        ContractError::InvalidMappingDecimals {
+            remote_decimals: 25,
+            local_decimals: 6
+        }
+    );
+
+    // decimals are checked against the cw20 token info and the denom metadata
+    assert_eq!(
+        update(deps.as_mut(), cw20.clone(), 18, 18, None).unwrap_err(),
+        ContractError::MappingDecimalsMismatch {
+            asset: "cw20:token".to_string(),
+            decimals: 18,
+            on_chain: 6
+        }
+    );
+    assert_eq!(
+        update(deps.as_mut(), usdt.clone(), 18, 18, Some(false)).unwrap_err(),
+        ContractError::MappingDecimalsMismatch {
+            asset: "uusdt".to_string(),
+            decimals: 18,
+            on_chain: 6
+        }
+    );
+    update(deps.as_mut(), cw20.clone(), 18, 6, None).unwrap();
+    update(deps.as_mut(), usdt, 18, 6, None).unwrap();
+    // unless overridden
+    update(deps.as_mut(), cw20, 18, 18, Some(true)).unwrap();
+    // native denoms without metadata cannot be checked
+    update(
+        deps.as_mut(),
+        AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        },
+        18,
+        18,
+        None,
+    )
+    .unwrap();
+    // a cw20 that cannot be queried is rejected
+    update(
+        deps.as_mut(),
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("cw20:unknown"),
+        },
+        18,
+        18,
+        None,
+    )
+    .unwrap_err();
+
+    let mapping: PairQuery = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PairMapping {
+                key: get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-0", "uatom"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(mapping.pair_mapping.asset_info_decimals, 18);
+}
+
+#[test]
+fn test_mapping_transfer_amount_bounds() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    let update_pair = |deps: DepsMut, min_amount: u128, max_amount: u128| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: local_channel.to_string(),
+                denom: "uatom".to_string(),
+                local_asset_info: AssetInfo::NativeToken {
+                    denom: "orai".to_string(),
+                },
+                remote_decimals: 18,
+                local_asset_info_decimals: 6,
+                is_mint_burn: None,
+                max_outstanding: None,
+                override_decimals: None,
+                min_amount: Some(Uint128::from(min_amount)),
+                max_amount: Some(Uint128::from(max_amount)),
+                status: None,
+                halted: None,
+            }),
+        )
+    };
+    let receive = |deps: DepsMut, amount: u128| -> Ics20Ack {
+        let res = ibc_packet_receive(
+            deps,
+            mock_env(),
+            IbcPacketReceiveMsg::new(
+                mock_receive_packet(
+                    "channel-1",
+                    local_channel,
+                    Uint128::from(amount),
+                    "uatom".to_string(),
+                    "orai1receiver".to_string(),
+                ),
+                Addr::unchecked("relayer"),
+            ),
+        )
+        .unwrap();
+        from_json(&res.acknowledgement).unwrap()
+    };
+    let transfer_back = |deps: DepsMut, amount: u128| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("sender", &coins(amount, "orai")),
+            ExecuteMsg::TransferToRemote(TransferBackMsg {
+                local_channel_id: local_channel.to_string(),
+                remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+                remote_denom: "uatom".to_string(),
+                timeout: None,
+                memo: None,
+                referral: None,
+            }),
+        )
+    };
+
+    assert_eq!(
+        update_pair(deps.as_mut(), 10, 1).unwrap_err(),
+        ContractError::InvalidTransferAmountBounds {
+            min_amount: Uint128::from(10u128),
+            max_amount: Uint128::one()
+        }
+    );
+    // between 10 and 1000 orai
+    update_pair(deps.as_mut(), 10_000_000_000_000, 1_000_000_000_000_000).unwrap();
+    let pair: PairQuery = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PairMapping {
+                key: ibc_denom.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        pair.pair_mapping.min_amount,
+        Some(Uint128::from(10_000_000_000_000u128))
+    );
+    assert_eq!(
+        pair.pair_mapping.max_amount,
+        Some(Uint128::from(1_000_000_000_000_000u128))
+    );
+
+    // inbound transfers out of bounds are acked with an error so that the source chain refunds them
+    assert_eq!(
+        receive(deps.as_mut(), 1_000_000_000_000),
+        Ics20Ack::Error(
+            ContractError::TransferAmountTooSmall {
+                key: ibc_denom.clone(),
+                amount: Uint128::from(1_000_000_000_000u128),
+                min_amount: Uint128::from(10_000_000_000_000u128),
+            }
+            .to_string()
+        )
+    );
+    assert_eq!(
+        receive(deps.as_mut(), 10_000_000_000_000_000),
+        Ics20Ack::Error(
+            ContractError::TransferAmountTooLarge {
+                key: ibc_denom.clone(),
+                amount: Uint128::from(10_000_000_000_000_000u128),
+                max_amount: Uint128::from(1_000_000_000_000_000u128),
+            }
+            .to_string()
+        )
+    );
+    assert!(matches!(
+        receive(deps.as_mut(), 100_000_000_000_000),
+        Ics20Ack::Result(_)
+    ));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: local_channel.to_string(),
+            ibc_denom: ibc_denom.clone(),
+            amount: Uint128::from(100_000_000_000_000u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    // outbound amounts are converted to remote decimals before being checked
+    assert_eq!(
+        transfer_back(deps.as_mut(), 5).unwrap_err(),
+        ContractError::TransferAmountTooSmall {
+            key: ibc_denom.clone(),
+            amount: Uint128::from(5_000_000_000_000u128),
+            min_amount: Uint128::from(10_000_000_000_000u128),
+        }
+    );
+    assert_eq!(
+        transfer_back(deps.as_mut(), 2000).unwrap_err(),
+        ContractError::TransferAmountTooLarge {
+            key: ibc_denom.clone(),
+            amount: Uint128::from(2_000_000_000_000_000u128),
+            max_amount: Uint128::from(1_000_000_000_000_000u128),
+        }
+    );
+    transfer_back(deps.as_mut(), 100).unwrap();
+}
+
+#[test]
+fn test_token_factory_administration() {
+    let mut deps = setup(&[], &[]);
+    let denom = "factory/token_factory_addr/uatom";
+    let force_transfer = |deps: DepsMut, sender: &str, denom: &str| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::ForceTransfer {
+                denom: denom.to_string(),
+                amount: Uint128::from(10u128),
+                from_address: "orai1holder".to_string(),
+                to_address: "orai1recipient".to_string(),
+            },
+        )
+    };
+
+    // only the denoms created by the bridge can be administered
+    assert_eq!(
+        force_transfer(deps.as_mut(), "gov", denom).unwrap_err(),
+        ContractError::UnknownTokenFactoryDenom {
+            denom: denom.to_string()
+        }
+    );
+    for subdenom in ["uosmo", "uatom"] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::RegisterDenom(RegisterDenomMsg {
+                subdenom: subdenom.to_string(),
+                metadata: None,
+            }),
+        )
+        .unwrap();
+    }
+    assert_eq!(
+        force_transfer(deps.as_mut(), "foobar", denom).unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    let res = force_transfer(deps.as_mut(), "gov", denom).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            wasm_execute(
+                "token_factory_addr",
+                &tokenfactory::msg::ExecuteMsg::ForceTransfer {
+                    denom: denom.to_string(),
+                    amount: Uint128::from(10u128),
+                    from_address: "orai1holder".to_string(),
+                    to_address: "orai1recipient".to_string(),
+                },
+                vec![],
+            )
+            .unwrap()
+        )]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::BurnFrom {
+            denom: denom.to_string(),
+            amount: Uint128::from(5u128),
+            burn_from_address: "orai1holder".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            wasm_execute(
+                "token_factory_addr",
+                &tokenfactory::msg::ExecuteMsg::BurnTokens {
+                    denom: denom.to_string(),
+                    amount: Uint128::from(5u128),
+                    burn_from_address: "orai1holder".to_string(),
+                },
+                vec![],
+            )
+            .unwrap()
+        )]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::ChangeDenomAdmin {
+            denom: denom.to_string(),
+            new_admin_address: "orai1newadmin".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            wasm_execute(
+                "token_factory_addr",
+                &tokenfactory::msg::ExecuteMsg::ChangeAdmin {
+                    denom: denom.to_string(),
+                    new_admin_address: "orai1newadmin".to_string(),
+                },
+                vec![],
+            )
+            .unwrap()
+        )]
+    );
+
+    deps.querier
+        .update_balance("orai1holder", coins(100, denom));
+    let list = |deps: Deps, start_after: Option<String>| {
+        from_json::<ListTokenFactoryDenomsResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::TokenFactoryDenoms {
+                    start_after,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .denoms
+    };
+    assert_eq!(
+        list(deps.as_ref(), None),
+        vec![
+            TokenFactoryDenomResponse {
+                denom: denom.to_string(),
+                subdenom: "uatom".to_string(),
+                supply: Uint128::from(100u128),
+            },
+            TokenFactoryDenomResponse {
+                denom: "factory/token_factory_addr/uosmo".to_string(),
+                subdenom: "uosmo".to_string(),
+                supply: Uint128::zero(),
+            }
+        ]
+    );
+    assert_eq!(list(deps.as_ref(), Some(denom.to_string())).len(), 1);
+
+    // denoms created before they were tracked are registered from an explicit list
+    let register = |deps: DepsMut, sender: &str, denoms: &[&str]| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::RegisterTokenFactoryDenoms {
+                denoms: denoms.iter().map(|denom| denom.to_string()).collect(),
+            },
+        )
+    };
+    let legacy_denom = "factory/token_factory_addr/uusdt";
+    assert_eq!(
+        register(deps.as_mut(), "foobar", &[legacy_denom]).unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    for invalid_denom in ["factory/orai1creator/uusdt", "factory/token_factory_addr/"] {
+        assert_eq!(
+            register(deps.as_mut(), "gov", &[legacy_denom, invalid_denom]).unwrap_err(),
+            ContractError::InvalidTokenFactoryDenom {
+                denom: invalid_denom.to_string()
+            }
+        );
+    }
+    assert_eq!(list(deps.as_ref(), None).len(), 2);
+    register(deps.as_mut(), "gov", &[legacy_denom]).unwrap();
+    assert_eq!(
+        list(deps.as_ref(), Some(denom.to_string())),
+        vec![
+            TokenFactoryDenomResponse {
+                denom: "factory/token_factory_addr/uosmo".to_string(),
+                subdenom: "uosmo".to_string(),
+                supply: Uint128::zero(),
+            },
+            TokenFactoryDenomResponse {
+                denom: legacy_denom.to_string(),
+                subdenom: "uusdt".to_string(),
+                supply: Uint128::zero(),
+            }
+        ]
+    );
+    force_transfer(deps.as_mut(), "gov", legacy_denom).unwrap();
+}
+
+#[test]
+fn test_reindex_mappings() {
+    let mut deps = setup(&["channel-0", "channel-1"], &[]);
+    // mappings saved before the channel index and the denom traces were recorded
+    let legacy_mappings: Map<&str, MappingMetadata> = Map::new("ics20_mapping_namespace");
+    for local_channel in ["channel-0", "channel-1"] {
+        legacy_mappings
+            .save(
+                deps.as_mut().storage,
+                &get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom"),
+                &MappingMetadata {
+                    // a token factory denom, possibly created by another account
+                    asset_info: AssetInfo::NativeToken {
+                        denom: "factory/token_factory_addr/uatom".to_string(),
+                    },
+                    remote_decimals: 6,
+                    asset_info_decimals: 6,
+                    is_mint_burn: false,
+                    max_outstanding: None,
+                    status: MappingStatus::Active,
+                    min_amount: None,
+                    max_amount: None,
+                },
+            )
+            .unwrap();
+    }
+    let channel_mappings = |deps: Deps, local_channel: &str| -> usize {
+        from_json::<ListChannelMappingsResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::PairMappingsByChannel {
+                    channel_id: local_channel.to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .pairs
+        .len()
+    };
+    let denom_traces = |deps: Deps| -> usize {
+        from_json::<ListDenomTracesResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::DenomTraces {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .traces
+        .len()
+    };
+    let reindex = |deps: DepsMut, sender: &str, start_after: Option<String>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::ReindexMappings {
+                start_after,
+                limit: Some(1),
+            },
+        )
+    };
+    assert_eq!(channel_mappings(deps.as_ref(), "channel-0"), 0);
+    assert_eq!(denom_traces(deps.as_ref()), 0);
+
+    assert_eq!(
+        reindex(deps.as_mut(), "foobar", None).unwrap_err(),
+        ContractError::Admin(AdminError::NotAdmin {})
+    );
+    // one page at a time, continuing from the last key
+    let last_key = |res: Response| -> String {
+        res.attributes
+            .into_iter()
+            .find(|attr| attr.key == "last_key")
+            .unwrap()
+            .value
+    };
+    let first_key = last_key(reindex(deps.as_mut(), "gov", None).unwrap());
+    assert_eq!(
+        first_key,
+        get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-0", "uatom")
+    );
+    assert_eq!(channel_mappings(deps.as_ref(), "channel-0"), 1);
+    assert_eq!(channel_mappings(deps.as_ref(), "channel-1"), 0);
+    assert_eq!(denom_traces(deps.as_ref()), 1);
+
+    let second_key = last_key(reindex(deps.as_mut(), "gov", Some(first_key)).unwrap());
+    assert_eq!(channel_mappings(deps.as_ref(), "channel-1"), 1);
+    assert_eq!(denom_traces(deps.as_ref()), 2);
+    // nothing left
+    assert_eq!(
+        last_key(reindex(deps.as_mut(), "gov", Some(second_key)).unwrap()),
+        ""
+    );
+    // the local denoms of the mappings are not taken for token factory denoms of the bridge
+    let token_factory_denoms: ListTokenFactoryDenomsResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::TokenFactoryDenoms {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(token_factory_denoms.denoms, vec![]);
+}
+
+#[test]
+fn test_pair_mappings_by_channel() {
+    let mut deps = setup(&["channel-0", "channel-1", "channel-10"], &[]);
+    let contract_addr = mock_env().contract.address;
+    for (local_channel, denom) in [
+        ("channel-1", "uosmo"),
+        ("channel-1", "uatom"),
+        ("channel-10", "uatom"),
+        ("channel-0", "uatom"),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+                local_channel_id: local_channel.to_string(),
+                denom: denom.to_string(),
+                local_asset_info: AssetInfo::NativeToken {
+                    denom: format!("local{}", denom),
+                },
+                remote_decimals: 6,
+                local_asset_info_decimals: 6,
+                is_mint_burn: None,
+                max_outstanding: None,
+                override_decimals: None,
+                min_amount: None,
+                max_amount: None,
+                status: None,
+                halted: None,
+            }),
+        )
+        .unwrap();
+    }
+    let uatom_key = get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-1", "uatom");
+    let uosmo_key = get_key_ics20_ibc_denom(CONTRACT_PORT, "channel-1", "uosmo");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: "channel-1".to_string(),
+            ibc_denom: uosmo_key.clone(),
+            amount: Uint128::from(100u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    let list = |deps: Deps, start_after: Option<String>, limit: Option<u32>| {
+        from_json::<ListChannelMappingsResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::PairMappingsByChannel {
+                    channel_id: "channel-1".to_string(),
+                    start_after,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    // channel-10 is not listed under channel-1
+    let res = list(deps.as_ref(), None, None);
+    assert_eq!(res.channel_id, "channel-1".to_string());
+    assert_eq!(
+        res.pairs
+            .iter()
+            .map(|pair| pair.key.clone())
+            .collect::<Vec<_>>(),
+        vec![uatom_key.clone(), uosmo_key.clone()]
+    );
+    assert_eq!(res.pairs[0].balance, ChannelState::default());
+    assert_eq!(res.pairs[1].balance.outstanding, Uint128::from(100u128));
+    assert_eq!(
+        res.pairs[1].pair_mapping.asset_info,
+        AssetInfo::NativeToken {
+            denom: "localuosmo".to_string()
+        }
+    );
+
+    let res = list(deps.as_ref(), None, Some(1));
+    assert_eq!(res.pairs.len(), 1);
+    assert_eq!(res.pairs[0].key, uatom_key);
+    let res = list(deps.as_ref(), Some(uatom_key), None);
+    assert_eq!(res.pairs.len(), 1);
+    assert_eq!(res.pairs[0].key, uosmo_key);
+}
+
+#[test]
+fn test_channel_stats() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let contract_addr = mock_env().contract.address;
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    TOKEN_FEE
+        .save(
+            deps.as_mut().storage,
+            "uatom",
+            &Ratio {
+                nominator: 1,
+                denominator: 10,
+            },
+        )
+        .unwrap();
+
+    // receive 100 uatom, 10 of which are taken as token fee
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet(
+                "channel-1",
+                local_channel,
+                Uint128::from(100u128),
+                "uatom".to_string(),
+                "orai1receiver".to_string(),
+            ),
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    let ack: Ics20Ack = from_json(&res.acknowledgement).unwrap();
+    assert!(matches!(ack, Ics20Ack::Result(_)));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(contract_addr.as_str(), &[]),
+        ExecuteMsg::IncreaseChannelBalanceIbcReceive {
+            dest_channel_id: local_channel.to_string(),
+            ibc_denom: ibc_denom.clone(),
+            amount: Uint128::from(100u128),
+            local_receiver: "orai1receiver".to_string(),
+        },
+    )
+    .unwrap();
+
+    let transfer_back = |deps: DepsMut, amount: u128| {
+        let res = execute(
+            deps,
+            mock_env(),
+            mock_info("sender", &coins(amount, "orai")),
+            ExecuteMsg::TransferToRemote(TransferBackMsg {
+                local_channel_id: local_channel.to_string(),
+                remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+                remote_denom: "uatom".to_string(),
+                timeout: None,
+                memo: None,
+                referral: None,
+            }),
+        )
+        .unwrap();
+        let Some(CosmosMsg::Ibc(IbcMsg::SendPacket { data, timeout, .. })) = res
+            .messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .find(|msg| matches!(msg, CosmosMsg::Ibc(_)))
+        else {
+            panic!("no ibc packet sent");
+        };
+        IbcPacket::new(
+            data,
+            IbcEndpoint {
+                port_id: CONTRACT_PORT.to_string(),
+                channel_id: local_channel.to_string(),
+            },
+            IbcEndpoint {
+                port_id: REMOTE_PORT.to_string(),
+                channel_id: "channel-1".to_string(),
+            },
+            3,
+            timeout,
+        )
+    };
+
+    // 40 is returned after a 4 token fee, then refunded by an error acknowledgement
+    let packet = transfer_back(deps.as_mut(), 40);
+    ibc_packet_ack(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketAckMsg::new(
+            IbcAcknowledgement::new(to_json_binary(&Ics20Ack::Error("error".to_string())).unwrap()),
+            packet,
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    // 20 is returned after a 2 token fee, then refunded by a timeout
+    let packet = transfer_back(deps.as_mut(), 20);
+    ibc_packet_timeout(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketTimeoutMsg::new(packet, Addr::unchecked("relayer")),
+    )
+    .unwrap();
+
+    let chan = query_channel_with_key(deps.as_ref(), local_channel.to_string(), ibc_denom).unwrap();
+    assert_eq!(chan.balance.amount(), Uint128::from(100u128));
+    assert_eq!(
+        chan.stats,
+        ChannelStats {
+            total_received: Uint128::from(100u128),
+            received_packets: 1,
+            total_returned: Uint128::from(54u128),
+            returned_packets: 2,
+            total_refunded: Uint128::from(36u128),
+            refunded_packets: 1,
+            total_timed_out: Uint128::from(18u128),
+            timed_out_packets: 1,
+            total_token_fee: Uint128::from(16u128),
+            total_relayer_fee: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn test_token_fee_schedule_loads_legacy_ratio() {
+    let mut deps = setup(&[], &[]);
+    for (denom, denominator) in [("orai", 10), ("uatom", 100)] {
+        TOKEN_FEE
+            .save(
+                deps.as_mut().storage,
+                denom,
+                &Ratio {
+                    nominator: 1,
+                    denominator,
+                },
+            )
+            .unwrap();
+    }
+    let token_fee = |deps: Deps, denom: &str| -> FeeSchedule {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::GetTransferTokenFee {
+                    remote_token_denom: denom.to_string(),
+                    direction: None,
+                    channel_id: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(token_fee(deps.as_ref(), "uatom").bps, 100);
+    assert_eq!(
+        deduct_token_fee(
+            deps.as_mut().storage,
+            &[],
+            FeeDirection::Inbound,
+            "channel-0",
+            "uatom",
+            Uint128::from(1000u64)
+        )
+        .unwrap(),
+        (Uint128::from(990u64), Uint128::from(10u64))
+    );
+
+    // a new schedule replaces the legacy ratio
+    let fee_schedule = FeeSchedule {
+        bps: 50,
+        flat: Uint128::from(1u64),
+        min: Uint128::from(2u64),
+        max: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateConfig {
+            admin: None,
+            default_timeout: None,
+            default_gas_limit: None,
+            swap_router_contract: None,
+            token_fee: Some(vec![TokenFee {
+                token_denom: "orai".to_string(),
+                fee: fee_schedule.clone(),
+                direction: None,
+                channel_id: None,
+            }]),
+            relayer_fee: None,
+            channel_relayer_fee: None,
+            fee_receiver: None,
+            relayer_fee_receiver: None,
+            converter_contract: None,
+            osor_entrypoint_contract: None,
+            token_factory_addr: None,
+            token_fee_shares: None,
+            relayer_fee_shares: None,
+        },
+    )
+    .unwrap();
+    assert!(!TOKEN_FEE.has(deps.as_ref().storage, "orai"));
+    assert_eq!(token_fee(deps.as_ref(), "orai"), fee_schedule);
+    assert_eq!(
+        deduct_token_fee(
+            deps.as_mut().storage,
+            &[],
+            FeeDirection::Outbound,
+            "channel-0",
+            "orai",
+            Uint128::from(1000u64)
+        )
+        .unwrap(),
+        (Uint128::from(994u64), Uint128::from(6u64))
+    );
+
+    let config: ConfigResponse =
+        from_json(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        config.token_fees,
+        vec![
+            TokenFee {
+                token_denom: "orai".to_string(),
+                fee: fee_schedule,
+                direction: None,
+                channel_id: None,
+            },
+            TokenFee {
+                token_denom: "uatom".to_string(),
+                fee: FeeSchedule {
+                    bps: 100,
+                    ..FeeSchedule::default()
+                },
+                direction: None,
+                channel_id: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_migrate_legacy_token_fees() {
+    let mut deps = setup(&[], &[]);
+    for (denom, denominator) in [("uatom", 100), ("orai", 100000)] {
+        TOKEN_FEE
+            .save(
+                deps.as_mut().storage,
+                denom,
+                &Ratio {
+                    nominator: 1,
+                    denominator,
+                },
+            )
+            .unwrap();
+    }
+    let migrate_msg = MigrateMsg {
+        token_fee_receiver: Addr::unchecked("token_fee_receiver"),
+        relayer_fee_receiver: Addr::unchecked("relayer_fee_receiver"),
+        default_timeout: DEFAULT_TIMEOUT,
+        default_gas_limit: None,
+        swap_router_contract: "router".to_string(),
+        converter_contract: "converter".to_string(),
+        osor_entrypoint_contract: "osor_entrypoint".to_string(),
+        token_factory_addr: Addr::unchecked("token_factory_addr"),
+    };
+
+    // a tenth of a basis point cannot be kept by a fee schedule
+    assert_eq!(
+        migrate(deps.as_mut(), mock_env(), migrate_msg.clone()).unwrap_err(),
+        ContractError::LossyTokenFeeRatio {
+            denom: "orai".to_string(),
+            nominator: 1,
+            denominator: 100000,
+        }
+    );
+
+    // the admin replaces it with a schedule first
+    let orai_fee = FeeSchedule {
+        bps: 1,
+        ..FeeSchedule::default()
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateFees {
+            updates: vec![FeeUpdate::SetTokenFee(TokenFee {
+                token_denom: "orai".to_string(),
+                fee: orai_fee.clone(),
+                direction: None,
+                channel_id: None,
+            })],
+            effective_at: None,
+        },
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), migrate_msg).unwrap();
+    assert!(TOKEN_FEE
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .next()
+        .is_none());
+    assert_eq!(
+        TOKEN_FEE_SCHEDULE
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap(),
+        vec![
+            (
+                ("".to_string(), "".to_string(), "orai".to_string()),
+                orai_fee
+            ),
+            (
+                ("".to_string(), "".to_string(), "uatom".to_string()),
+                FeeSchedule {
+                    bps: 100,
+                    ..FeeSchedule::default()
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_fee_schedule_validation() {
+    let mut deps = setup(&[], &[]);
+    let update_fee = |deps: DepsMut, fee: FeeSchedule, effective_at: Option<Timestamp>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateFees {
+                updates: vec![FeeUpdate::SetTokenFee(TokenFee {
+                    token_denom: "uatom".to_string(),
+                    fee,
+                    direction: None,
+                    channel_id: None,
+                })],
+                effective_at,
+            },
+        )
+    };
+
+    assert_eq!(
+        update_fee(
+            deps.as_mut(),
+            FeeSchedule {
+                bps: MAX_BPS + 1,
+                ..FeeSchedule::default()
+            },
+            None
+        )
+        .unwrap_err(),
+        ContractError::InvalidFeeBps { bps: MAX_BPS + 1 }
+    );
+    // scheduled changes are checked when scheduled
+    let inverted_bounds = FeeSchedule {
+        bps: 10,
+        flat: Uint128::zero(),
+        min: Uint128::from(10u128),
+        max: Some(Uint128::from(5u128)),
+    };
+    assert_eq!(
+        update_fee(
+            deps.as_mut(),
+            inverted_bounds,
+            Some(mock_env().block.time.plus_seconds(100))
+        )
+        .unwrap_err(),
+        ContractError::InvalidFeeBounds {
+            min: Uint128::from(10u128),
+            max: Uint128::from(5u128),
+        }
+    );
+    update_fee(
+        deps.as_mut(),
+        FeeSchedule {
+            bps: MAX_BPS,
+            flat: Uint128::zero(),
+            min: Uint128::from(5u128),
+            max: Some(Uint128::from(5u128)),
+        },
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_fee_exemptions() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    save_token_fee(
+        deps.as_mut().storage,
+        &TokenFee {
+            token_denom: "uatom".to_string(),
+            fee: FeeSchedule {
+                bps: 1000,
+                ..FeeSchedule::default()
+            },
+            direction: None,
+            channel_id: None,
+        },
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        Uint128::from(1000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+
+    let update_exemption = |direction: FeeDirection, address: &str, token_fee_discount_bps| {
+        ExecuteMsg::UpdateFeeExemption {
+            direction,
+            address: address.to_string(),
+            exemption: Some(FeeExemption {
+                token_fee_discount_bps,
+                relayer_fee_discount_bps: MAX_BPS,
+            }),
+        }
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("partner", &[]),
+        update_exemption(FeeDirection::Outbound, "partner", 5000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_exemption(FeeDirection::Outbound, "partner", MAX_BPS + 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidFeeDiscount {});
+    for (direction, address, discount) in [
+        (FeeDirection::Outbound, "partner", 5000),
+        (FeeDirection::Inbound, "remote-sender", MAX_BPS),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gov", &[]),
+            update_exemption(direction, address, discount),
+        )
+        .unwrap();
+    }
+    let exemptions: ListFeeExemptionsResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FeeExemptions {
+                direction: FeeDirection::Outbound,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        exemptions.exemptions,
+        vec![FeeExemptionResponse {
+            address: "partner".to_string(),
+            exemption: FeeExemption {
+                token_fee_discount_bps: 5000,
+                relayer_fee_discount_bps: MAX_BPS,
+            },
+        }]
+    );
+
+    let find_attr = |attributes: &[Attribute], key: &str| {
+        attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    // the token fee of the remote sender is waived
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet(
+                "channel-1",
+                local_channel,
+                Uint128::from(100u128),
+                "uatom".to_string(),
+                "orai1receiver".to_string(),
+            ),
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    assert_eq!(find_attr(&res.attributes, "token_fee"), "0");
+    assert_eq!(find_attr(&res.attributes, "token_fee_discount"), "10");
+
+    // the partner pays half of the token fee, other senders pay it in full
+    for (sender, token_fee, discount) in [("partner", "5", "5"), ("sender", "10", "0")] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(sender, &coins(100, "orai")),
+            ExecuteMsg::TransferToRemote(TransferBackMsg {
+                local_channel_id: local_channel.to_string(),
+                remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+                remote_denom: "uatom".to_string(),
+                timeout: None,
+                memo: None,
+                referral: None,
+            }),
+        )
+        .unwrap();
+        assert_eq!(find_attr(&res.attributes, "token_fee"), token_fee);
+        assert_eq!(find_attr(&res.attributes, "token_fee_discount"), discount);
+    }
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateFeeExemption {
+            direction: FeeDirection::Outbound,
+            address: "partner".to_string(),
+            exemption: None,
+        },
+    )
+    .unwrap();
+    assert!(FEE_EXEMPTIONS
+        .may_load(deps.as_ref().storage, ("outbound", "partner"))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_relayer_fee_paid_in_orai() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "uatom".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        Uint128::from(1000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    RELAYER_FEE
+        .save(deps.as_mut().storage, "cosmos", &Uint128::from(100u128))
+        .unwrap();
+
+    let transfer = ExecuteMsg::TransferToRemote(TransferBackMsg {
+        local_channel_id: local_channel.to_string(),
+        remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+        remote_denom: "uatom".to_string(),
+        timeout: None,
+        memo: None,
+        referral: None,
+    });
+    let funds = |orai: u128| vec![Coin::new(orai, "orai"), Coin::new(100, "uatom")];
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &funds(50)),
+        transfer.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientRelayerFeePayment {
+            required: Uint128::from(100u128),
+            paid: Uint128::from(50u128),
+        }
+    );
+
+    // the relayer fee is paid in ORAI, the whole amount is transferred and the excess ORAI refunded
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &funds(150)),
+        transfer,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0..2],
+        vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "gov".to_string(),
+                amount: coins(100, "orai"),
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "sender".to_string(),
+                amount: coins(50, "orai"),
+            }),
+        ]
+    );
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(attribute("relayer_fee"), "100");
+    assert_eq!(attribute("relayer_fee_price_source"), "orai_payment");
+    assert_eq!(attribute("relayer_fee_refund"), "50");
+    assert_eq!(attribute("amount"), "100");
+}
+
+#[test]
+fn test_relayer_fee_deposit_for_cw20_transfer() {
+    let local_channel = "channel-0";
+    let cw20_addr = "cw20-token";
+    let mut deps = setup(&[local_channel], &[]);
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::Token {
+                contract_addr: Addr::unchecked(cw20_addr),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: Some(true),
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        Uint128::from(1000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    RELAYER_FEE
+        .save(deps.as_mut().storage, "cosmos", &Uint128::from(100u128))
+        .unwrap();
+    let deposit = |deps: Deps| -> Uint128 {
+        from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::RelayerFeeDeposit {
+                    address: "sender".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    // what the cw20 contract executes on a Send from the sender: it never attaches native coins
+    let send = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "sender".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_json_binary(&TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        })
+        .unwrap(),
+    });
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &coins(150, "uatom")),
+        ExecuteMsg::DepositRelayerFee {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Payment(PaymentError::MissingDenom("orai".to_string()))
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &coins(150, "orai")),
+        ExecuteMsg::DepositRelayerFee {},
+    )
+    .unwrap();
+    assert_eq!(deposit(deps.as_ref()), Uint128::from(150u128));
+
+    // the relayer fee is paid from the deposit, the whole cw20 amount is transferred
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(cw20_addr, &[]),
+        send.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(BankMsg::Send {
+            to_address: "gov".to_string(),
+            amount: coins(100, "orai"),
+        })
+    );
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(attribute("relayer_fee"), "100");
+    assert_eq!(attribute("relayer_fee_price_source"), "orai_payment");
+    assert_eq!(attribute("relayer_fee_refund"), "0");
+    assert_eq!(attribute("amount"), "100");
+    assert_eq!(deposit(deps.as_ref()), Uint128::from(50u128));
+
+    // the deposit left does not cover the next relayer fee, which is deducted from the cw20 amount instead
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateRelayerFeePricing {
+            asset_info: AssetInfo::Token {
+                contract_addr: Addr::unchecked(cw20_addr),
+            },
+            pricing: Some(RelayerFeePricing {
+                source: RelayerFeePriceSource::StaticPrice {
+                    price: Decimal::percent(40),
+                },
+                on_failure: PricingFailure::Reject,
+            }),
+        },
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info(cw20_addr, &[]), send).unwrap();
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(attribute("relayer_fee"), "40");
+    assert_eq!(attribute("relayer_fee_price_source"), "static_price");
+    assert_eq!(attribute("amount"), "60");
+    assert_eq!(deposit(deps.as_ref()), Uint128::from(50u128));
+
+    let withdraw = |deps: DepsMut, amount: Option<u128>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("sender", &[]),
+            ExecuteMsg::WithdrawRelayerFee {
+                amount: amount.map(Uint128::from),
+            },
+        )
+    };
+    assert_eq!(
+        withdraw(deps.as_mut(), Some(80)).unwrap_err(),
+        ContractError::InsufficientRelayerFeeDeposit {
+            amount: Uint128::from(80u128),
+            deposit: Uint128::from(50u128),
+        }
+    );
+    let res = withdraw(deps.as_mut(), None).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "sender".to_string(),
+            amount: coins(50, "orai"),
+        })]
+    );
+    assert_eq!(deposit(deps.as_ref()), Uint128::zero());
+    assert_eq!(
+        withdraw(deps.as_mut(), None).unwrap_err(),
+        ContractError::NoFunds {}
+    );
+}
+
+#[test]
+fn test_fee_shares() {
+    let mut deps = setup(&[], &[]);
+    let shares = vec![
+        FeeShare {
+            recipient: FeeRecipient::Address("treasury".to_string()),
+            weight: 70,
+        },
+        FeeShare {
+            recipient: FeeRecipient::Address("staking".to_string()),
+            weight: 20,
+        },
+        FeeShare {
+            recipient: FeeRecipient::Referral,
+            weight: 10,
+        },
+    ];
+    let update_shares = |shares: Vec<FeeShare>| ExecuteMsg::UpdateConfig {
+        admin: None,
+        default_timeout: None,
+        default_gas_limit: None,
+        swap_router_contract: None,
+        token_fee: None,
+        relayer_fee: None,
+        channel_relayer_fee: None,
+        fee_receiver: None,
+        relayer_fee_receiver: None,
+        converter_contract: None,
+        osor_entrypoint_contract: None,
+        token_factory_addr: None,
+        token_fee_shares: Some(shares),
+        relayer_fee_shares: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_shares(vec![FeeShare {
+            recipient: FeeRecipient::Referral,
+            weight: 0,
+        }]),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidFeeShares {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        update_shares(shares.clone()),
+    )
+    .unwrap();
+
+    let fee = Amount::from_parts("orai".to_string(), Uint128::from(1001u128));
+    let send = |to_address: &str, amount: u128| -> CosmosMsg {
+        BankMsg::Send {
+            to_address: to_address.to_string(),
+            amount: coins(amount, "orai"),
+        }
+        .into()
+    };
+    assert_eq!(
+        build_fee_msgs(
+            deps.as_ref().api,
+            &fee,
+            &shares,
+            "receiver",
+            Some("orai1referral")
+        ),
+        vec![
+            send("treasury", 700),
+            send("staking", 200),
+            send("orai1referral", 100),
+            send("receiver", 1),
+        ]
+    );
+    // the referral share goes to the default receiver without a valid referral
+    for referral in [None, Some("Invalid Referral")] {
+        assert_eq!(
+            build_fee_msgs(deps.as_ref().api, &fee, &shares, "receiver", referral),
+            vec![
+                send("treasury", 700),
+                send("staking", 200),
+                send("receiver", 101),
+            ]
+        );
+    }
+    assert_eq!(
+        build_fee_msgs(deps.as_ref().api, &fee, &[], "receiver", None),
+        vec![send("receiver", 1001)]
+    );
+    assert_eq!(
+        build_fee_msgs(
+            deps.as_ref().api,
+            &Amount::from_parts("orai".to_string(), Uint128::zero()),
+            &shares,
+            "receiver",
+            None
+        ),
+        vec![]
+    );
+}
+
+#[test]
+fn test_transfer_referral() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    let ibc_denom = get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "uatom".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &ibc_denom,
+        Uint128::from(1000u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    RELAYER_FEE
+        .save(deps.as_mut().storage, "cosmos", &Uint128::from(100u128))
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateConfig {
+            admin: None,
+            default_timeout: None,
+            default_gas_limit: None,
+            swap_router_contract: None,
+            token_fee: None,
+            relayer_fee: None,
+            channel_relayer_fee: None,
+            fee_receiver: None,
+            relayer_fee_receiver: None,
+            converter_contract: None,
+            osor_entrypoint_contract: None,
+            token_factory_addr: None,
+            token_fee_shares: None,
+            relayer_fee_shares: Some(vec![
+                FeeShare {
+                    recipient: FeeRecipient::Address("treasury".to_string()),
+                    weight: 1,
+                },
+                FeeShare {
+                    recipient: FeeRecipient::Referral,
+                    weight: 1,
+                },
+            ]),
+        },
+    )
+    .unwrap();
+
+    let transfer = |memo: &str, referral: Option<&str>| {
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: Some(memo.to_string()),
+            referral: referral.map(|referral| referral.to_string()),
+        })
+    };
+    let funds = vec![Coin::new(100, "orai"), Coin::new(100, "uatom")];
+    let fee_msgs = |referral: &str| {
+        vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: coins(50, "orai"),
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: referral.to_string(),
+                amount: coins(50, "orai"),
+            }),
+        ]
+    };
+    let packet_memo = |res: &Response| {
+        res.messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) => {
+                    Some(from_json::<Ics20Packet>(data).unwrap().memo)
+                }
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // the referral earns its share and the memo is forwarded untouched to the remote chain
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &funds),
+        transfer("remote memo", Some("orai1referral")),
+    )
+    .unwrap();
+    assert_eq!(res.messages[0..2], fee_msgs("orai1referral"));
+    assert_eq!(packet_memo(&res), Some("remote memo".to_string()));
+
+    // a referral memo is only a remote memo, the referral share goes to the relayer fee receiver
+    let referral_memo = r#"{"referral":"orai1referral"}"#;
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &funds),
+        transfer(referral_memo, None),
+    )
+    .unwrap();
+    assert_eq!(res.messages[0..2], fee_msgs("gov"));
+    assert_eq!(packet_memo(&res), Some(referral_memo.to_string()));
+}
+
+#[test]
+fn test_fee_management() {
+    let mut deps = setup(&[], &[]);
+    let env = mock_env();
+    let token_fee = |token_denom: &str, bps: u16| TokenFee {
+        token_denom: token_denom.to_string(),
+        fee: FeeSchedule {
+            bps,
+            ..FeeSchedule::default()
+        },
+        direction: None,
+        channel_id: None,
+    };
+    let fees = |deps: Deps| -> FeesResponse {
+        from_json(&query(deps, mock_env(), QueryMsg::Fees {}).unwrap()).unwrap()
+    };
+    let update_fees = ExecuteMsg::UpdateFees {
+        updates: vec![
+            FeeUpdate::SetTokenFee(token_fee("uatom", 100)),
+            FeeUpdate::SetRelayerFee(RelayerFee {
+                prefix: "cosmos".to_string(),
+                fee: Uint128::from(10u128),
+            }),
+        ],
+        effective_at: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("foo", &[]),
+        update_fees.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("gov", &[]),
+        update_fees,
+    )
+    .unwrap();
+    TOKEN_FEE
+        .save(
+            deps.as_mut().storage,
+            "orai",
+            &Ratio {
+                nominator: 1,
+                denominator: 100,
+            },
+        )
+        .unwrap();
+    let current = fees(deps.as_ref());
+    assert_eq!(
+        current.token_fees,
+        vec![token_fee("orai", 100), token_fee("uatom", 100)]
+    );
+    assert_eq!(current.relayer_fees.len(), 1);
+
+    // no fee configured is a zero fee
+    let no_fee: FeeSchedule = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTransferTokenFee {
+                remote_token_denom: "foo".to_string(),
+                direction: None,
+                channel_id: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(no_fee, FeeSchedule::default());
+
+    // announce a replacement of every fee, and a removal cancelled later
+    for (updates_at, msg) in [
+        (
+            100,
+            ExecuteMsg::ReplaceFees {
+                token_fees: vec![token_fee("orai", 50)],
+                relayer_fees: vec![],
+                channel_relayer_fees: vec![],
+                effective_at: Some(env.block.time.plus_seconds(100)),
+            },
+        ),
+        (
+            50,
+            ExecuteMsg::UpdateFees {
+                updates: vec![FeeUpdate::RemoveTokenFee {
+                    token_denom: "orai".to_string(),
+                    direction: None,
+                    channel_id: None,
+                }],
+                effective_at: Some(env.block.time.plus_seconds(50)),
+            },
+        ),
+    ] {
+        let res = execute(deps.as_mut(), env.clone(), mock_info("gov", &[]), msg).unwrap();
+        assert_eq!(
+            res.attributes[2].value,
+            env.block
+                .time
+                .plus_seconds(updates_at)
+                .seconds()
+                .to_string()
+        );
+    }
+    let scheduled = fees(deps.as_ref()).scheduled;
+    assert_eq!(
+        scheduled.iter().map(|s| s.id).collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("gov", &[]),
+        ExecuteMsg::CancelScheduledFeeChange { id: 2 },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("gov", &[]),
+        ExecuteMsg::CancelScheduledFeeChange { id: 2 },
+    )
+    .unwrap_err();
+
+    // nothing changes before the effective time
+    apply_scheduled_fee_changes(deps.as_mut().storage, env.block.time.plus_seconds(99)).unwrap();
+    assert_eq!(fees(deps.as_ref()).token_fees.len(), 2);
+    apply_scheduled_fee_changes(deps.as_mut().storage, env.block.time.plus_seconds(100)).unwrap();
+    let current = fees(deps.as_ref());
+    assert_eq!(current.token_fees, vec![token_fee("orai", 50)]);
+    assert_eq!(current.relayer_fees, vec![]);
+    assert_eq!(current.scheduled, vec![]);
+    assert!(!TOKEN_FEE.has(deps.as_ref().storage, "orai"));
+
+    // removing the fee of every direction and channel
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateFees {
+            updates: vec![FeeUpdate::RemoveTokenFee {
+                token_denom: "orai".to_string(),
+                direction: None,
+                channel_id: None,
+            }],
+            effective_at: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(fees(deps.as_ref()).token_fees, vec![]);
+}
+
+#[test]
+fn test_fee_revenue() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "uatom".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    save_token_fee(
+        deps.as_mut().storage,
+        &TokenFee {
+            token_denom: "uatom".to_string(),
+            fee: FeeSchedule {
+                bps: 1000,
+                ..FeeSchedule::default()
+            },
+            direction: None,
+            channel_id: None,
+        },
+    )
+    .unwrap();
+    RELAYER_FEE
+        .save(deps.as_mut().storage, "cosmos", &Uint128::from(5u128))
+        .unwrap();
+
+    let update_duration = |deps: DepsMut, duration: Option<u64>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("gov", &[]),
+            ExecuteMsg::UpdateFeeEpochDuration { duration },
+        )
+    };
+    assert_eq!(
+        update_duration(deps.as_mut(), Some(0)).unwrap_err(),
+        ContractError::InvalidFeeEpochDuration {}
+    );
+    update_duration(deps.as_mut(), Some(3600)).unwrap();
+    let epoch: FeeEpochResponse =
+        from_json(&query(deps.as_ref(), mock_env(), QueryMsg::FeeEpoch {}).unwrap()).unwrap();
+    let current_epoch = mock_env().block.time.seconds() / 3600;
+    assert_eq!(epoch.current_epoch, Some(current_epoch));
+
+    // 10 uatom of token fee inbound, then 10 uatom of token fee and 5 orai of relayer fee outbound
+    ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet(
+                "channel-1",
+                local_channel,
+                Uint128::from(100u128),
+                "uatom".to_string(),
+                "orai1receiver".to_string(),
+            ),
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    // the balance is increased by a self-call of the contract
+    increase_channel_balance(
+        deps.as_mut().storage,
+        local_channel,
+        &get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom"),
+        Uint128::from(100u128),
+        mock_env().block.height,
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("sender", &[Coin::new(5, "orai"), Coin::new(100, "uatom")]),
+        ExecuteMsg::TransferToRemote(TransferBackMsg {
+            local_channel_id: local_channel.to_string(),
+            remote_address: "cosmos1603j3e4juddh7cuhfquxspl0p0nsun046us7n0".to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: None,
+            referral: None,
+        }),
+    )
+    .unwrap();
+
+    let fee_stats = |deps: Deps,
+                     fee_type: FeeType,
+                     epoch: Option<u64>,
+                     start_after: Option<(String, String)>|
+     -> Vec<FeeRevenue> {
+        let res: ListFeeStatsResponse = from_json(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::FeeStats {
+                    fee_type,
+                    epoch,
+                    start_after,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.stats
+    };
+    let revenue = |denom: &str, amount: u128| FeeRevenue {
+        denom: denom.to_string(),
+        channel_id: local_channel.to_string(),
+        amount: Uint128::from(amount),
+    };
+    for epoch in [None, Some(current_epoch)] {
+        assert_eq!(
+            fee_stats(deps.as_ref(), FeeType::Token, epoch, None),
+            vec![revenue("uatom", 20)]
+        );
+        assert_eq!(
+            fee_stats(deps.as_ref(), FeeType::Relayer, epoch, None),
+            vec![revenue("orai", 5)]
+        );
+    }
+    assert_eq!(
+        fee_stats(deps.as_ref(), FeeType::Token, Some(current_epoch - 1), None),
+        vec![]
+    );
+    assert_eq!(
+        fee_stats(
+            deps.as_ref(),
+            FeeType::Token,
+            None,
+            Some(("uatom".to_string(), local_channel.to_string()))
+        ),
+        vec![]
+    );
+}
+
+#[test]
+fn test_simulate_receive() {
+    let local_channel = "channel-0";
+    let mut deps = setup(&[local_channel], &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateMappingPair(UpdatePairMsg {
+            local_channel_id: local_channel.to_string(),
+            denom: "uatom".to_string(),
+            local_asset_info: AssetInfo::NativeToken {
+                denom: "ibc/uatom".to_string(),
+            },
+            remote_decimals: 6,
+            local_asset_info_decimals: 6,
+            is_mint_burn: None,
+            max_outstanding: None,
+            override_decimals: None,
+            min_amount: None,
+            max_amount: None,
+            status: None,
+            halted: None,
+        }),
+    )
+    .unwrap();
+    save_token_fee(
+        deps.as_mut().storage,
+        &TokenFee {
+            token_denom: "uatom".to_string(),
+            fee: FeeSchedule {
+                bps: 1000,
+                ..FeeSchedule::default()
+            },
+            direction: None,
+            channel_id: None,
+        },
+    )
+    .unwrap();
+
+    let simulate = |deps: Deps, env: Env, denom: &str| -> SimulateReceiveResponse {
+        from_json(
+            &query(
+                deps,
+                env,
+                QueryMsg::SimulateReceive {
+                    packet: Ics20Packet::new(
+                        Uint128::from(100u128),
+                        denom,
+                        "cosmos1sender",
+                        "orai1receiver",
+                        None,
+                    ),
+                    dest_channel: local_channel.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let res = simulate(deps.as_ref(), mock_env(), "uatom");
+    assert_eq!(res.error, None);
+    assert_eq!(
+        res.ack,
+        to_json_binary(&Ics20Ack::Result(b"1".into())).unwrap()
+    );
+    assert_eq!(res.fees.len(), 1);
+    assert_eq!(res.fees[0].token_fee.amount(), Uint128::from(10u128));
+    assert_eq!(res.fees[0].deducted_amount, Uint128::from(90u128));
+    // increase the channel balance, pay the token fee, then send the rest to the receiver
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "gov".to_string(),
+            amount: coins(10, "ibc/uatom"),
+        })
+    );
+    assert_eq!(
+        res.messages[2].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "orai1receiver".to_string(),
+            amount: coins(90, "ibc/uatom"),
+        })
+    );
+    // nothing is recorded
+    assert!(!CHANNEL_STATS.has(
+        deps.as_ref().storage,
+        (
+            local_channel,
+            &get_key_ics20_ibc_denom(CONTRACT_PORT, local_channel, "uatom")
+        )
+    ));
+
+    let res = simulate(deps.as_ref(), mock_env(), "uosmo");
+    let error = ContractError::NotOnMappingList {}.to_string();
+    assert_eq!(res.error, Some(error.clone()));
+    assert_eq!(res.ack, to_json_binary(&Ics20Ack::Error(error)).unwrap());
+    assert!(res.messages.is_empty());
+
+    // a scheduled fee change is simulated once it is due, even though no transfer applied it yet
+    let scheduled = |deps: Deps| -> usize {
+        from_json::<FeesResponse>(&query(deps, mock_env(), QueryMsg::Fees {}).unwrap())
+            .unwrap()
+            .scheduled
+            .len()
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gov", &[]),
+        ExecuteMsg::UpdateFees {
+            updates: vec![FeeUpdate::SetTokenFee(TokenFee {
+                token_denom: "uatom".to_string(),
+                fee: FeeSchedule {
+                    bps: 2000,
+                    ..FeeSchedule::default()
+                },
+                direction: None,
+                channel_id: None,
+            })],
+            effective_at: Some(mock_env().block.time.plus_seconds(100)),
+        },
+    )
+    .unwrap();
+    let res = simulate(deps.as_ref(), mock_env(), "uatom");
+    assert_eq!(res.fees[0].token_fee.amount(), Uint128::from(10u128));
+    let mut due_env = mock_env();
+    due_env.block.time = due_env.block.time.plus_seconds(100);
+    let simulated = simulate(deps.as_ref(), due_env.clone(), "uatom");
+    assert_eq!(simulated.error, None);
+    assert_eq!(simulated.fees[0].token_fee.amount(), Uint128::from(20u128));
+    assert_eq!(simulated.fees[0].deducted_amount, Uint128::from(80u128));
+    assert_eq!(scheduled(deps.as_ref()), 1);
+
+    // the receive applies the change and charges what was simulated
+    let res = ibc_packet_receive(
+        deps.as_mut(),
+        due_env,
+        IbcPacketReceiveMsg::new(
+            mock_receive_packet_remote_to_local(
+                local_channel,
+                100,
+                "uatom",
+                "orai1receiver",
+                Some("cosmos1sender"),
+            ),
+            Addr::unchecked("relayer"),
+        ),
+    )
+    .unwrap();
+    assert_eq!(res.acknowledgement, simulated.ack);
+    assert_eq!(res.messages[1..], simulated.messages[1..]);
+    assert_eq!(scheduled(deps.as_ref()), 0);
+}
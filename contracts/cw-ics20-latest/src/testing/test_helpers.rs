@@ -19,6 +19,10 @@ pub const CONNECTION_ID: &str = "connection-2";
 pub const WASM_BYTES: &[u8] = include_bytes!("../../artifacts/cw-ics20-latest.wasm");
 
 pub fn mock_channel(channel_id: &str) -> IbcChannel {
+    mock_channel_with_version(channel_id, ICS20_VERSION)
+}
+
+pub fn mock_channel_with_version(channel_id: &str, version: &str) -> IbcChannel {
     IbcChannel::new(
         IbcEndpoint {
             port_id: CONTRACT_PORT.into(),
@@ -29,7 +33,7 @@ pub fn mock_channel(channel_id: &str) -> IbcChannel {
             channel_id: format!("{}5", channel_id),
         },
         ICS20_ORDERING,
-        ICS20_VERSION,
+        version,
         CONNECTION_ID,
     )
 }
@@ -42,15 +46,21 @@ pub fn mock_channel_info(channel_id: &str) -> ChannelInfo {
             channel_id: format!("{}5", channel_id),
         },
         connection_id: CONNECTION_ID.into(),
+        version: Some(ICS20_VERSION.to_string()),
+        metadata: None,
     }
 }
 
 // we simulate instantiate and ack here
-pub fn add_channel(mut deps: DepsMut, channel_id: &str) {
-    let channel = mock_channel(channel_id);
+pub fn add_channel(deps: DepsMut, channel_id: &str) {
+    add_channel_with_version(deps, channel_id, ICS20_VERSION)
+}
+
+pub fn add_channel_with_version(mut deps: DepsMut, channel_id: &str, version: &str) {
+    let channel = mock_channel_with_version(channel_id, version);
     let open_msg = IbcChannelOpenMsg::new_init(channel.clone());
     ibc_channel_open(deps.branch(), mock_env(), open_msg).unwrap();
-    let connect_msg = IbcChannelConnectMsg::new_ack(channel, ICS20_VERSION);
+    let connect_msg = IbcChannelConnectMsg::new_ack(channel, version);
     ibc_channel_connect(deps.branch(), mock_env(), connect_msg).unwrap();
 }
 
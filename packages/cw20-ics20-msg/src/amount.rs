@@ -179,6 +179,23 @@ pub fn convert_local_to_remote(
     )
 }
 
+/// Returns whether amounts can be converted between the given decimals: both powers of ten fit in a u128
+/// and the conversion ratio is a non-zero `Decimal` in both directions
+pub fn decimals_convertible(remote_decimals: u8, local_decimals: u8) -> bool {
+    let (Some(remote), Some(local)) = (
+        10u128.checked_pow(remote_decimals as u32),
+        10u128.checked_pow(local_decimals as u32),
+    ) else {
+        return false;
+    };
+    [(local, remote), (remote, local)]
+        .into_iter()
+        .all(|(numerator, denominator)| {
+            Decimal::checked_from_ratio(numerator, denominator)
+                .map_or(false, |ratio| !ratio.is_zero())
+        })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -194,6 +211,20 @@ mod tests {
         assert_eq!(new_amount, Uint128::from(1u128))
     }
 
+    #[test]
+    pub fn test_decimals_convertible() {
+        assert!(decimals_convertible(6, 6));
+        assert!(decimals_convertible(18, 6));
+        assert!(decimals_convertible(6, 24));
+        assert!(decimals_convertible(20, 38));
+        // 10^39 overflows a u128
+        assert!(!decimals_convertible(39, 39));
+        assert!(!decimals_convertible(6, 255));
+        // the ratio rounds to zero one way and overflows a Decimal the other way
+        assert!(!decimals_convertible(0, 19));
+        assert!(!decimals_convertible(25, 6));
+    }
+
     #[test]
     pub fn test_into_asset_info() {
         let deps = mock_dependencies();
@@ -1,8 +1,15 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Binary, IbcEndpoint, SubMsg, Uint128};
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    attr, from_json, Attribute, Binary, HexBinary, IbcEndpoint, StdError, StdResult, SubMsg,
+    Uint128,
+};
 use oraiswap::asset::AssetInfo;
+use sha2::{Digest, Sha256};
 
 use crate::amount::Amount;
+use crate::state::MappingStatus;
 
 /// This is the message we accept via Receive
 #[cw_serde]
@@ -16,6 +23,8 @@ pub struct TransferBackMsg {
     pub timeout: Option<u64>,
     /// metadata of the transfer to suit the new fungible token transfer
     pub memo: Option<String>,
+    /// the address earning the referral share of the transfer fees. Not forwarded to the remote chain
+    pub referral: Option<String>,
 }
 
 /// This is the message we accept via Receive
@@ -43,6 +52,44 @@ pub struct FeeData {
     pub deducted_amount: Uint128,
     pub token_fee: Amount,
     pub relayer_fee: Amount,
+    /// token fee waived by the fee exemption of the sender
+    pub token_fee_discount: Uint128,
+    /// relayer fee waived by the fee exemption of the sender
+    pub relayer_fee_discount: Uint128,
+    /// source the relayer fee was priced with, None if no relayer fee applies
+    pub relayer_fee_price_source: Option<String>,
+}
+
+impl FeeData {
+    /// fee attributes emitted by every transfer
+    pub fn attributes(&self) -> Vec<Attribute> {
+        vec![
+            attr("token_fee", self.token_fee.amount()),
+            attr("relayer_fee", self.relayer_fee.amount()),
+            attr("token_fee_discount", self.token_fee_discount),
+            attr("relayer_fee_discount", self.relayer_fee_discount),
+            attr(
+                "relayer_fee_price_source",
+                self.relayer_fee_price_source.as_deref().unwrap_or("none"),
+            ),
+        ]
+    }
+}
+
+/// memo of an incoming packet naming the referral of the transfer, who earns the referral share of its fees.
+/// Eg: {"referral":"orai1..."}
+#[cw_serde]
+pub struct ReferralMemo {
+    pub referral: String,
+}
+
+impl ReferralMemo {
+    /// returns the referral of the memo, None if the memo is not a referral memo
+    pub fn parse(memo: &str) -> Option<String> {
+        from_json::<ReferralMemo>(memo.as_bytes())
+            .ok()
+            .map(|memo| memo.referral)
+    }
 }
 
 #[cw_serde]
@@ -62,6 +109,288 @@ pub struct UpdatePairMsg {
     pub remote_decimals: u8,
     pub local_asset_info_decimals: u8,
     pub is_mint_burn: Option<bool>,
+    /// cap of the outstanding channel balance, in remote decimals
+    pub max_outstanding: Option<Uint128>,
+    /// skips checking local_asset_info_decimals against the decimals of the asset on chain
+    pub override_decimals: Option<bool>,
+    /// bounds of the amount of a transfer in either direction, in remote decimals
+    pub min_amount: Option<Uint128>,
+    pub max_amount: Option<Uint128>,
+    /// status of the pair. None keeps the current status, Active for a new pair
+    pub status: Option<MappingStatus>,
+    /// halts or resumes the pair. None keeps it as is
+    pub halted: Option<bool>,
+}
+
+/// The format for sending an ics20-2 packet.
+/// Proto defined here: https://github.com/cosmos/ibc-go/blob/v9.0.0/proto/ibc/applications/transfer/v2/packet.proto#L27-L41
+/// ibc-go v9 sends it protobuf encoded, see decode and encode. ics20-2 was removed in ibc-go v10
+#[cw_serde]
+pub struct Ics20PacketV2 {
+    /// the tokens to be transferred
+    pub tokens: Vec<Token>,
+    /// the sender address
+    pub sender: String,
+    /// the recipient address on the destination chain
+    pub receiver: String,
+    /// optional memo
+    #[serde(default)]
+    pub memo: String,
+    /// optional forwarding information
+    pub forwarding: Option<ForwardingPacketData>,
+}
+
+impl Ics20PacketV2 {
+    /// decodes the protobuf encoded FungibleTokenPacketDataV2
+    pub fn decode(data: &[u8]) -> StdResult<Self> {
+        let packet = <proto::FungibleTokenPacketDataV2 as prost::Message>::decode(data)
+            .map_err(|err| StdError::parse_err("FungibleTokenPacketDataV2", err))?;
+        let tokens = packet
+            .tokens
+            .into_iter()
+            .map(|token| {
+                let denom = token
+                    .denom
+                    .ok_or_else(|| StdError::parse_err("Token", "missing denom"))?;
+                Ok(Token {
+                    denom: Denom {
+                        base: denom.base,
+                        trace: denom.trace.into_iter().map(Hop::from).collect(),
+                    },
+                    amount: Uint128::from_str(&token.amount)?,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(Ics20PacketV2 {
+            tokens,
+            sender: packet.sender,
+            receiver: packet.receiver,
+            memo: packet.memo,
+            forwarding: packet.forwarding.map(|forwarding| ForwardingPacketData {
+                destination_memo: forwarding.destination_memo,
+                hops: forwarding.hops.into_iter().map(Hop::from).collect(),
+            }),
+        })
+    }
+
+    /// encodes the packet as the protobuf FungibleTokenPacketDataV2
+    pub fn encode(&self) -> Binary {
+        let packet = proto::FungibleTokenPacketDataV2 {
+            tokens: self
+                .tokens
+                .iter()
+                .map(|token| proto::Token {
+                    denom: Some(proto::Denom {
+                        base: token.denom.base.clone(),
+                        trace: token.denom.trace.iter().map(proto::Hop::from).collect(),
+                    }),
+                    amount: token.amount.to_string(),
+                })
+                .collect(),
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            memo: self.memo.clone(),
+            forwarding: self
+                .forwarding
+                .as_ref()
+                .map(|forwarding| proto::ForwardingPacketData {
+                    destination_memo: forwarding.destination_memo.clone(),
+                    hops: forwarding.hops.iter().map(proto::Hop::from).collect(),
+                }),
+        };
+        prost::Message::encode_to_vec(&packet).into()
+    }
+}
+
+/// protobuf messages of ibc.applications.transfer.v2 in ibc-go v9
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FungibleTokenPacketDataV2 {
+        #[prost(message, repeated, tag = "1")]
+        pub tokens: Vec<Token>,
+        #[prost(string, tag = "2")]
+        pub sender: String,
+        #[prost(string, tag = "3")]
+        pub receiver: String,
+        #[prost(string, tag = "4")]
+        pub memo: String,
+        #[prost(message, optional, tag = "5")]
+        pub forwarding: Option<ForwardingPacketData>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Token {
+        #[prost(message, optional, tag = "1")]
+        pub denom: Option<Denom>,
+        #[prost(string, tag = "2")]
+        pub amount: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Denom {
+        #[prost(string, tag = "1")]
+        pub base: String,
+        #[prost(message, repeated, tag = "3")]
+        pub trace: Vec<Hop>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Hop {
+        #[prost(string, tag = "1")]
+        pub port_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ForwardingPacketData {
+        #[prost(string, tag = "1")]
+        pub destination_memo: String,
+        #[prost(message, repeated, tag = "2")]
+        pub hops: Vec<Hop>,
+    }
+
+    impl From<&super::Hop> for Hop {
+        fn from(hop: &super::Hop) -> Self {
+            Hop {
+                port_id: hop.port_id.clone(),
+                channel_id: hop.channel_id.clone(),
+            }
+        }
+    }
+}
+
+impl From<proto::Hop> for Hop {
+    fn from(hop: proto::Hop) -> Self {
+        Hop {
+            port_id: hop.port_id,
+            channel_id: hop.channel_id,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct Token {
+    /// the token denomination, including its trace
+    pub denom: Denom,
+    /// amount of tokens to transfer is encoded as a string
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct Denom {
+    /// the base denomination, eg: uatom
+    pub base: String,
+    /// the hops the token went through, the most recent hop first
+    #[serde(default)]
+    pub trace: Vec<Hop>,
+}
+
+impl Denom {
+    /// builds the ics20-1 style path of the denom, eg: transfer/channel-0/uatom
+    pub fn path(&self) -> String {
+        self.trace
+            .iter()
+            .map(|hop| format!("{}/{}/", hop.port_id, hop.channel_id))
+            .chain(std::iter::once(self.base.clone()))
+            .collect()
+    }
+}
+
+/// trace of an ics20-1 denom, compatible with the DenomTrace of ibc-go
+#[cw_serde]
+pub struct DenomTrace {
+    /// the port/channel pairs the token went through, the most recent hop first. Eg: transfer/channel-1/transfer/channel-5
+    pub path: String,
+    /// the denom on the chain the token originates from. Eg: uatom
+    pub base_denom: String,
+}
+
+impl DenomTrace {
+    /// parses a full denom such as transfer/channel-1/transfer/channel-5/uatom.
+    /// Leading port/channel pairs form the path, the remaining segments (which may contain '/') form the base denom
+    pub fn parse(full_denom: &str) -> Self {
+        let segments: Vec<&str> = full_denom.split('/').collect();
+        let mut hops = 0;
+        // always keep at least one segment for the base denom
+        while hops * 2 + 2 < segments.len() && is_channel_id(segments[hops * 2 + 1]) {
+            hops += 1;
+        }
+        DenomTrace {
+            path: segments[..hops * 2].join("/"),
+            base_denom: segments[hops * 2..].join("/"),
+        }
+    }
+
+    /// the full denom, path and base denom joined by '/'
+    pub fn full_path(&self) -> String {
+        if self.path.is_empty() {
+            return self.base_denom.clone();
+        }
+        format!("{}/{}", self.path, self.base_denom)
+    }
+
+    /// the hops of the path, the most recent hop first
+    pub fn hops(&self) -> Vec<Hop> {
+        let segments: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        segments
+            .chunks_exact(2)
+            .map(|hop| Hop {
+                port_id: hop[0].to_string(),
+                channel_id: hop[1].to_string(),
+            })
+            .collect()
+    }
+
+    /// uppercase hex of the sha256 of the full path, as computed by ibc-go
+    pub fn hash(&self) -> String {
+        HexBinary::from(Sha256::digest(self.full_path().as_bytes()).as_slice())
+            .to_hex()
+            .to_uppercase()
+    }
+
+    /// the denom of the token on the local chain: ibc/{hash} for traced denoms, the base denom for native ones
+    pub fn ibc_denom(&self) -> String {
+        if self.path.is_empty() {
+            return self.base_denom.clone();
+        }
+        format!("ibc/{}", self.hash())
+    }
+}
+
+impl From<&Denom> for DenomTrace {
+    fn from(denom: &Denom) -> Self {
+        DenomTrace {
+            path: denom
+                .trace
+                .iter()
+                .map(|hop| format!("{}/{}", hop.port_id, hop.channel_id))
+                .collect::<Vec<String>>()
+                .join("/"),
+            base_denom: denom.base.clone(),
+        }
+    }
+}
+
+fn is_channel_id(id: &str) -> bool {
+    id.strip_prefix("channel-")
+        .map(|seq| !seq.is_empty() && seq.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or_default()
+}
+
+#[cw_serde]
+pub struct Hop {
+    pub port_id: String,
+    pub channel_id: String,
+}
+
+#[cw_serde]
+pub struct ForwardingPacketData {
+    /// the memo to use on the final hop
+    #[serde(default)]
+    pub destination_memo: String,
+    /// the hops the tokens must be forwarded through
+    pub hops: Vec<Hop>,
 }
 
 #[cw_serde]
@@ -70,3 +399,162 @@ pub struct DeletePairMsg {
     /// native denom of the remote chain. Eg: orai
     pub denom: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Uint128;
+
+    use crate::msg::{
+        Denom, DenomTrace, ForwardingPacketData, Hop, Ics20PacketV2, ReferralMemo, Token,
+    };
+
+    #[test]
+    fn test_ics20_packet_v2_protobuf() {
+        let packet = Ics20PacketV2 {
+            tokens: vec![Token {
+                denom: Denom {
+                    base: "uatom".to_string(),
+                    trace: vec![Hop {
+                        port_id: "transfer".to_string(),
+                        channel_id: "channel-0".to_string(),
+                    }],
+                },
+                amount: Uint128::from(100u128),
+            }],
+            sender: "a".to_string(),
+            receiver: "b".to_string(),
+            memo: "".to_string(),
+            forwarding: None,
+        };
+        let hop = [&[0x0a, 0x08][..], b"transfer", &[0x12, 0x09], b"channel-0"].concat();
+        let denom = [&[0x0a, 0x05][..], b"uatom", &[0x1a, hop.len() as u8], &hop].concat();
+        let token = [
+            &[0x0a, denom.len() as u8][..],
+            &denom,
+            &[0x12, 0x03],
+            b"100",
+        ]
+        .concat();
+        let expected = [
+            &[0x0a, token.len() as u8][..],
+            &token,
+            &[0x12, 0x01],
+            b"a",
+            &[0x1a, 0x01],
+            b"b",
+        ]
+        .concat();
+        assert_eq!(packet.encode().as_slice(), expected.as_slice());
+        assert_eq!(Ics20PacketV2::decode(&expected).unwrap(), packet);
+
+        let packet = Ics20PacketV2 {
+            memo: "memo".to_string(),
+            forwarding: Some(ForwardingPacketData {
+                destination_memo: "destination".to_string(),
+                hops: vec![Hop {
+                    port_id: "transfer".to_string(),
+                    channel_id: "channel-1".to_string(),
+                }],
+            }),
+            ..packet
+        };
+        assert_eq!(Ics20PacketV2::decode(&packet.encode()).unwrap(), packet);
+    }
+
+    #[test]
+    fn test_parse_referral_memo() {
+        assert_eq!(
+            ReferralMemo::parse(r#"{"referral":"orai1referral"}"#),
+            Some("orai1referral".to_string())
+        );
+        assert_eq!(ReferralMemo::parse("orai1referral"), None);
+        assert_eq!(ReferralMemo::parse(r#"{"referral":"a","foo":"b"}"#), None);
+        assert_eq!(ReferralMemo::parse(""), None);
+    }
+
+    #[test]
+    fn test_denom_path() {
+        let mut denom = Denom {
+            base: "uatom".to_string(),
+            trace: vec![],
+        };
+        assert_eq!(denom.path(), "uatom".to_string());
+
+        denom.trace = vec![
+            Hop {
+                port_id: "transfer".to_string(),
+                channel_id: "channel-1".to_string(),
+            },
+            Hop {
+                port_id: "transfer".to_string(),
+                channel_id: "channel-5".to_string(),
+            },
+        ];
+        assert_eq!(
+            denom.path(),
+            "transfer/channel-1/transfer/channel-5/uatom".to_string()
+        );
+        assert_eq!(
+            DenomTrace::from(&denom).full_path(),
+            "transfer/channel-1/transfer/channel-5/uatom".to_string()
+        );
+    }
+
+    #[test]
+    fn test_denom_trace() {
+        let trace = DenomTrace::parse("transfer/channel-1/transfer/channel-5/uatom");
+        assert_eq!(
+            trace,
+            DenomTrace {
+                path: "transfer/channel-1/transfer/channel-5".to_string(),
+                base_denom: "uatom".to_string(),
+            }
+        );
+        assert_eq!(
+            trace.full_path(),
+            "transfer/channel-1/transfer/channel-5/uatom".to_string()
+        );
+        assert_eq!(
+            trace.hops(),
+            vec![
+                Hop {
+                    port_id: "transfer".to_string(),
+                    channel_id: "channel-1".to_string(),
+                },
+                Hop {
+                    port_id: "transfer".to_string(),
+                    channel_id: "channel-5".to_string(),
+                },
+            ]
+        );
+
+        // base denoms may contain '/'
+        let trace = DenomTrace::parse("wasm.orai1contract/channel-29/gamm/pool/1");
+        assert_eq!(trace.path, "wasm.orai1contract/channel-29".to_string());
+        assert_eq!(trace.base_denom, "gamm/pool/1".to_string());
+        let trace = DenomTrace::parse("factory/orai1creator/ton");
+        assert_eq!(trace.path, "".to_string());
+        assert_eq!(trace.base_denom, "factory/orai1creator/ton".to_string());
+        // a channel id is never taken as the base denom
+        let trace = DenomTrace::parse("transfer/channel-0");
+        assert_eq!(trace.path, "".to_string());
+        assert_eq!(trace.base_denom, "transfer/channel-0".to_string());
+
+        // native denoms are not hashed
+        let trace = DenomTrace::parse("uatom");
+        assert_eq!(trace.path, "".to_string());
+        assert_eq!(trace.hops(), vec![]);
+        assert_eq!(trace.ibc_denom(), "uatom".to_string());
+
+        // ATOM on Osmosis
+        let trace = DenomTrace::parse("transfer/channel-0/uatom");
+        assert_eq!(
+            trace.hash(),
+            "27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string()
+        );
+        assert_eq!(
+            trace.ibc_denom(),
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string()
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{IbcEndpoint, Uint128};
+use cosmwasm_std::{IbcEndpoint, StdError, StdResult, Uint128};
 use oraiswap::asset::{Asset, AssetInfo};
 
 #[cw_serde]
@@ -10,6 +10,37 @@ pub struct ChannelInfo {
     pub counterparty_endpoint: IbcEndpoint,
     /// the connection this exists on (you can use to query client/consensus info)
     pub connection_id: String,
+    /// the ics20 version negotiated on this channel. Channels connected before ics20-2 was supported speak ics20-1
+    pub version: Option<String>,
+    /// admin-set information about the counterparty chain
+    pub metadata: Option<ChannelMetadata>,
+}
+
+#[cw_serde]
+pub struct ChannelMetadata {
+    /// chain id of the counterparty chain. Eg: cosmoshub-4
+    pub chain_id: String,
+    /// bech32 prefix of the counterparty chain addresses. Eg: cosmos
+    pub bech32_prefix: String,
+    /// true if the counterparty is OraiBridge, which forwards the tokens to EVM chains
+    pub is_evm_through_oraibridge: bool,
+    /// EVM prefixes reachable through OraiBridge. Eg: eth-mainnet, trontrx-mainnet
+    pub evm_prefixes: Vec<String>,
+}
+
+impl ChannelMetadata {
+    /// returns where a transfer of the remote denom ends up: the EVM prefix of the denom for OraiBridge channels,
+    /// or the bech32 prefix of the counterparty chain otherwise
+    pub fn destination(&self, remote_denom: &str) -> String {
+        if self.is_evm_through_oraibridge {
+            if let Some((evm_prefix, _)) = remote_denom.split_once("0x") {
+                if self.evm_prefixes.iter().any(|prefix| prefix == evm_prefix) {
+                    return evm_prefix.to_string();
+                }
+            }
+        }
+        self.bech32_prefix.clone()
+    }
 }
 
 #[cw_serde]
@@ -20,7 +51,81 @@ pub struct AllowInfo {
 #[cw_serde]
 pub struct TokenFee {
     pub token_denom: String,
-    pub ratio: Ratio,
+    pub fee: FeeSchedule,
+    /// direction of the transfers charged. None applies to both directions
+    #[serde(default)]
+    pub direction: Option<FeeDirection>,
+    /// local channel of the transfers charged. None applies to every channel
+    #[serde(default)]
+    pub channel_id: Option<String>,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum FeeDirection {
+    /// transfers received from the remote chain
+    Inbound,
+    /// transfers sent back to the remote chain
+    Outbound,
+}
+
+impl FeeDirection {
+    /// key of the direction in the fee storage
+    pub fn key(&self) -> &'static str {
+        match self {
+            FeeDirection::Inbound => "inbound",
+            FeeDirection::Outbound => "outbound",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "inbound" => Some(FeeDirection::Inbound),
+            "outbound" => Some(FeeDirection::Outbound),
+            _ => None,
+        }
+    }
+}
+
+/// token fee charged on a transfer: a percentage of the amount plus a flat fee, clamped between min and max
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeSchedule {
+    /// percentage of the amount in basis points. 1 bps = 0.01%
+    pub bps: u16,
+    /// flat fee added to the percentage, in local decimals
+    #[serde(default)]
+    pub flat: Uint128,
+    /// lower bound of the fee, in local decimals
+    #[serde(default)]
+    pub min: Uint128,
+    /// upper bound of the fee, in local decimals. None means no cap
+    #[serde(default)]
+    pub max: Option<Uint128>,
+}
+
+/// token fees used to be bare ratios. Only a whole number of basis points, up to 100%, converts without changing the fee
+impl TryFrom<Ratio> for FeeSchedule {
+    type Error = StdError;
+
+    fn try_from(ratio: Ratio) -> StdResult<Self> {
+        // a zero denominator never charged any fee
+        if ratio.denominator == 0 {
+            return Ok(FeeSchedule::default());
+        }
+        let scaled = u128::from(ratio.nominator) * u128::from(MAX_BPS);
+        let denominator = u128::from(ratio.denominator);
+        if scaled % denominator != 0 || scaled / denominator > u128::from(MAX_BPS) {
+            return Err(StdError::generic_err(format!(
+                "token fee ratio {}/{} is not a whole number of basis points up to 100%",
+                ratio.nominator, ratio.denominator
+            )));
+        }
+        Ok(FeeSchedule {
+            bps: (scaled / denominator) as u16,
+            ..FeeSchedule::default()
+        })
+    }
 }
 
 #[cw_serde]
@@ -29,6 +134,41 @@ pub struct RelayerFee {
     pub fee: Uint128,
 }
 
+#[cw_serde]
+pub struct ChannelRelayerFee {
+    pub channel_id: String,
+    /// destination of the transfer, see ChannelMetadata::destination
+    pub destination: String,
+    pub fee: Uint128,
+}
+
+/// weighted share of the fees of a fee type
+#[cw_serde]
+pub struct FeeShare {
+    pub recipient: FeeRecipient,
+    /// weight of the share relative to the other shares of the fee type
+    pub weight: u64,
+}
+
+#[cw_serde]
+pub enum FeeRecipient {
+    Address(String),
+    /// the referral of the transfer: the `referral` of an outgoing transfer or the referral memo of an incoming
+    /// packet. Without one, the share goes to the default fee receiver
+    Referral,
+}
+
+/// discounts on the fees of a partner address, in basis points. MAX_BPS waives the fee
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeExemption {
+    pub token_fee_discount_bps: u16,
+    pub relayer_fee_discount_bps: u16,
+}
+
+/// 100% in basis points
+pub const MAX_BPS: u16 = 10000;
+
 #[cw_serde]
 pub struct Ratio {
     pub nominator: u64,
@@ -43,6 +183,41 @@ pub struct MappingMetadata {
     pub asset_info_decimals: u8,
     #[serde(default)]
     pub is_mint_burn: bool,
+    /// cap of the outstanding channel balance of the mapping, in remote decimals. None means no cap
+    #[serde(default)]
+    pub max_outstanding: Option<Uint128>,
+    #[serde(default)]
+    pub status: MappingStatus,
+    /// bounds of the amount of a transfer in either direction, in remote decimals. None means no bound
+    #[serde(default)]
+    pub min_amount: Option<Uint128>,
+    #[serde(default)]
+    pub max_amount: Option<Uint128>,
+}
+
+/// lifecycle of a mapping pair. Refunds of the packets already sent are processed whatever the status
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum MappingStatus {
+    #[default]
+    Active,
+    /// only transfers received from the remote chain are accepted
+    InboundOnly,
+    /// only transfers sent back to the remote chain are accepted, so that holders can wind down their balances
+    OutboundOnly,
+    /// no new transfers are accepted. The mapping is kept for refunds and balance tracking until it is deleted
+    Deprecated,
+}
+
+impl MappingStatus {
+    pub fn allows(&self, direction: FeeDirection) -> bool {
+        matches!(
+            (self, direction),
+            (MappingStatus::Active, _)
+                | (MappingStatus::InboundOnly, FeeDirection::Inbound)
+                | (MappingStatus::OutboundOnly, FeeDirection::Outbound)
+        )
+    }
 }
 
 #[cw_serde]
@@ -58,3 +233,74 @@ pub struct ConvertReplyArgs {
     pub local_receiver: String,
     pub asset: Asset,
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Uint128;
+
+    use crate::state::{ChannelMetadata, FeeDirection, FeeSchedule, MappingStatus, Ratio};
+
+    #[test]
+    fn test_fee_schedule_from_ratio() {
+        let to_bps = |nominator: u64, denominator: u64| {
+            FeeSchedule::try_from(Ratio {
+                nominator,
+                denominator,
+            })
+        };
+        assert_eq!(
+            to_bps(1, 10).unwrap(),
+            FeeSchedule {
+                bps: 1000,
+                flat: Uint128::zero(),
+                min: Uint128::zero(),
+                max: None,
+            }
+        );
+        assert_eq!(to_bps(1, 1000).unwrap().bps, 10);
+        assert_eq!(to_bps(3, 20000).unwrap().bps, 15);
+        assert_eq!(to_bps(1, 0).unwrap().bps, 0);
+        // finer than one basis point
+        to_bps(1, 30000).unwrap_err();
+        to_bps(1, 100000).unwrap_err();
+        // more than 100%
+        to_bps(3, 2).unwrap_err();
+    }
+
+    #[test]
+    fn test_mapping_status_allows() {
+        assert!(MappingStatus::Active.allows(FeeDirection::Inbound));
+        assert!(MappingStatus::Active.allows(FeeDirection::Outbound));
+        assert!(MappingStatus::InboundOnly.allows(FeeDirection::Inbound));
+        assert!(!MappingStatus::InboundOnly.allows(FeeDirection::Outbound));
+        assert!(!MappingStatus::OutboundOnly.allows(FeeDirection::Inbound));
+        assert!(MappingStatus::OutboundOnly.allows(FeeDirection::Outbound));
+        assert!(!MappingStatus::Deprecated.allows(FeeDirection::Inbound));
+        assert!(!MappingStatus::Deprecated.allows(FeeDirection::Outbound));
+    }
+
+    #[test]
+    fn test_channel_metadata_destination() {
+        let mut metadata = ChannelMetadata {
+            chain_id: "oraibridge-subnet-2".to_string(),
+            bech32_prefix: "oraib".to_string(),
+            is_evm_through_oraibridge: true,
+            evm_prefixes: vec!["eth-mainnet".to_string(), "trontrx-mainnet".to_string()],
+        };
+        assert_eq!(
+            metadata.destination("eth-mainnet0xdAC17F958D2ee523a2206206994597C13D831ec7"),
+            "eth-mainnet".to_string()
+        );
+        // unknown evm prefix falls back to the bech32 prefix
+        assert_eq!(
+            metadata.destination("foo0xdAC17F958D2ee523a2206206994597C13D831ec7"),
+            "oraib".to_string()
+        );
+
+        metadata.is_evm_through_oraibridge = false;
+        assert_eq!(
+            metadata.destination("eth-mainnet0xdAC17F958D2ee523a2206206994597C13D831ec7"),
+            "oraib".to_string()
+        );
+    }
+}